@@ -0,0 +1,380 @@
+use ciborium::Value as Cbor;
+use serde_json::{Map, Number, Value};
+
+use super::error::ApiError;
+use super::{kind, ItemTypes, JsonMessage};
+
+// Compact CBOR form of the API messages for Thread links, enabled with the "cbor" feature.
+//
+// The message is converted from its JSON form, field names in KEYS and the kinds in KINDS
+// are replaced by their index. Anything else is kept as text, so payloads of newer versions
+// survive the round trip, and decoding goes through the same parsing and migrations as JSON.
+
+// Appending is fine, never reorder or remove entries. The first 24 encode as one byte.
+const KEYS: &[&str] = &[
+    // 0
+    "apiVersion",
+    "data",
+    "error",
+    "warnings",
+    "meta",
+    "kind",
+    "id",
+    "currentItemCount",
+    "items",
+    "updated",
+    // 10
+    "type",
+    "channel",
+    "value1",
+    "value2",
+    "value",
+    "typeId",
+    "code",
+    "message",
+    "errors",
+    "reason",
+    // 20
+    "location",
+    "locationType",
+    "domain",
+    "name",
+    "description",
+    "devSn",
+    "devType",
+    "status",
+    "firmware",
+    "infobits",
+    // 30
+    "rssi",
+    "mLinkQuality",
+    "mAverageRssi",
+    "rxOnIdle",
+    "rloc16",
+    "euid64",
+    "networkName",
+    "tlv",
+    "settingTypeId",
+    "settingTypeName",
+    // 40
+    "settingTypeDescription",
+    "settingTypeUnit",
+    "settingTypeIcon",
+    "defaultValue",
+    "minValue",
+    "maxValue",
+    "deviceId",
+    "idx",
+    "startIndex",
+    "itemsPerPage",
+    // 50
+    "totalItems",
+    "pageIndex",
+    "totalPages",
+    "nextCursor",
+    "previousCursor",
+    "method",
+    "command",
+    "expires",
+    "lastActive",
+    "uptime",
+];
+
+const KINDS: &[&str] = &[
+    kind::SETTINGS,
+    kind::SETTING_TYPES,
+    kind::DEVICES_CONNECTED,
+    kind::OT_NET,
+    kind::MEASUREMENT,
+    kind::NEIGHBOR,
+    kind::OT_NET_CONFIG,
+    kind::DEV_INFO,
+    kind::HEART_BEAT,
+    kind::NAME_CHANGE,
+    kind::DESCRIPTION_CHANGE,
+    kind::COMMAND,
+    kind::COMMAND_RESULT,
+];
+
+// CoAP content-format ids, RFC 7252 and RFC 7049
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContentFormat {
+    Json,
+    Cbor,
+}
+impl ContentFormat {
+    pub fn id(&self) -> u16 {
+        match self {
+            ContentFormat::Json => 50,
+            ContentFormat::Cbor => 60,
+        }
+    }
+    pub fn from_id(id: u16) -> Option<ContentFormat> {
+        match id {
+            50 => Some(ContentFormat::Json),
+            60 => Some(ContentFormat::Cbor),
+            _ => None,
+        }
+    }
+    pub fn media_type(&self) -> &'static str {
+        match self {
+            ContentFormat::Json => "application/json",
+            ContentFormat::Cbor => "application/cbor",
+        }
+    }
+    // For links without content-format option. A JSON message starts with '{', a CBOR
+    // message with a map header (major type 5).
+    pub fn detect(bytes: &[u8]) -> Option<ContentFormat> {
+        match bytes.iter().find(|b| !b.is_ascii_whitespace())? {
+            b'{' | b'[' => Some(ContentFormat::Json),
+            b if b >> 5 == 5 => Some(ContentFormat::Cbor),
+            _ => None,
+        }
+    }
+}
+
+impl JsonMessage {
+    pub fn to_cbor(&self) -> Result<Vec<u8>, ApiError> {
+        to_bytes(self)
+    }
+    pub fn from_cbor(bytes: &[u8]) -> Result<JsonMessage, ApiError> {
+        JsonMessage::parse(from_bytes(bytes)?)
+    }
+    pub fn encode(&self, format: ContentFormat) -> Result<Vec<u8>, ApiError> {
+        match format {
+            ContentFormat::Json => {
+                serde_json::to_vec(self).map_err(|e| ApiError::internal(&e.to_string()))
+            }
+            ContentFormat::Cbor => self.to_cbor(),
+        }
+    }
+    pub fn decode(format: ContentFormat, bytes: &[u8]) -> Result<JsonMessage, ApiError> {
+        match format {
+            ContentFormat::Json => serde_json::from_slice::<Value>(bytes)
+                .map_err(|e| ApiError::bad_request(&e.to_string()))
+                .and_then(JsonMessage::parse),
+            ContentFormat::Cbor => JsonMessage::from_cbor(bytes),
+        }
+    }
+}
+
+impl ItemTypes {
+    pub fn to_cbor(&self) -> Result<Vec<u8>, ApiError> {
+        to_bytes(self)
+    }
+    pub fn from_cbor(bytes: &[u8]) -> Result<ItemTypes, ApiError> {
+        ItemTypes::from_value(from_bytes(bytes)?).map_err(|e| ApiError::bad_request(&e))
+    }
+}
+
+fn to_bytes(item: &impl serde::Serialize) -> Result<Vec<u8>, ApiError> {
+    let value = serde_json::to_value(item).map_err(|e| ApiError::internal(&e.to_string()))?;
+    let mut bytes = vec![];
+    ciborium::into_writer(&compact(value), &mut bytes)
+        .map_err(|e| ApiError::internal(&e.to_string()))?;
+    Ok(bytes)
+}
+
+fn from_bytes(bytes: &[u8]) -> Result<Value, ApiError> {
+    let value: Cbor = ciborium::from_reader(bytes)
+        .map_err(|e| ApiError::bad_request(&format!("invalid CBOR: {e}")))?;
+    expand(value).map_err(|e| ApiError::bad_request(&format!("invalid CBOR: {e}")))
+}
+
+fn compact(value: Value) -> Cbor {
+    match value {
+        Value::Null => Cbor::Null,
+        Value::Bool(b) => Cbor::Bool(b),
+        Value::Number(n) => match (n.as_u64(), n.as_i64()) {
+            (Some(u), _) => Cbor::Integer(u.into()),
+            (_, Some(i)) => Cbor::Integer(i.into()),
+            _ => Cbor::Float(n.as_f64().unwrap_or(f64::NAN)),
+        },
+        Value::String(s) => Cbor::Text(s),
+        Value::Array(items) => Cbor::Array(items.into_iter().map(compact).collect()),
+        Value::Object(map) => Cbor::Map(
+            map.into_iter()
+                .map(|(key, value)| {
+                    let value = match value {
+                        Value::String(k) if key == "kind" => {
+                            match KINDS.iter().position(|n| *n == k) {
+                                Some(n) => Cbor::Integer(n.into()),
+                                None => Cbor::Text(k),
+                            }
+                        }
+                        value => compact(value),
+                    };
+                    let key = match KEYS.iter().position(|n| *n == key) {
+                        Some(n) => Cbor::Integer(n.into()),
+                        None => Cbor::Text(key),
+                    };
+                    (key, value)
+                })
+                .collect(),
+        ),
+    }
+}
+
+fn expand(value: Cbor) -> Result<Value, String> {
+    let value = match value {
+        Cbor::Null => Value::Null,
+        Cbor::Bool(b) => Value::Bool(b),
+        Cbor::Integer(i) => {
+            let i = i128::from(i);
+            match (u64::try_from(i), i64::try_from(i)) {
+                (Ok(u), _) => u.into(),
+                (_, Ok(i)) => i.into(),
+                _ => return Err(format!("integer {i} out of range")),
+            }
+        }
+        Cbor::Float(f) => match Number::from_f64(f) {
+            Some(n) => Value::Number(n),
+            None => return Err(format!("float {f} is not a JSON number")),
+        },
+        Cbor::Text(s) => Value::String(s),
+        Cbor::Array(items) => {
+            Value::Array(items.into_iter().map(expand).collect::<Result<_, _>>()?)
+        }
+        Cbor::Map(entries) => {
+            let mut map = Map::new();
+            for (key, value) in entries {
+                let key = match key {
+                    Cbor::Text(key) => key,
+                    Cbor::Integer(n) => usize::try_from(i128::from(n))
+                        .ok()
+                        .and_then(|n| KEYS.get(n))
+                        .ok_or(format!("unknown key {}", i128::from(n)))?
+                        .to_string(),
+                    key => return Err(format!("unsupported key {key:?}")),
+                };
+                let value = match value {
+                    Cbor::Integer(n) if key == "kind" => usize::try_from(i128::from(n))
+                        .ok()
+                        .and_then(|n| KINDS.get(n))
+                        .map(|k| Value::from(*k))
+                        .ok_or(format!("unknown kind {}", i128::from(n)))?,
+                    value => expand(value)?,
+                };
+                map.insert(key, value);
+            }
+            Value::Object(map)
+        }
+        value => return Err(format!("unsupported value {value:?}")),
+    };
+    Ok(value)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::api::error::ErrorItem;
+    use crate::api::API_VERSION;
+    use crate::measurement::{ApiMeasurements, Measurement};
+    use crate::neighbors::ApiNeighbors;
+    use crate::settings::ApiDevSettings;
+
+    fn measurements() -> ApiMeasurements {
+        let mut measurements = ApiMeasurements::new(1234567);
+        for (n, value1) in [(13u8, 21), (14, 55), (15, 1013)] {
+            measurements.add_measurement(Measurement {
+                channel: 0u8.into(),
+                measurement_type: n.into(),
+                value1,
+                value2: 500000,
+            });
+        }
+        measurements
+    }
+
+    #[test]
+    fn round_trip() {
+        for item in [
+            ItemTypes::Measurement(measurements()),
+            ItemTypes::Settings(ApiDevSettings::new(1)),
+            ItemTypes::Neighbor(ApiNeighbors::new(&[], 1)),
+            ItemTypes::HeartBeat(crate::devs::hb::HeartBeat::new().id(5).devtype(1)),
+            ItemTypes::NameChange(crate::devs::NameChange::new(1, "north".to_string())),
+            ItemTypes::DeviceInfo(crate::devs::DevInfo::unknown_device(1)),
+            ItemTypes::CommandResult(crate::command::CommandResult::accepted(1)),
+        ] {
+            let bytes = item.to_cbor().unwrap();
+            assert_eq!(ContentFormat::detect(&bytes), Some(ContentFormat::Cbor));
+            assert_eq!(ItemTypes::from_cbor(&bytes).unwrap(), item);
+
+            let message = JsonMessage::new(item);
+            let bytes = message.encode(ContentFormat::Cbor).unwrap();
+            let decoded = JsonMessage::decode(ContentFormat::Cbor, &bytes).unwrap();
+            assert_eq!(decoded.data(), message.data());
+            assert_eq!(decoded.source_version(), Some(API_VERSION));
+        }
+        let error = ApiError::not_found("Device not found").error(ErrorItem::new(
+            "devices",
+            "notFound",
+            "No device with id 1337",
+        ));
+        let message = JsonMessage::new_error(error);
+        let parsed = JsonMessage::from_cbor(&message.to_cbor().unwrap()).unwrap();
+        assert_eq!(parsed.error(), message.error());
+    }
+
+    #[test]
+    fn integer_keys() {
+        let bytes = ItemTypes::NameChange(crate::devs::NameChange::new(1, "A".to_string()))
+            .to_cbor()
+            .unwrap();
+        let value: Cbor = ciborium::from_reader(bytes.as_slice()).unwrap();
+        let entries = value.as_map().unwrap();
+        assert!(entries.contains(&(Cbor::Integer(5.into()), Cbor::Integer(9.into()))));
+        assert!(entries.contains(&(Cbor::Integer(23.into()), Cbor::Text("A".to_string()))));
+    }
+
+    #[test]
+    fn unknown_fields_and_kinds_are_kept() {
+        let data = serde_json::json!({"kind": "Schedule", "at": [1, 2], "items": [{"id": -3}]});
+        let item = ItemTypes::Unknown(data.clone());
+        assert_eq!(
+            ItemTypes::from_cbor(&item.to_cbor().unwrap()).unwrap(),
+            item
+        );
+        // {5: 200}
+        let err = ItemTypes::from_cbor(&[0xa1, 0x05, 0x18, 0xc8]).unwrap_err();
+        assert_eq!(err.message, "invalid CBOR: unknown kind 200");
+    }
+
+    #[test]
+    fn content_format() {
+        assert_eq!(ContentFormat::from_id(60), Some(ContentFormat::Cbor));
+        assert_eq!(ContentFormat::Json.id(), 50);
+        assert_eq!(ContentFormat::Cbor.media_type(), "application/cbor");
+        assert_eq!(
+            ContentFormat::detect(b" {\"apiVersion\": \"1.1\"}"),
+            Some(ContentFormat::Json)
+        );
+        assert_eq!(ContentFormat::detect(&[0x01]), None);
+        let message = JsonMessage::new(ItemTypes::Measurement(measurements()));
+        let json = message.encode(ContentFormat::Json).unwrap();
+        let decoded = JsonMessage::decode(ContentFormat::detect(&json).unwrap(), &json).unwrap();
+        assert_eq!(decoded.data(), message.data());
+    }
+
+    #[test]
+    fn smaller_than_json() {
+        let message = JsonMessage::new(ItemTypes::Measurement(measurements()));
+        let json = message.encode(ContentFormat::Json).unwrap();
+        let cbor = message.encode(ContentFormat::Cbor).unwrap();
+        assert!(
+            cbor.len() * 2 < json.len(),
+            "{} vs {}",
+            cbor.len(),
+            json.len()
+        );
+        // Fits a single 802.15.4 frame, JSON needs fragmenting
+        assert!(cbor.len() < 127, "{}", cbor.len());
+        assert!(json.len() > 127);
+
+        let heartbeat = ItemTypes::HeartBeat(crate::devs::hb::HeartBeat::new().id(5).devtype(1));
+        let json = serde_json::to_vec(&heartbeat).unwrap();
+        let cbor = heartbeat.to_cbor().unwrap();
+        assert!(cbor.len() < json.len(), "{} vs {}", cbor.len(), json.len());
+    }
+}
@@ -0,0 +1,123 @@
+use serde::{Deserialize, Serialize};
+use std::fmt::Display;
+
+// Error object of the envelope, following the Google JSON style guide:
+// { "code": 404, "message": "...", "errors": [{ "domain", "reason", "message", "location", "locationType" }] }
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct ApiError {
+    pub code: u16,
+    pub message: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<ErrorItem>,
+}
+impl ApiError {
+    pub fn new(code: u16, message: &str) -> Self {
+        Self {
+            code,
+            message: message.to_string(),
+            errors: vec![],
+        }
+    }
+    pub fn error(mut self, error: ErrorItem) -> Self {
+        self.errors.push(error);
+        self
+    }
+    pub fn bad_request(message: &str) -> Self {
+        Self::new(400, message)
+    }
+    pub fn not_found(message: &str) -> Self {
+        Self::new(404, message)
+    }
+    pub fn internal(message: &str) -> Self {
+        Self::new(500, message)
+    }
+}
+impl Display for ApiError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.code, self.message)?;
+        for error in &self.errors {
+            write!(f, ", {error}")?;
+        }
+        Ok(())
+    }
+}
+impl std::error::Error for ApiError {}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct ErrorItem {
+    pub domain: String,
+    pub reason: String,
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub location: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub location_type: Option<String>,
+}
+impl ErrorItem {
+    pub fn new(domain: &str, reason: &str, message: &str) -> Self {
+        Self {
+            domain: domain.to_string(),
+            reason: reason.to_string(),
+            message: message.to_string(),
+            location: None,
+            location_type: None,
+        }
+    }
+    pub fn location(mut self, location: &str, location_type: &str) -> Self {
+        self.location = Some(location.to_string());
+        self.location_type = Some(location_type.to_string());
+        self
+    }
+}
+impl Display for ErrorItem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}.{}: {}", self.domain, self.reason, self.message)?;
+        if let Some(location) = &self.location {
+            write!(f, " ({location})")?;
+        }
+        Ok(())
+    }
+}
+
+// Something left out of an otherwise successful response
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct ApiWarning {
+    pub reason: String,
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub location: Option<String>,
+}
+impl ApiWarning {
+    pub fn new(reason: &str, message: &str) -> Self {
+        Self {
+            reason: reason.to_string(),
+            message: message.to_string(),
+            location: None,
+        }
+    }
+    pub fn location(mut self, location: &str) -> Self {
+        self.location = Some(location.to_string());
+        self
+    }
+    pub fn item_dropped(index: usize, message: &str) -> Self {
+        Self::new("itemDropped", message).location(&format!("data.items[{index}]"))
+    }
+    pub fn truncated(message: &str) -> Self {
+        Self::new("truncated", message)
+    }
+}
+impl Display for ApiWarning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.reason, self.message)?;
+        if let Some(location) = &self.location {
+            write!(f, " ({location})")?;
+        }
+        Ok(())
+    }
+}
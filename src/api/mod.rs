@@ -1,6 +1,12 @@
+#[cfg(feature = "cbor")]
+pub mod cbor;
+pub mod error;
+pub mod page;
 pub mod post;
+pub mod version;
 use std::collections::HashMap;
 
+use crate::api::error::{ApiError, ApiWarning};
 use crate::devices_connected::ApiDevicesConnected;
 
 use crate::measurement::ApiMeasurements;
@@ -11,32 +17,130 @@ use crate::otnet::OtNetwork;
 use crate::settings::ApiDevSettings;
 use crate::settings::ApiSettingTypes;
 
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+pub const API_VERSION: &str = "1.1";
 
 #[allow(dead_code)]
 impl JsonMessage {
     pub fn new(data: ItemTypes) -> Self {
         Self {
-            api_version: "1.0".to_string(),
-            data,
-            warning: None,
+            api_version: API_VERSION.to_string(),
+            data: Some(data),
+            error: None,
+            warnings: vec![],
+            meta: None,
+            source_version: None,
+        }
+    }
+    pub fn new_error(error: ApiError) -> Self {
+        Self {
+            api_version: API_VERSION.to_string(),
+            data: None,
+            error: Some(error),
+            warnings: vec![],
             meta: None,
+            source_version: None,
+        }
+    }
+    // Upgrades messages from older API versions before parsing
+    pub fn parse(value: Value) -> Result<JsonMessage, ApiError> {
+        #[derive(Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct Fields {
+            api_version: String,
+            data: Option<ItemTypes>,
+            error: Option<ApiError>,
+            #[serde(default)]
+            warnings: Vec<ApiWarning>,
+            meta: Option<HashMap<String, i32>>,
+        }
+        let source_version = value
+            .get("apiVersion")
+            .and_then(Value::as_str)
+            .map(str::to_string);
+        let fields: Fields = serde_json::from_value(version::upgrade(value)?)
+            .map_err(|e| ApiError::bad_request(&e.to_string()))?;
+        Ok(JsonMessage {
+            api_version: fields.api_version,
+            data: fields.data,
+            error: fields.error,
+            warnings: fields.warnings,
+            meta: fields.meta,
+            source_version,
+        })
+    }
+    // apiVersion the message was received with, to answer in the same version
+    pub fn source_version(&self) -> Option<&str> {
+        self.source_version.as_deref()
+    }
+    pub fn to_value_for(&self, api_version: &str) -> Result<Value, ApiError> {
+        let value = serde_json::to_value(self).map_err(|e| ApiError::internal(&e.to_string()))?;
+        version::downgrade(value, api_version)
+    }
+    pub fn data(&self) -> Option<&ItemTypes> {
+        self.data.as_ref()
+    }
+    pub fn error(&self) -> Option<&ApiError> {
+        self.error.as_ref()
+    }
+    pub fn is_error(&self) -> bool {
+        self.error.is_some()
+    }
+    pub fn into_result(self) -> Result<ItemTypes, ApiError> {
+        match (self.data, self.error) {
+            (_, Some(error)) => Err(error),
+            (Some(data), None) => Ok(data),
+            (None, None) => Err(ApiError::internal("Message has neither data nor error")),
         }
     }
+    pub fn add_warning(&mut self, warning: ApiWarning) {
+        self.warnings.push(warning);
+    }
+    pub fn warning(mut self, warning: ApiWarning) -> Self {
+        self.warnings.push(warning);
+        self
+    }
+    pub fn warnings(&self) -> &[ApiWarning] {
+        &self.warnings
+    }
+    pub fn has_warnings(&self) -> bool {
+        !self.warnings.is_empty()
+    }
+}
+
+impl<'de> Deserialize<'de> for JsonMessage {
+    fn deserialize<D>(deserializer: D) -> Result<JsonMessage, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let value = Value::deserialize(deserializer)?;
+        JsonMessage::parse(value).map_err(|e| serde::de::Error::custom(e.message))
+    }
 }
 
-#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+// Either data or error is set
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct JsonMessage {
     #[serde(rename = "apiVersion")]
     pub api_version: String,
-    pub data: ItemTypes,
     #[serde(skip_serializing_if = "Option::is_none")]
-    warning: Option<bool>,
+    pub data: Option<ItemTypes>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<ApiError>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    warnings: Vec<ApiWarning>,
     #[serde(skip_serializing_if = "Option::is_none")]
     meta: Option<HashMap<String, i32>>,
+    #[serde(skip)]
+    source_version: Option<String>,
 }
 
-#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[serde(untagged)]
 pub enum ItemTypes {
     Settings(ApiDevSettings),
@@ -50,6 +154,21 @@ pub enum ItemTypes {
     HeartBeat(crate::devs::hb::HeartBeat),
     NameChange(crate::devs::NameChange),
     DescriptionChange(crate::devs::DescriptionChange), // remove
+    Command(crate::command::Command),
+    CommandResult(crate::command::CommandResult),
+    // Listings of devices and heartbeats, plain arrays like OtNet
+    DeviceInfoList(Vec<crate::devs::DevInfo>),
+    HeartBeatList(Vec<crate::devs::hb::HeartBeat>),
+    // One page of a listing, from ApiDataList::paginate. Read back as a page when it has
+    // the paging fields.
+    Page(Box<ApiDataList>),
+    // `[]` on the wire. Empty OtNet and OtNetConfig lists look the same, they are read back as this.
+    #[serde(serialize_with = "serialize_empty_list")]
+    #[cfg_attr(feature = "schema", schemars(with = "[Value; 0]"))]
+    EmptyList,
+    // Payload with a kind this version does not know, kept as received
+    #[cfg_attr(feature = "schema", schemars(with = "serde_json::Map<String, Value>"))]
+    Unknown(Value),
 }
 impl ItemTypes {
     pub fn len(&self) -> usize {
@@ -65,31 +184,210 @@ impl ItemTypes {
             ItemTypes::NameChange(_) => 1,
             ItemTypes::DescriptionChange(_) => 1,
             ItemTypes::DeviceInfo(_) => 1,
+            ItemTypes::Command(_) => 1,
+            ItemTypes::CommandResult(_) => 1,
+            ItemTypes::DeviceInfoList(devices) => devices.len(),
+            ItemTypes::HeartBeatList(heartbeats) => heartbeats.len(),
+            ItemTypes::Page(page) => page.len(),
+            ItemTypes::EmptyList => 0,
+            ItemTypes::Unknown(value) => match value.get("items") {
+                Some(Value::Array(items)) => items.len(),
+                _ => 1,
+            },
         }
     }
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    // Same names as the "kind" field on the wire
     pub fn kind(&self) -> &str {
         match self {
-            ItemTypes::Settings(_) => "Setting",
-            ItemTypes::ConnectedDevices(_) => "ConnectedDeviceType",
-            ItemTypes::OtNet(_) => "OtNet",
-            ItemTypes::Measurement(_) => "Measurement",
-            ItemTypes::Neighbor(_) => "Neighbor",
-            ItemTypes::OtNetConfig(_) => "OtNetConfig",
-            ItemTypes::HeartBeat(_) => "HeartBeat",
-            ItemTypes::SettingTypes(_) => "SettingType",
-            ItemTypes::NameChange(_) => "NameChange",
-            ItemTypes::DescriptionChange(_) => "DescriptionChange",
-            ItemTypes::DeviceInfo(_) => "DeviceInfo",
+            ItemTypes::Settings(_) => kind::SETTINGS,
+            ItemTypes::ConnectedDevices(_) => kind::DEVICES_CONNECTED,
+            ItemTypes::OtNet(_) => kind::OT_NET,
+            ItemTypes::Measurement(_) => kind::MEASUREMENT,
+            ItemTypes::Neighbor(_) => kind::NEIGHBOR,
+            ItemTypes::OtNetConfig(_) => kind::OT_NET_CONFIG,
+            ItemTypes::HeartBeat(_) => kind::HEART_BEAT,
+            ItemTypes::SettingTypes(_) => kind::SETTING_TYPES,
+            ItemTypes::NameChange(_) => kind::NAME_CHANGE,
+            ItemTypes::DescriptionChange(_) => kind::DESCRIPTION_CHANGE,
+            ItemTypes::DeviceInfo(_) => kind::DEV_INFO,
+            ItemTypes::Command(_) => kind::COMMAND,
+            ItemTypes::CommandResult(_) => kind::COMMAND_RESULT,
+            ItemTypes::DeviceInfoList(_) => kind::DEV_INFO,
+            ItemTypes::HeartBeatList(_) => kind::HEART_BEAT,
+            ItemTypes::Page(page) => page.kind(),
+            ItemTypes::EmptyList => kind::UNKNOWN,
+            ItemTypes::Unknown(value) => match value {
+                Value::Array(items) => items.first().unwrap_or(&Value::Null),
+                value => value,
+            }
+            .get("kind")
+            .and_then(Value::as_str)
+            .unwrap_or(kind::UNKNOWN),
+        }
+    }
+    fn from_value(value: Value) -> Result<ItemTypes, String> {
+        fn parse<T: DeserializeOwned>(kind: &str, value: Value) -> Result<T, String> {
+            serde_json::from_value(value).map_err(|e| format!("invalid {kind} payload: {e}"))
+        }
+        let item_kind = match &value {
+            // Lists are sent as plain arrays, the items carry the kind. OtNetwork items are
+            // known by their keys, their kind is the network's. Anything else is left as is.
+            Value::Array(items) => {
+                let Some(item) = items.first() else {
+                    return Ok(ItemTypes::EmptyList);
+                };
+                let item = match item.get("kind").and_then(Value::as_str) {
+                    Some(kind::OT_NET_CONFIG) => {
+                        ItemTypes::OtNetConfig(parse(kind::OT_NET_CONFIG, value)?)
+                    }
+                    Some(kind::DEV_INFO) => {
+                        ItemTypes::DeviceInfoList(parse(kind::DEV_INFO, value)?)
+                    }
+                    Some(kind::HEART_BEAT) => {
+                        ItemTypes::HeartBeatList(parse(kind::HEART_BEAT, value)?)
+                    }
+                    _ if OT_NETWORK_FIELDS.iter().all(|f| item.get(*f).is_some()) => {
+                        ItemTypes::OtNet(parse(kind::OT_NET, value)?)
+                    }
+                    _ => ItemTypes::Unknown(value),
+                };
+                return Ok(item);
+            }
+            Value::Object(map) if PAGE_FIELDS.iter().any(|f| map.contains_key(*f)) => {
+                return Ok(ItemTypes::Page(parse("page", value)?));
+            }
+            Value::Object(map) => match map.get("kind") {
+                Some(Value::String(k)) => kind::canonical(k).unwrap_or(kind::UNKNOWN),
+                Some(_) => return Err("invalid payload: kind is not a string".to_string()),
+                None => return Ok(Self::from_untagged(value)),
+            },
+            _ => {
+                return Err(format!(
+                    "invalid payload: expected object or array, got {value}"
+                ))
+            }
+        };
+        let item = match item_kind {
+            kind::SETTINGS => ItemTypes::Settings(parse(item_kind, value)?),
+            kind::SETTING_TYPES => ItemTypes::SettingTypes(parse(item_kind, value)?),
+            kind::DEVICES_CONNECTED => ItemTypes::ConnectedDevices(parse(item_kind, value)?),
+            kind::OT_NET => ItemTypes::OtNet(parse(item_kind, value)?),
+            kind::MEASUREMENT => ItemTypes::Measurement(parse(item_kind, value)?),
+            kind::NEIGHBOR => ItemTypes::Neighbor(parse(item_kind, value)?),
+            kind::OT_NET_CONFIG => ItemTypes::OtNetConfig(parse(item_kind, value)?),
+            kind::DEV_INFO => ItemTypes::DeviceInfo(parse(item_kind, value)?),
+            kind::HEART_BEAT => ItemTypes::HeartBeat(parse(item_kind, value)?),
+            kind::NAME_CHANGE => ItemTypes::NameChange(parse(item_kind, value)?),
+            kind::DESCRIPTION_CHANGE => ItemTypes::DescriptionChange(parse(item_kind, value)?),
+            kind::COMMAND => ItemTypes::Command(parse(item_kind, value)?),
+            kind::COMMAND_RESULT => ItemTypes::CommandResult(parse(item_kind, value)?),
+            _ => ItemTypes::Unknown(value),
+        };
+        Ok(item)
+    }
+    // Older clients sent DescriptionChange without a kind
+    fn from_untagged(value: Value) -> ItemTypes {
+        match serde_json::from_value(value.clone()) {
+            Ok(description) => ItemTypes::DescriptionChange(description),
+            Err(_) => ItemTypes::Unknown(value),
+        }
+    }
+}
+const OT_NETWORK_FIELDS: &[&str] = &["id", "tlv", "networkName"];
+// Set by ApiDataList::paginate, none of the payloads has them
+const PAGE_FIELDS: &[&str] = &[
+    "startIndex",
+    "itemsPerPage",
+    "totalItems",
+    "pageIndex",
+    "totalPages",
+];
+fn serialize_empty_list<S>(serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: serde::Serializer,
+{
+    serializer.collect_seq(std::iter::empty::<Value>())
+}
+impl<'de> Deserialize<'de> for ItemTypes {
+    fn deserialize<D>(deserializer: D) -> Result<ItemTypes, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let value = Value::deserialize(deserializer)?;
+        ItemTypes::from_value(value).map_err(serde::de::Error::custom)
+    }
+}
+
+pub mod kind {
+    pub const SETTINGS: &str = "Settings";
+    pub const SETTING_TYPES: &str = "SettingTypes";
+    pub const DEVICES_CONNECTED: &str = "devicesConnected";
+    pub const OT_NET: &str = "OtNet";
+    pub const MEASUREMENT: &str = "Measurement";
+    pub const NEIGHBOR: &str = "Neighbor";
+    pub const OT_NET_CONFIG: &str = "OtNetConfig";
+    pub const DEV_INFO: &str = "DevInfo";
+    pub const HEART_BEAT: &str = "HeartBeat";
+    pub const NAME_CHANGE: &str = "NameChange";
+    pub const DESCRIPTION_CHANGE: &str = "DescriptionChange";
+    pub const COMMAND: &str = "Command";
+    pub const COMMAND_RESULT: &str = "CommandResult";
+    pub const UNKNOWN: &str = "Unknown";
+
+    pub const ALL: &[&str] = &[
+        SETTINGS,
+        SETTING_TYPES,
+        DEVICES_CONNECTED,
+        OT_NET,
+        MEASUREMENT,
+        NEIGHBOR,
+        OT_NET_CONFIG,
+        DEV_INFO,
+        HEART_BEAT,
+        NAME_CHANGE,
+        DESCRIPTION_CHANGE,
+        COMMAND,
+        COMMAND_RESULT,
+    ];
+
+    // Maps a kind, including names used by older versions, to the name used on the wire
+    pub fn canonical(kind: &str) -> Option<&'static str> {
+        match kind {
+            "Setting" => Some(SETTINGS),
+            "SettingType" => Some(SETTING_TYPES),
+            "ConnectedDeviceType" | "DevicesConnected" => Some(DEVICES_CONNECTED),
+            "Neighbors" => Some(NEIGHBOR),
+            "DeviceInfo" => Some(DEV_INFO),
+            kind => ALL.iter().find(|k| **k == kind).copied(),
         }
     }
 }
 #[derive(Default, Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "camelCase")]
 pub struct ApiDataList {
     id: String,
     kind: String,
     // updated: String,
-    #[serde(rename = "currentItemCount")]
     current_item_count: usize,
+    // Paging, startIndex and pageIndex are 1-based
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    start_index: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    items_per_page: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    total_items: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    page_index: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    total_pages: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    next_cursor: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    previous_cursor: Option<String>,
     items: Option<ItemTypes>,
 }
 impl ApiDataList {
@@ -99,6 +397,278 @@ impl ApiDataList {
             kind: items.kind().to_string(),
             current_item_count: items.len(),
             items: Some(items),
+            ..Default::default()
         }
     }
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+    pub fn kind(&self) -> &str {
+        &self.kind
+    }
+    pub fn len(&self) -> usize {
+        self.current_item_count
+    }
+    pub fn is_empty(&self) -> bool {
+        self.current_item_count == 0
+    }
+    pub fn items(&self) -> Option<&ItemTypes> {
+        self.items.as_ref()
+    }
+    pub fn into_items(self) -> Option<ItemTypes> {
+        self.items
+    }
+    pub fn start_index(&self) -> Option<usize> {
+        self.start_index
+    }
+    pub fn items_per_page(&self) -> Option<usize> {
+        self.items_per_page
+    }
+    pub fn total_items(&self) -> Option<usize> {
+        self.total_items
+    }
+    pub fn page_index(&self) -> Option<usize> {
+        self.page_index
+    }
+    pub fn total_pages(&self) -> Option<usize> {
+        self.total_pages
+    }
+    pub fn next_cursor(&self) -> Option<&str> {
+        self.next_cursor.as_deref()
+    }
+    pub fn previous_cursor(&self) -> Option<&str> {
+        self.previous_cursor.as_deref()
+    }
+    pub fn set_cursors(&mut self, previous: Option<String>, next: Option<String>) {
+        self.previous_cursor = previous;
+        self.next_cursor = next;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::api::error::ErrorItem;
+    use serde_json::json;
+
+    #[test]
+    fn kind_matches_wire() {
+        for item in [
+            ItemTypes::Settings(ApiDevSettings::new(1)),
+            ItemTypes::SettingTypes(ApiSettingTypes::new(1)),
+            ItemTypes::ConnectedDevices(ApiDevicesConnected::new(1)),
+            ItemTypes::Measurement(ApiMeasurements::new(1)),
+            ItemTypes::Neighbor(ApiNeighbors::new(&[], 1)),
+            ItemTypes::DeviceInfo(crate::devs::DevInfo::unknown_device(1)),
+            ItemTypes::HeartBeat(crate::devs::hb::HeartBeat::new().id(5).devtype(1)),
+            ItemTypes::NameChange(crate::devs::NameChange::new(1, "name".to_string())),
+            ItemTypes::Command(crate::command::Command::with_ttl(
+                1,
+                crate::command::DeviceCommand::Reboot,
+                std::time::Duration::from_secs(60),
+            )),
+            ItemTypes::CommandResult(crate::command::CommandResult::accepted(1)),
+        ] {
+            let value = serde_json::to_value(&item).unwrap();
+            assert_eq!(value["kind"], item.kind());
+            assert_eq!(serde_json::from_value::<ItemTypes>(value).unwrap(), item);
+        }
+        let mut config = OtNetConfig::new();
+        config.set_netname("horti");
+        let item = ItemTypes::OtNetConfig(vec![config]);
+        let value = serde_json::to_value(&item).unwrap();
+        assert_eq!(serde_json::from_value::<ItemTypes>(value).unwrap(), item);
+    }
+
+    #[test]
+    fn description_change_stays_untagged() {
+        let item = ItemTypes::DescriptionChange(crate::devs::DescriptionChange::new(1, "desc"));
+        let value = serde_json::to_value(&item).unwrap();
+        assert_eq!(value, json!({"description": "desc"}));
+        assert_eq!(serde_json::from_value::<ItemTypes>(value).unwrap(), item);
+        let tagged = json!({"kind": "DescriptionChange", "description": "desc"});
+        assert_eq!(serde_json::from_value::<ItemTypes>(tagged).unwrap(), item);
+        assert_eq!(item.kind(), "DescriptionChange");
+    }
+
+    #[test]
+    fn empty_lists() {
+        for item in [
+            ItemTypes::OtNet(vec![]),
+            ItemTypes::OtNetConfig(vec![]),
+            ItemTypes::EmptyList,
+        ] {
+            let value = serde_json::to_value(&item).unwrap();
+            assert_eq!(value, json!([]));
+            let parsed = serde_json::from_value::<ItemTypes>(value).unwrap();
+            assert_eq!(parsed, ItemTypes::EmptyList);
+            assert!(parsed.is_empty());
+        }
+    }
+
+    #[test]
+    fn lists_and_pages() {
+        let devices = ItemTypes::DeviceInfoList(vec![
+            crate::devs::DevInfo::unknown_device(1),
+            crate::devs::DevInfo::unknown_device(2),
+        ]);
+        let heartbeats =
+            ItemTypes::HeartBeatList(vec![crate::devs::hb::HeartBeat::new().id(5).devtype(1)]);
+        let mut measurements = ApiMeasurements::new(1);
+        measurements.add_measurement(crate::measurement::Measurement {
+            channel: 0.into(),
+            measurement_type: 13.into(),
+            value1: 1,
+            value2: 0,
+        });
+        let measurements = ItemTypes::Measurement(measurements);
+        for items in [devices, heartbeats, measurements] {
+            for page in ApiDataList::paginate("1".to_string(), items.clone(), 1) {
+                let message = JsonMessage::new(ItemTypes::Page(Box::new(page)));
+                let value = serde_json::to_value(&message).unwrap();
+                assert_eq!(value["data"]["kind"], items.kind());
+                let parsed: JsonMessage = serde_json::from_value(value).unwrap();
+                assert_eq!(parsed.data(), message.data());
+            }
+            let value = serde_json::to_value(JsonMessage::new(items.clone())).unwrap();
+            let parsed: JsonMessage = serde_json::from_value(value).unwrap();
+            assert_eq!(parsed.data(), Some(&items));
+        }
+        // A list without paging is the payload itself
+        let value = serde_json::to_value(ApiDataList::new(
+            "1".to_string(),
+            ItemTypes::Measurement(ApiMeasurements::new(1)),
+        ))
+        .unwrap();
+        assert!(serde_json::from_value::<ItemTypes>(value).is_err());
+
+        // A list of something newer is kept, not read as OtNet
+        let value = json!([{"kind": "FutureThing"}]);
+        let parsed: ItemTypes = serde_json::from_value(value.clone()).unwrap();
+        assert_eq!(parsed, ItemTypes::Unknown(value));
+        let value = json!([{"id": 1, "kind": "thread", "tlv": "0e08", "networkName": "horti"}]);
+        let parsed: ItemTypes = serde_json::from_value(value).unwrap();
+        assert!(matches!(parsed, ItemTypes::OtNet(networks) if networks.len() == 1));
+    }
+
+    #[test]
+    fn precise_errors() {
+        let err = serde_json::from_value::<ItemTypes>(json!({"kind": "Measurement", "id": "1"}))
+            .unwrap_err()
+            .to_string();
+        assert!(
+            err.starts_with("invalid Measurement payload: missing field"),
+            "{err}"
+        );
+        let err = serde_json::from_value::<JsonMessage>(json!({
+            "apiVersion": "1.0",
+            "data": {"kind": "HeartBeat", "id": 12}
+        }))
+        .unwrap_err()
+        .to_string();
+        assert!(err.starts_with("invalid HeartBeat payload"), "{err}");
+    }
+
+    #[test]
+    fn unknown_kind_is_kept() {
+        let data = json!({"kind": "Schedule", "items": [{"at": 1}, {"at": 2}]});
+        let message: JsonMessage =
+            serde_json::from_value(json!({"apiVersion": "1.0", "data": data})).unwrap();
+        assert_eq!(message.data(), Some(&ItemTypes::Unknown(data.clone())));
+        assert_eq!(message.data().unwrap().kind(), "Schedule");
+        assert_eq!(message.data().unwrap().len(), 2);
+        assert_eq!(serde_json::to_value(&message).unwrap()["data"], data);
+    }
+
+    #[test]
+    fn legacy_kinds() {
+        let item: ItemTypes = serde_json::from_value(json!({
+            "kind": "ConnectedDeviceType", "id": "1", "currentItemCount": 0, "items": []
+        }))
+        .unwrap();
+        assert_eq!(
+            item,
+            ItemTypes::ConnectedDevices(ApiDevicesConnected::new(1))
+        );
+        let item: ItemTypes = serde_json::from_value(json!({"description": "desc"})).unwrap();
+        assert_eq!(
+            item,
+            ItemTypes::DescriptionChange(crate::devs::DescriptionChange::new(1, "desc"))
+        );
+    }
+
+    #[test]
+    fn migrates_older_versions() {
+        let message: JsonMessage = serde_json::from_value(json!({
+            "apiVersion": "1.0",
+            "data": {"kind": "DescriptionChange", "description": "desc"}
+        }))
+        .unwrap();
+        assert_eq!(message.api_version, API_VERSION);
+        assert_eq!(message.source_version(), Some("1.0"));
+        assert_eq!(
+            message.data(),
+            Some(&ItemTypes::NameChange(
+                crate::devs::NameChange::new_with_description(1, None, Some("desc".to_string()))
+            ))
+        );
+        let reply = message
+            .to_value_for(message.source_version().unwrap())
+            .unwrap();
+        assert_eq!(reply["apiVersion"], "1.0");
+        assert_eq!(reply["data"], json!({"description": "desc"}));
+
+        let err = serde_json::from_value::<JsonMessage>(json!({"apiVersion": "2.0"}))
+            .unwrap_err()
+            .to_string();
+        assert_eq!(err, "Unsupported apiVersion 2.0");
+    }
+
+    #[test]
+    fn error_envelope() {
+        let error = ApiError::not_found("Device not found").error(
+            ErrorItem::new("devices", "notFound", "No device with id 1337")
+                .location("id", "parameter"),
+        );
+        let message = JsonMessage::new_error(error.clone());
+        let value = serde_json::to_value(&message).unwrap();
+        assert_eq!(
+            value,
+            json!({
+                "apiVersion": API_VERSION,
+                "error": {
+                    "code": 404,
+                    "message": "Device not found",
+                    "errors": [{
+                        "domain": "devices",
+                        "reason": "notFound",
+                        "message": "No device with id 1337",
+                        "location": "id",
+                        "locationType": "parameter"
+                    }]
+                }
+            })
+        );
+        let parsed: JsonMessage = serde_json::from_value(value).unwrap();
+        assert!(parsed.is_error());
+        assert_eq!(parsed.into_result(), Err(error));
+    }
+
+    #[test]
+    fn warnings() {
+        let mut message = JsonMessage::new(ItemTypes::Measurement(ApiMeasurements::new(1)));
+        assert!(serde_json::to_value(&message)
+            .unwrap()
+            .get("warnings")
+            .is_none());
+        message.add_warning(ApiWarning::item_dropped(3, "Unknown measurement type"));
+        let value = serde_json::to_value(&message).unwrap();
+        assert_eq!(
+            value["warnings"],
+            json!([{"reason": "itemDropped", "message": "Unknown measurement type", "location": "data.items[3]"}])
+        );
+        let parsed: JsonMessage = serde_json::from_value(value).unwrap();
+        assert_eq!(parsed.warnings(), message.warnings());
+        assert!(parsed.into_result().is_ok());
+    }
 }
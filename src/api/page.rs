@@ -0,0 +1,203 @@
+use serde_json::Value;
+
+use super::{ApiDataList, ItemTypes};
+
+// Splitting works on the serialized form, so every list payload pages the same way:
+// plain arrays (OtNet, OtNetConfig) are chunked, objects get their "items" chunked and
+// "currentItemCount" updated. Single items always fit on one page.
+//
+// Cursors are the 1-based startIndex of the next and previous page.
+impl ApiDataList {
+    pub fn paginate(id: String, items: ItemTypes, items_per_page: usize) -> Vec<ApiDataList> {
+        let items_per_page = items_per_page.max(1);
+        let total_items = items.len();
+        let pages = match split(&items, items_per_page) {
+            Some(pages) if pages.len() > 1 => pages,
+            _ => vec![items],
+        };
+        let total_pages = pages.len();
+        pages
+            .into_iter()
+            .enumerate()
+            .map(|(n, page)| {
+                let start = n * items_per_page;
+                let mut ret = ApiDataList::new(id.clone(), page);
+                ret.start_index = Some(start + 1);
+                ret.items_per_page = Some(items_per_page);
+                ret.total_items = Some(total_items);
+                ret.page_index = Some(n + 1);
+                ret.total_pages = Some(total_pages);
+                ret.previous_cursor = (n > 0).then(|| (start + 1 - items_per_page).to_string());
+                ret.next_cursor =
+                    (n + 1 < total_pages).then(|| (start + 1 + items_per_page).to_string());
+                ret
+            })
+            .collect()
+    }
+
+    // Puts the pages of one listing back together, in any order. Every page has to be
+    // there once, pageIndex 1..=totalPages, each starting where the previous one ended.
+    pub fn reassemble(mut pages: Vec<ApiDataList>) -> Result<ItemTypes, &'static str> {
+        pages.sort_by_key(|p| (p.page_index.unwrap_or(1), p.start_index.unwrap_or(1)));
+        let Some(first) = pages.first() else {
+            return Err("No pages to reassemble");
+        };
+        if pages
+            .iter()
+            .any(|p| p.kind != first.kind || p.id != first.id)
+        {
+            return Err("Pages belong to different listings");
+        }
+        let total_pages = first.total_pages.unwrap_or(pages.len());
+        if pages.len() != total_pages
+            || pages.iter().enumerate().any(|(n, p)| {
+                p.page_index.unwrap_or(1) != n + 1
+                    || p.total_pages.is_some_and(|t| t != total_pages)
+            })
+        {
+            return Err("Missing or duplicated pages");
+        }
+        let mut next_start = 1;
+        for page in &pages {
+            if page.start_index.is_some_and(|s| s != next_start) {
+                return Err("Pages do not follow on from each other");
+            }
+            next_start += page.items.as_ref().map_or(0, ItemTypes::len);
+        }
+        let total_items = first.total_items;
+        if pages.len() == 1 {
+            return pages.remove(0).items.ok_or("Page without items");
+        }
+        let mut values = pages
+            .into_iter()
+            .map(|p| p.items.ok_or("Page without items"))
+            .map(|i| serde_json::to_value(i?).map_err(|_| "Unable to serialize page"));
+        let mut ret = values.next().ok_or("No pages to reassemble")??;
+        for value in values {
+            let mut value = value?;
+            let more = items_mut(&mut value).ok_or("Page is not a list")?;
+            let more = std::mem::take(more);
+            items_mut(&mut ret)
+                .ok_or("Page is not a list")?
+                .extend(more);
+        }
+        let count = items_mut(&mut ret).ok_or("Page is not a list")?.len();
+        if total_items.is_some_and(|t| t != count) {
+            return Err("Missing or duplicated items in pages");
+        }
+        set_count(&mut ret, count);
+        ItemTypes::from_value(ret).map_err(|_| "Unable to parse reassembled pages")
+    }
+}
+
+fn items_mut(value: &mut Value) -> Option<&mut Vec<Value>> {
+    match value {
+        Value::Array(items) => Some(items),
+        Value::Object(map) => map.get_mut("items")?.as_array_mut(),
+        _ => None,
+    }
+}
+fn set_count(value: &mut Value, count: usize) {
+    if let Value::Object(map) = value {
+        if map.contains_key("currentItemCount") {
+            map.insert("currentItemCount".to_string(), count.into());
+        }
+    }
+}
+fn split(items: &ItemTypes, items_per_page: usize) -> Option<Vec<ItemTypes>> {
+    let mut value = serde_json::to_value(items).ok()?;
+    let list = std::mem::take(items_mut(&mut value)?);
+    list.chunks(items_per_page)
+        .map(|chunk| {
+            let mut page = value.clone();
+            *items_mut(&mut page)? = chunk.to_vec();
+            set_count(&mut page, chunk.len());
+            ItemTypes::from_value(page).ok()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::measurement::{ApiMeasurements, Measurement};
+    use crate::otnet::OtNetConfig;
+
+    fn measurements(n: i32) -> ItemTypes {
+        let mut ret = ApiMeasurements::new(1337);
+        for v in 0..n {
+            ret.add_measurement(Measurement {
+                channel: 0.into(),
+                measurement_type: 13.into(),
+                value1: v,
+                value2: 0,
+            });
+        }
+        ItemTypes::Measurement(ret)
+    }
+
+    #[test]
+    fn split_and_reassemble() {
+        let items = measurements(25);
+        let mut pages = ApiDataList::paginate("1337".to_string(), items.clone(), 10);
+        assert_eq!(pages.len(), 3);
+        assert_eq!(pages[1].start_index(), Some(11));
+        assert_eq!(pages[1].page_index(), Some(2));
+        assert_eq!(pages[1].len(), 10);
+        assert_eq!(pages[1].previous_cursor(), Some("1"));
+        assert_eq!(pages[1].next_cursor(), Some("21"));
+        assert_eq!(pages[2].len(), 5);
+        assert_eq!(pages[2].next_cursor(), None);
+        assert!(pages
+            .iter()
+            .all(|p| p.total_items() == Some(25) && p.total_pages() == Some(3)));
+
+        let value = serde_json::to_value(&pages[0]).unwrap();
+        assert_eq!(value["startIndex"], 1);
+        assert_eq!(value["itemsPerPage"], 10);
+        assert_eq!(value["items"]["currentItemCount"], 10);
+        let page: ApiDataList = serde_json::from_value(value).unwrap();
+        assert_eq!(page, pages[0]);
+
+        pages.reverse();
+        assert_eq!(ApiDataList::reassemble(pages.clone()), Ok(items));
+        let mut duplicated = pages.clone();
+        duplicated[1] = duplicated[2].clone();
+        assert_eq!(
+            ApiDataList::reassemble(duplicated),
+            Err("Missing or duplicated pages")
+        );
+        let mut shifted = pages.clone();
+        shifted[1].start_index = Some(12);
+        assert_eq!(
+            ApiDataList::reassemble(shifted),
+            Err("Pages do not follow on from each other")
+        );
+        pages.remove(1);
+        assert!(ApiDataList::reassemble(pages).is_err());
+    }
+
+    #[test]
+    fn arrays_and_single_items() {
+        let configs: Vec<_> = (0..3)
+            .map(|n| {
+                let mut config = OtNetConfig::new();
+                config.set_netname(&format!("net{n}"));
+                config
+            })
+            .collect();
+        let items = ItemTypes::OtNetConfig(configs);
+        let pages = ApiDataList::paginate("1".to_string(), items.clone(), 2);
+        assert_eq!(pages.len(), 2);
+        assert_eq!(ApiDataList::reassemble(pages), Ok(items));
+
+        let item = ItemTypes::DeviceInfo(crate::devs::DevInfo::unknown_device(1));
+        let pages = ApiDataList::paginate("1".to_string(), item.clone(), 2);
+        assert_eq!(pages.len(), 1);
+        assert_eq!(ApiDataList::reassemble(pages), Ok(item));
+
+        let pages = ApiDataList::paginate("1".to_string(), measurements(0), 10);
+        assert_eq!(pages.len(), 1);
+        assert_eq!(pages[0].total_items(), Some(0));
+    }
+}
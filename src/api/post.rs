@@ -1,16 +1,394 @@
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
 
-use super::ItemTypes;
+use super::error::ApiError;
+use super::{version, ItemTypes, API_VERSION};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "camelCase")]
+pub enum Method {
+    Get,
+    Insert,
+    #[default]
+    Update,
+    Delete,
+}
+
+// Request from the frontend. id and method are optional on the wire,
+// a request without method is an update.
 #[derive(Debug, Clone, PartialEq, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "camelCase")]
 pub struct ApiPost {
-    #[serde(rename = "apiVersion")]
     api_version: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    id: Option<String>,
+    method: Method,
     data: ItemTypes,
+    #[serde(skip)]
+    source_version: Option<String>,
+}
+impl ApiPost {
+    pub fn new(method: Method, data: ItemTypes) -> Self {
+        Self {
+            api_version: API_VERSION.to_string(),
+            id: None,
+            method,
+            data,
+            source_version: None,
+        }
+    }
+    // Runs the version migrations like JsonMessage::parse, older requests are read as API_VERSION
+    pub fn parse(value: Value) -> Result<ApiPost, ApiError> {
+        #[derive(Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct Fields {
+            api_version: String,
+            #[serde(default)]
+            id: Option<String>,
+            #[serde(default)]
+            method: Method,
+            data: ItemTypes,
+        }
+        let source_version = value
+            .get("apiVersion")
+            .and_then(Value::as_str)
+            .map(str::to_string);
+        let fields: Fields = serde_json::from_value(version::upgrade(value)?)
+            .map_err(|e| ApiError::bad_request(&e.to_string()))?;
+        Ok(ApiPost {
+            api_version: fields.api_version,
+            id: fields.id,
+            method: fields.method,
+            data: fields.data,
+            source_version,
+        })
+    }
+    // apiVersion the request was received with, to answer in the same version
+    pub fn source_version(&self) -> Option<&str> {
+        self.source_version.as_deref()
+    }
+    pub fn id(mut self, id: &str) -> Self {
+        self.id = Some(id.to_string());
+        self
+    }
+    pub fn api_version(&self) -> &str {
+        &self.api_version
+    }
+    pub fn request_id(&self) -> Option<&str> {
+        self.id.as_deref()
+    }
+    pub fn method(&self) -> Method {
+        self.method
+    }
+    pub fn data(&self) -> &ItemTypes {
+        &self.data
+    }
+    pub fn into_data(self) -> ItemTypes {
+        self.data
+    }
+    // Checks the version the request was sent with, parsing upgrades api_version
+    pub fn validate(&self) -> Result<(), ApiError> {
+        validate_api_version(self.source_version().unwrap_or(&self.api_version))
+    }
 }
-impl ApiPost {}
+
 #[derive(Debug, Clone, PartialEq, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "camelCase")]
 pub struct ApiPostList {
-    #[serde(rename = "apiVersion")]
     api_version: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    id: Option<String>,
+    method: Method,
     data: Vec<ItemTypes>,
+    #[serde(skip)]
+    source_version: Option<String>,
+}
+impl ApiPostList {
+    pub fn new(method: Method, data: Vec<ItemTypes>) -> Self {
+        Self {
+            api_version: API_VERSION.to_string(),
+            id: None,
+            method,
+            data,
+            source_version: None,
+        }
+    }
+    // Runs the version migrations like JsonMessage::parse, older requests are read as API_VERSION
+    pub fn parse(value: Value) -> Result<ApiPostList, ApiError> {
+        #[derive(Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct Fields {
+            api_version: String,
+            #[serde(default)]
+            id: Option<String>,
+            #[serde(default)]
+            method: Method,
+            data: Vec<ItemTypes>,
+        }
+        let source_version = value
+            .get("apiVersion")
+            .and_then(Value::as_str)
+            .map(str::to_string);
+        let fields: Fields = serde_json::from_value(version::upgrade_list(value)?)
+            .map_err(|e| ApiError::bad_request(&e.to_string()))?;
+        Ok(ApiPostList {
+            api_version: fields.api_version,
+            id: fields.id,
+            method: fields.method,
+            data: fields.data,
+            source_version,
+        })
+    }
+    // apiVersion the request was received with, to answer in the same version
+    pub fn source_version(&self) -> Option<&str> {
+        self.source_version.as_deref()
+    }
+    pub fn id(mut self, id: &str) -> Self {
+        self.id = Some(id.to_string());
+        self
+    }
+    pub fn api_version(&self) -> &str {
+        &self.api_version
+    }
+    pub fn request_id(&self) -> Option<&str> {
+        self.id.as_deref()
+    }
+    pub fn method(&self) -> Method {
+        self.method
+    }
+    pub fn as_slice(&self) -> &[ItemTypes] {
+        &self.data
+    }
+    pub fn into_vec(self) -> Vec<ItemTypes> {
+        self.data
+    }
+    // Checks the version the request was sent with, parsing upgrades api_version
+    pub fn validate(&self) -> Result<(), ApiError> {
+        validate_api_version(self.source_version().unwrap_or(&self.api_version))
+    }
+}
+
+impl<'de> Deserialize<'de> for ApiPost {
+    fn deserialize<D>(deserializer: D) -> Result<ApiPost, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let value = Value::deserialize(deserializer)?;
+        ApiPost::parse(value).map_err(|e| serde::de::Error::custom(e.message))
+    }
+}
+impl<'de> Deserialize<'de> for ApiPostList {
+    fn deserialize<D>(deserializer: D) -> Result<ApiPostList, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let value = Value::deserialize(deserializer)?;
+        ApiPostList::parse(value).map_err(|e| serde::de::Error::custom(e.message))
+    }
+}
+
+// What ApiPostResponse echoes of ApiPost and ApiPostList
+pub trait PostRequest {
+    fn request_id(&self) -> Option<&str>;
+    fn method(&self) -> Method;
+}
+impl PostRequest for ApiPost {
+    fn request_id(&self) -> Option<&str> {
+        self.request_id()
+    }
+    fn method(&self) -> Method {
+        self.method()
+    }
+}
+impl PostRequest for ApiPostList {
+    fn request_id(&self) -> Option<&str> {
+        self.request_id()
+    }
+    fn method(&self) -> Method {
+        self.method()
+    }
+}
+
+// Answer to an ApiPost or ApiPostList, echoes id and method of the request
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct ApiPostResponse {
+    api_version: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    id: Option<String>,
+    method: Method,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    data: Option<ItemTypes>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    error: Option<ApiError>,
+}
+impl ApiPostResponse {
+    fn new(id: Option<&str>, method: Method) -> Self {
+        Self {
+            api_version: API_VERSION.to_string(),
+            id: id.map(|id| id.to_string()),
+            method,
+            data: None,
+            error: None,
+        }
+    }
+    pub fn ok(request: &impl PostRequest, data: Option<ItemTypes>) -> Self {
+        let mut ret = Self::new(request.request_id(), request.method());
+        ret.data = data;
+        ret
+    }
+    pub fn error(request: &impl PostRequest, error: ApiError) -> Self {
+        let mut ret = Self::new(request.request_id(), request.method());
+        ret.error = Some(error);
+        ret
+    }
+    // Serializes for a client on api_version, see JsonMessage::to_value_for
+    pub fn to_value_for(&self, api_version: &str) -> Result<Value, ApiError> {
+        let value = serde_json::to_value(self).map_err(|e| ApiError::internal(&e.to_string()))?;
+        version::downgrade(value, api_version)
+    }
+    pub fn request_id(&self) -> Option<&str> {
+        self.id.as_deref()
+    }
+    pub fn method(&self) -> Method {
+        self.method
+    }
+    pub fn data(&self) -> Option<&ItemTypes> {
+        self.data.as_ref()
+    }
+    pub fn error_info(&self) -> Option<&ApiError> {
+        self.error.as_ref()
+    }
+    pub fn is_ok(&self) -> bool {
+        self.error.is_none()
+    }
+}
+
+fn validate_api_version(api_version: &str) -> Result<(), ApiError> {
+    super::version::resolve(api_version).map(|_| ())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::devs::NameChange;
+    use serde_json::json;
+
+    #[test]
+    fn parse_frontend_post() {
+        let post: ApiPost = serde_json::from_value(json!({
+            "apiVersion": "1.0",
+            "data": {"kind": "NameChange", "name": "Greenhouse 1", "description": null}
+        }))
+        .unwrap();
+        assert!(post.validate().is_ok());
+        assert_eq!(post.method(), Method::Update);
+        assert_eq!(post.request_id(), None);
+        assert_eq!(
+            post.data(),
+            &ItemTypes::NameChange(NameChange::new(1, "Greenhouse 1".to_string()))
+        );
+    }
+
+    #[test]
+    fn response_echoes_request() {
+        let post = ApiPost::new(
+            Method::Update,
+            ItemTypes::NameChange(NameChange::new(1, "Greenhouse 1".to_string())),
+        )
+        .id("req-42");
+        let post: ApiPost = serde_json::from_str(&serde_json::to_string(&post).unwrap()).unwrap();
+        let response = ApiPostResponse::ok(&post, None);
+        assert_eq!(
+            serde_json::to_value(&response).unwrap(),
+            json!({"apiVersion": API_VERSION, "id": "req-42", "method": "update"})
+        );
+        let list = ApiPostList::new(Method::Delete, vec![post.into_data()]).id("req-43");
+        let response = ApiPostResponse::error(&list, ApiError::not_found("No such device"));
+        assert_eq!(response.request_id(), Some("req-43"));
+        assert!(!response.is_ok());
+    }
+
+    #[test]
+    fn upgrades_older_versions() {
+        let post: ApiPost = serde_json::from_value(json!({
+            "apiVersion": "1.0",
+            "id": "req-7",
+            "data": {"description": "north wall"}
+        }))
+        .unwrap();
+        assert_eq!(post.api_version(), API_VERSION);
+        assert_eq!(post.source_version(), Some("1.0"));
+        assert_eq!(post.request_id(), Some("req-7"));
+        let upgraded = ItemTypes::NameChange(NameChange::new_with_description(
+            1,
+            None,
+            Some("north wall".to_string()),
+        ));
+        assert_eq!(post.data(), &upgraded);
+
+        let list: ApiPostList = serde_json::from_value(json!({
+            "apiVersion": "1.0",
+            "method": "insert",
+            "data": [
+                {"kind": "DescriptionChange", "description": "north wall"},
+                {"kind": "NameChange", "name": "A", "description": null}
+            ]
+        }))
+        .unwrap();
+        assert_eq!(list.source_version(), Some("1.0"));
+        assert_eq!(list.as_slice()[0], upgraded);
+        assert_eq!(
+            list.as_slice()[1],
+            ItemTypes::NameChange(NameChange::new(1, "A".to_string()))
+        );
+    }
+
+    #[test]
+    fn response_for_older_version() {
+        let post: ApiPost = serde_json::from_value(json!({
+            "apiVersion": "1.0",
+            "id": "req-8",
+            "method": "get",
+            "data": {"description": "north wall"}
+        }))
+        .unwrap();
+        assert!(post.validate().is_ok());
+        let response = ApiPostResponse::ok(&post, Some(post.data().clone()));
+        assert_eq!(
+            response
+                .to_value_for(post.source_version().unwrap())
+                .unwrap(),
+            json!({
+                "apiVersion": "1.0",
+                "id": "req-8",
+                "method": "get",
+                "data": {"description": "north wall"}
+            })
+        );
+
+        // Only parsing checks the version, validate() looks at the version it was sent with
+        let mut post = ApiPost::new(Method::Get, post.into_data());
+        post.source_version = Some("0.9".to_string());
+        assert!(post.validate().is_err());
+    }
+
+    #[test]
+    fn rejects_unsupported_version() {
+        let error = ApiPost::parse(json!({
+            "apiVersion": "2.0",
+            "id": "req-1",
+            "method": "get",
+            "data": {"kind": "NameChange", "name": "x", "description": null}
+        }))
+        .unwrap_err();
+        assert_eq!(error.code, 400);
+        assert_eq!(error.errors[0].reason, "unsupportedApiVersion");
+        assert!(validate_api_version("1.3").is_ok());
+        assert!(validate_api_version("").is_err());
+    }
 }
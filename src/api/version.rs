@@ -0,0 +1,188 @@
+use serde_json::{json, Value};
+
+use super::error::{ApiError, ErrorItem};
+use super::{kind, API_VERSION};
+
+// Oldest first, the last one is API_VERSION
+pub const SUPPORTED_API_VERSIONS: &[&str] = &["1.0", "1.1"];
+
+// Rewrites the data of a message between two adjacent versions
+struct Migration {
+    from: &'static str,
+    to: &'static str,
+    upgrade: fn(&mut Value),
+    downgrade: fn(&mut Value),
+}
+
+const MIGRATIONS: &[Migration] = &[
+    // 1.1: DescriptionChange is replaced by NameChange with only a description
+    Migration {
+        from: "1.0",
+        to: "1.1",
+        upgrade: description_to_name_change,
+        downgrade: name_change_to_description,
+    },
+];
+
+// Maps the apiVersion of a message to a supported version. Newer minor versions of
+// the current major version only add fields, they are read as API_VERSION.
+pub fn resolve(api_version: &str) -> Result<&'static str, ApiError> {
+    fn parts(version: &str) -> Option<(u32, u32)> {
+        let (major, minor) = version.split_once('.').unwrap_or((version, "0"));
+        Some((major.parse().ok()?, minor.parse().ok()?))
+    }
+    if let Some(version) = SUPPORTED_API_VERSIONS.iter().find(|v| **v == api_version) {
+        return Ok(version);
+    }
+    match (parts(api_version), parts(API_VERSION)) {
+        (Some((major, minor)), Some((current_major, current_minor)))
+            if major == current_major && minor > current_minor =>
+        {
+            Ok(API_VERSION)
+        }
+        _ => Err(unsupported(api_version)),
+    }
+}
+
+pub fn is_supported(api_version: &str) -> bool {
+    resolve(api_version).is_ok()
+}
+
+// Newest version both sides support, for clients that list the versions they accept
+pub fn negotiate(accepted: &[&str]) -> Option<&'static str> {
+    accepted
+        .iter()
+        .filter_map(|v| resolve(v).ok())
+        .max_by_key(|v| SUPPORTED_API_VERSIONS.iter().position(|s| s == v))
+}
+
+// Upgrades a message envelope to API_VERSION
+pub fn upgrade(mut message: Value) -> Result<Value, ApiError> {
+    let from = message_version(&message)?;
+    for migration in migrations(from, API_VERSION) {
+        if let Some(data) = message.get_mut("data") {
+            (migration.upgrade)(data);
+        }
+    }
+    message["apiVersion"] = API_VERSION.into();
+    Ok(message)
+}
+
+// Same as upgrade, for envelopes where data is a list of items (ApiPostList)
+pub fn upgrade_list(mut message: Value) -> Result<Value, ApiError> {
+    let from = message_version(&message)?;
+    for migration in migrations(from, API_VERSION) {
+        if let Some(Value::Array(items)) = message.get_mut("data") {
+            items.iter_mut().for_each(migration.upgrade);
+        }
+    }
+    message["apiVersion"] = API_VERSION.into();
+    Ok(message)
+}
+
+// Rewrites a message envelope of API_VERSION for a client on an older version
+pub fn downgrade(mut message: Value, target: &str) -> Result<Value, ApiError> {
+    let target = resolve(target)?;
+    for migration in migrations(target, API_VERSION).iter().rev() {
+        if let Some(data) = message.get_mut("data") {
+            (migration.downgrade)(data);
+        }
+    }
+    message["apiVersion"] = target.into();
+    Ok(message)
+}
+
+pub(crate) fn unsupported(api_version: &str) -> ApiError {
+    let reason = ErrorItem::new(
+        "global",
+        "unsupportedApiVersion",
+        &format!(
+            "Supported apiVersions are {}",
+            SUPPORTED_API_VERSIONS.join(", ")
+        ),
+    )
+    .location("apiVersion", "body");
+    ApiError::bad_request(&format!("Unsupported apiVersion {api_version}")).error(reason)
+}
+
+fn message_version(message: &Value) -> Result<&'static str, ApiError> {
+    match message.get("apiVersion") {
+        Some(Value::String(version)) => resolve(version),
+        Some(version) => Err(unsupported(&version.to_string())),
+        None => Err(unsupported("(missing)")),
+    }
+}
+
+fn migrations(from: &str, to: &str) -> &'static [Migration] {
+    let start = MIGRATIONS.iter().position(|m| m.from == from);
+    let end = MIGRATIONS.iter().position(|m| m.to == to);
+    match (start, end) {
+        (Some(start), Some(end)) if start <= end => &MIGRATIONS[start..=end],
+        _ => &[],
+    }
+}
+
+fn description_to_name_change(data: &mut Value) {
+    let Value::Object(map) = data else {
+        return;
+    };
+    let is_description = match map.get("kind").and_then(Value::as_str) {
+        Some(k) => k == kind::DESCRIPTION_CHANGE,
+        None => map.len() == 1 && map.contains_key("description"),
+    };
+    if is_description {
+        *data = json!({
+            "kind": kind::NAME_CHANGE,
+            "name": null,
+            "description": map.get("description").cloned().unwrap_or(Value::Null),
+        });
+    }
+}
+
+fn name_change_to_description(data: &mut Value) {
+    let is_description_only = data.get("kind").and_then(Value::as_str) == Some(kind::NAME_CHANGE)
+        && data.get("name").is_none_or(Value::is_null)
+        && data.get("description").is_some_and(Value::is_string);
+    if is_description_only {
+        *data = json!({"description": data["description"].clone()});
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn resolve_versions() {
+        assert_eq!(resolve("1.0").unwrap(), "1.0");
+        assert_eq!(resolve("1.3").unwrap(), API_VERSION);
+        assert!(resolve("0.9").is_err());
+        let error = resolve("2.0").unwrap_err();
+        assert_eq!(error.errors[0].reason, "unsupportedApiVersion");
+        assert_eq!(negotiate(&["0.9", "1.0"]), Some("1.0"));
+        assert_eq!(negotiate(&["1.1", "1.0"]), Some("1.1"));
+        assert_eq!(negotiate(&["2.0"]), None);
+    }
+
+    #[test]
+    fn description_change_migration() {
+        let old = json!({"apiVersion": "1.0", "data": {"description": "north wall"}});
+        let new = upgrade(old).unwrap();
+        assert_eq!(
+            new,
+            json!({"apiVersion": "1.1", "data": {"kind": "NameChange", "name": null, "description": "north wall"}})
+        );
+        assert_eq!(
+            downgrade(new, "1.0").unwrap(),
+            json!({"apiVersion": "1.0", "data": {"description": "north wall"}})
+        );
+        let tagged = json!({"apiVersion": "1.0", "data": {"kind": "DescriptionChange", "description": "north wall"}});
+        assert_eq!(upgrade(tagged).unwrap()["data"]["kind"], "NameChange");
+        let rename = json!({"apiVersion": "1.1", "data": {"kind": "NameChange", "name": "A", "description": null}});
+        assert_eq!(
+            downgrade(rename.clone(), "1.0").unwrap()["data"],
+            rename["data"]
+        );
+        assert!(upgrade(json!({"data": {}})).is_err());
+    }
+}
@@ -103,7 +103,7 @@ mod test {
             .next()
             .unwrap()
             .unwrap();
-        assert_eq!(jsonmessage.data.kind(), "Neighbor");
-        assert_eq!(jsonmessage.data.len(), 7);
+        assert_eq!(jsonmessage.data().unwrap().kind(), "Neighbor");
+        assert_eq!(jsonmessage.data().unwrap().len(), 7);
     }
 }
@@ -0,0 +1,424 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fmt::Display;
+
+// Commands are sent to the device over CoAP as:
+//
+// | opcode: u8 | key: u32 le | expires: i32 le (unix time) | parameters |
+//
+// Reboot              1  -
+// Identify            2  duration: u16 le (seconds)
+// FactoryReset        3  -
+// StartFirmwareUpdate 4  fwver: u32 le, size: u32 le
+// SetLed              5  channel: u16 le, level: u8 (percent)
+//
+// The device answers with a CommandResult:
+//
+// | key: u32 le | status: u8 | error_code: u16 le |
+//
+// Deserializing checks the parameters the same way the binary decoder does
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum DeviceCommand {
+    Reboot,
+    Identify {
+        duration: u16,
+    },
+    FactoryReset,
+    StartFirmwareUpdate {
+        fwver: u32,
+        size: u32,
+    },
+    SetLed {
+        channel: u16,
+        #[cfg_attr(feature = "schema", schemars(range(max = 100)))]
+        level: u8,
+    },
+}
+impl DeviceCommand {
+    pub const MAX_LED_LEVEL: u8 = 100;
+
+    // None when level is above MAX_LED_LEVEL percent
+    pub fn set_led(channel: u16, level: u8) -> Option<DeviceCommand> {
+        let command = DeviceCommand::SetLed { channel, level };
+        command.is_valid().then_some(command)
+    }
+    pub fn is_valid(&self) -> bool {
+        match self {
+            DeviceCommand::SetLed { level, .. } => *level <= Self::MAX_LED_LEVEL,
+            _ => true,
+        }
+    }
+    pub fn opcode(&self) -> u8 {
+        match self {
+            DeviceCommand::Reboot => 1,
+            DeviceCommand::Identify { .. } => 2,
+            DeviceCommand::FactoryReset => 3,
+            DeviceCommand::StartFirmwareUpdate { .. } => 4,
+            DeviceCommand::SetLed { .. } => 5,
+        }
+    }
+    fn params(&self) -> Vec<u8> {
+        match self {
+            DeviceCommand::Reboot | DeviceCommand::FactoryReset => vec![],
+            DeviceCommand::Identify { duration } => duration.to_le_bytes().to_vec(),
+            DeviceCommand::StartFirmwareUpdate { fwver, size } => {
+                [fwver.to_le_bytes(), size.to_le_bytes()].concat()
+            }
+            DeviceCommand::SetLed { channel, level } => {
+                let [c1, c2] = channel.to_le_bytes();
+                vec![c1, c2, *level]
+            }
+        }
+    }
+    fn from_params(opcode: u8, params: &[u8]) -> Option<DeviceCommand> {
+        let command = match (opcode, params) {
+            (1, []) => DeviceCommand::Reboot,
+            (2, [d1, d2]) => DeviceCommand::Identify {
+                duration: u16::from_le_bytes([*d1, *d2]),
+            },
+            (3, []) => DeviceCommand::FactoryReset,
+            (4, [v1, v2, v3, v4, s1, s2, s3, s4]) => DeviceCommand::StartFirmwareUpdate {
+                fwver: u32::from_le_bytes([*v1, *v2, *v3, *v4]),
+                size: u32::from_le_bytes([*s1, *s2, *s3, *s4]),
+            },
+            (5, [c1, c2, level]) => DeviceCommand::SetLed {
+                channel: u16::from_le_bytes([*c1, *c2]),
+                level: *level,
+            },
+            _ => return None,
+        };
+        command.is_valid().then_some(command)
+    }
+}
+impl<'de> Deserialize<'de> for DeviceCommand {
+    fn deserialize<D>(deserializer: D) -> Result<DeviceCommand, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        #[serde(tag = "type", rename_all = "camelCase")]
+        enum Fields {
+            Reboot,
+            Identify { duration: u16 },
+            FactoryReset,
+            StartFirmwareUpdate { fwver: u32, size: u32 },
+            SetLed { channel: u16, level: u8 },
+        }
+        let command = match Fields::deserialize(deserializer)? {
+            Fields::Reboot => DeviceCommand::Reboot,
+            Fields::Identify { duration } => DeviceCommand::Identify { duration },
+            Fields::FactoryReset => DeviceCommand::FactoryReset,
+            Fields::StartFirmwareUpdate { fwver, size } => {
+                DeviceCommand::StartFirmwareUpdate { fwver, size }
+            }
+            Fields::SetLed { channel, level } => DeviceCommand::SetLed { channel, level },
+        };
+        if !command.is_valid() {
+            return Err(serde::de::Error::custom(format!(
+                "invalid {command:?}, level is at most {}",
+                DeviceCommand::MAX_LED_LEVEL
+            )));
+        }
+        Ok(command)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(
+    feature = "schema",
+    derive(schemars::JsonSchema),
+    schemars(transform = crate::schema::KindTag("Command"))
+)]
+#[serde(rename_all = "camelCase", tag = "kind", rename = "Command")]
+pub struct Command {
+    // Repeating a command with the same key has no further effect on the device
+    pub key: u32,
+    pub expires: DateTime<Utc>,
+    pub command: DeviceCommand,
+}
+impl Command {
+    pub fn new(key: u32, command: DeviceCommand, expires: DateTime<Utc>) -> Self {
+        Self {
+            key,
+            expires,
+            command,
+        }
+    }
+    pub fn with_ttl(key: u32, command: DeviceCommand, ttl: std::time::Duration) -> Self {
+        let ttl = chrono::Duration::from_std(ttl).unwrap_or(chrono::Duration::zero());
+        Self::new(key, command, Utc::now() + ttl)
+    }
+    pub fn is_expired(&self, now: DateTime<Utc>) -> bool {
+        now >= self.expires
+    }
+    // Fails for commands the device would reject, and for an expiry before 1970 or past 2038
+    pub fn to_payload(&self) -> Result<Vec<u8>, CommandError> {
+        if !self.command.is_valid() {
+            return Err(CommandError::InvalidParameters(self.command));
+        }
+        let expires = i32::try_from(self.expires.timestamp())
+            .ok()
+            .filter(|e| *e >= 0)
+            .ok_or(CommandError::ExpiryOutOfRange(self.expires))?;
+        let mut ret = vec![self.command.opcode()];
+        ret.extend_from_slice(&self.key.to_le_bytes());
+        ret.extend_from_slice(&expires.to_le_bytes());
+        ret.extend(self.command.params());
+        Ok(ret)
+    }
+    pub fn from_payload(payload: &[u8]) -> Option<Command> {
+        let [opcode, k1, k2, k3, k4, e1, e2, e3, e4, params @ ..] = payload else {
+            return None;
+        };
+        let expires = i32::from_le_bytes([*e1, *e2, *e3, *e4]);
+        Some(Command {
+            key: u32::from_le_bytes([*k1, *k2, *k3, *k4]),
+            expires: DateTime::from_timestamp(expires.into(), 0)?,
+            command: DeviceCommand::from_params(*opcode, params)?,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CommandError {
+    InvalidParameters(DeviceCommand),
+    // The expiry does not fit the i32 unix time of the payload
+    ExpiryOutOfRange(DateTime<Utc>),
+}
+impl Display for CommandError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CommandError::InvalidParameters(command) => {
+                write!(f, "Invalid parameters for {command:?}")
+            }
+            CommandError::ExpiryOutOfRange(expires) => {
+                write!(f, "Expiry {expires} can not be sent to the device")
+            }
+        }
+    }
+}
+impl std::error::Error for CommandError {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(into = "u8", from = "u8")]
+pub enum CommandStatus {
+    Accepted,
+    Rejected,
+    Completed,
+    Unknown(u8),
+}
+impl From<u8> for CommandStatus {
+    fn from(value: u8) -> Self {
+        match value {
+            1 => CommandStatus::Accepted,
+            2 => CommandStatus::Rejected,
+            3 => CommandStatus::Completed,
+            n => CommandStatus::Unknown(n),
+        }
+    }
+}
+impl From<CommandStatus> for u8 {
+    fn from(value: CommandStatus) -> Self {
+        match value {
+            CommandStatus::Accepted => 1,
+            CommandStatus::Rejected => 2,
+            CommandStatus::Completed => 3,
+            CommandStatus::Unknown(n) => n,
+        }
+    }
+}
+impl Display for CommandStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CommandStatus::Accepted => write!(f, "Accepted"),
+            CommandStatus::Rejected => write!(f, "Rejected"),
+            CommandStatus::Completed => write!(f, "Completed"),
+            CommandStatus::Unknown(n) => write!(f, "Unknown: {n}"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(
+    feature = "schema",
+    derive(schemars::JsonSchema),
+    schemars(transform = crate::schema::KindTag("CommandResult"))
+)]
+#[serde(rename_all = "camelCase", tag = "kind", rename = "CommandResult")]
+pub struct CommandResult {
+    pub key: u32,
+    pub status: CommandStatus,
+    // 0 when there is no error, otherwise a zephyr errno
+    pub error_code: u16,
+}
+impl CommandResult {
+    pub const PAYLOAD_LEN: usize = 7;
+
+    pub fn new(key: u32, status: CommandStatus, error_code: u16) -> Self {
+        Self {
+            key,
+            status,
+            error_code,
+        }
+    }
+    pub fn accepted(key: u32) -> Self {
+        Self::new(key, CommandStatus::Accepted, 0)
+    }
+    pub fn rejected(key: u32, error_code: u16) -> Self {
+        Self::new(key, CommandStatus::Rejected, error_code)
+    }
+    pub fn completed(key: u32) -> Self {
+        Self::new(key, CommandStatus::Completed, 0)
+    }
+    pub fn is_error(&self) -> bool {
+        self.status == CommandStatus::Rejected || self.error_code != 0
+    }
+    pub fn to_payload(&self) -> [u8; Self::PAYLOAD_LEN] {
+        let [k1, k2, k3, k4] = self.key.to_le_bytes();
+        let [e1, e2] = self.error_code.to_le_bytes();
+        [k1, k2, k3, k4, self.status.into(), e1, e2]
+    }
+    pub fn from_payload(payload: &[u8]) -> Option<CommandResult> {
+        let [k1, k2, k3, k4, status, e1, e2] = payload else {
+            return None;
+        };
+        Some(CommandResult {
+            key: u32::from_le_bytes([*k1, *k2, *k3, *k4]),
+            status: (*status).into(),
+            error_code: u16::from_le_bytes([*e1, *e2]),
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    fn expires() -> DateTime<Utc> {
+        DateTime::from_timestamp(1691096258, 0).unwrap()
+    }
+
+    #[test]
+    fn binary_roundtrip() {
+        for command in [
+            DeviceCommand::Reboot,
+            DeviceCommand::Identify { duration: 30 },
+            DeviceCommand::FactoryReset,
+            DeviceCommand::StartFirmwareUpdate {
+                fwver: 0x01040200,
+                size: 262144,
+            },
+            DeviceCommand::SetLed {
+                channel: 1,
+                level: 40,
+            },
+        ] {
+            let command = Command::new(7, command, expires());
+            assert_eq!(
+                Command::from_payload(&command.to_payload().unwrap()),
+                Some(command)
+            );
+        }
+        let command = Command::new(
+            7,
+            DeviceCommand::SetLed {
+                channel: 1,
+                level: 40,
+            },
+            expires(),
+        );
+        let payload = command.to_payload().unwrap();
+        assert_eq!(
+            payload,
+            vec![5, 7, 0, 0, 0, 0xc2, 0x14, 0xcc, 0x64, 1, 0, 40]
+        );
+        assert_eq!(Command::from_payload(&payload[..11]), None);
+    }
+
+    #[test]
+    fn expiry_is_checked() {
+        for timestamp in [-1, i64::from(i32::MAX) + 1] {
+            let expires = DateTime::from_timestamp(timestamp, 0).unwrap();
+            assert_eq!(
+                Command::new(7, DeviceCommand::Reboot, expires).to_payload(),
+                Err(CommandError::ExpiryOutOfRange(expires))
+            );
+        }
+        let expires = DateTime::from_timestamp(i32::MAX.into(), 0).unwrap();
+        let command = Command::new(7, DeviceCommand::Reboot, expires);
+        assert_eq!(
+            Command::from_payload(&command.to_payload().unwrap()),
+            Some(command)
+        );
+    }
+
+    #[test]
+    fn led_level_is_checked() {
+        assert_eq!(DeviceCommand::set_led(1, 101), None);
+        let invalid = DeviceCommand::SetLed {
+            channel: 1,
+            level: 200,
+        };
+        assert_eq!(
+            Command::new(7, invalid, expires()).to_payload(),
+            Err(CommandError::InvalidParameters(invalid))
+        );
+        let mut payload = Command::new(7, DeviceCommand::set_led(1, 100).unwrap(), expires())
+            .to_payload()
+            .unwrap();
+        payload[11] = 200;
+        assert_eq!(Command::from_payload(&payload), None);
+        let err = serde_json::from_value::<DeviceCommand>(
+            json!({"type": "setLed", "channel": 1, "level": 200}),
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("level is at most 100"), "{err}");
+        assert_eq!(
+            serde_json::from_value::<DeviceCommand>(json!({"type": "identify", "duration": 5}))
+                .unwrap(),
+            DeviceCommand::Identify { duration: 5 }
+        );
+    }
+
+    #[test]
+    fn json_encoding() {
+        let command = Command::new(
+            7,
+            DeviceCommand::SetLed {
+                channel: 1,
+                level: 40,
+            },
+            expires(),
+        );
+        let value = serde_json::to_value(command).unwrap();
+        assert_eq!(
+            value,
+            json!({
+                "kind": "Command",
+                "key": 7,
+                "expires": "2023-08-03T20:57:38Z",
+                "command": {"type": "setLed", "channel": 1, "level": 40}
+            })
+        );
+        assert_eq!(serde_json::from_value::<Command>(value).unwrap(), command);
+        assert!(command.is_expired(expires()));
+    }
+
+    #[test]
+    fn result_roundtrip() {
+        let result = CommandResult::rejected(7, 22);
+        assert_eq!(
+            CommandResult::from_payload(&result.to_payload()),
+            Some(result)
+        );
+        assert!(result.is_error());
+        assert_eq!(
+            serde_json::to_value(CommandResult::completed(7)).unwrap(),
+            json!({"kind": "CommandResult", "key": 7, "status": 3, "errorCode": 0})
+        );
+    }
+}
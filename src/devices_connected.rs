@@ -1,6 +1,11 @@
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[cfg_attr(
+    feature = "schema",
+    derive(schemars::JsonSchema),
+    schemars(rename = "ApiDevicesConnected", transform = crate::schema::KindTag("devicesConnected"))
+)]
 #[serde(rename_all = "camelCase", tag = "kind", rename = "devicesConnected")]
 pub struct ApiDevicesConnected {
     current_item_count: usize,
@@ -57,6 +62,7 @@ impl ApiDevicesConnected {
     }
 }
 #[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize, Hash, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct DevicesConnected {
     device_id: DevicesConnectedTypes,
     idx: u16,
@@ -96,6 +102,19 @@ pub enum DevicesConnectedTypes {
     DoorSensor,
     Other(u16),
 }
+#[cfg(feature = "schema")]
+impl schemars::JsonSchema for DevicesConnectedTypes {
+    fn schema_name() -> std::borrow::Cow<'static, str> {
+        "DevicesConnectedTypes".into()
+    }
+    fn json_schema(_: &mut schemars::SchemaGenerator) -> schemars::Schema {
+        let known = (0..=u8::MAX.into())
+            .map(DevicesConnectedTypes::from)
+            .filter(|t| !matches!(t, DevicesConnectedTypes::Other(_)))
+            .map(|t| (u16::from(t).into(), format!("{t:?}")));
+        crate::schema::wire_enum("Peripheral type", known)
+    }
+}
 impl From<u16> for DevicesConnectedTypes {
     fn from(value: u16) -> Self {
         match value {
@@ -16,6 +16,7 @@ use telys::TeLys;
 use crate::{
     devices_connected::DevicesConnected,
     devs::hb::{DevStatus, DevType},
+    firmware::{DeviceFirmware, FirmwareVersion},
 };
 pub trait Dev {
     fn dev_id(&self) -> String {
@@ -34,11 +35,17 @@ pub trait Dev {
         }
     }
     fn dev_type(&self) -> &'static str;
-    fn fwver(&self) -> Option<[u8; 4]> {
+    fn firmware(&self) -> Option<&DeviceFirmware> {
         None
     }
+    fn firmware_version(&self) -> Option<FirmwareVersion> {
+        self.firmware()?.version()
+    }
+    fn fwver(&self) -> Option<[u8; 4]> {
+        self.firmware()?.fwver.map(|v| v.to_be_bytes())
+    }
     fn fwver_name(&self) -> Option<String> {
-        None
+        self.firmware()?.fwver_name.clone()
     }
     fn status(&self) -> crate::devs::hb::DevStatus {
         crate::devs::hb::DevStatus::Unknown(0)
@@ -57,6 +64,7 @@ pub trait Battery {
 }
 
 #[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub enum Device {
     Soil(SoilSensor),
     Env(EnvSensor),
@@ -65,6 +73,11 @@ pub enum Device {
     TeLys(TeLys),
 }
 #[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[cfg_attr(
+    feature = "schema",
+    derive(schemars::JsonSchema),
+    schemars(transform = crate::schema::KindTag("NameChange"))
+)]
 #[serde(rename_all = "camelCase", tag = "kind")]
 pub struct NameChange {
     name: Option<String>,
@@ -99,7 +112,10 @@ impl NameChange {
         self.description = Some(description);
     }
 }
+// Sent without a kind, as 1.0 clients expect. A "kind": "DescriptionChange" on input is
+// accepted and ignored.
 #[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct DescriptionChange {
     description: String,
 }
@@ -116,6 +132,7 @@ impl DescriptionChange {
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct SensorReading {
     pub h: i32,
     pub l: i32,
@@ -173,6 +190,15 @@ impl Dev for Device {
             Device::TeLys(telys) => telys.display_name(),
         }
     }
+    fn firmware(&self) -> Option<&DeviceFirmware> {
+        match self {
+            Device::Soil(sensor) => sensor.firmware(),
+            Device::Env(sensor) => sensor.firmware(),
+            Device::Router(router) => router.firmware(),
+            Device::Led(panel) => panel.firmware(),
+            Device::TeLys(telys) => telys.firmware(),
+        }
+    }
     fn dev_type(&self) -> &'static str {
         match self {
             Device::Soil(_) => "Soil Sensor",
@@ -185,6 +211,11 @@ impl Dev for Device {
 }
 
 #[derive(Debug, Clone, PartialEq, Deserialize, Serialize, Hash, Eq)]
+#[cfg_attr(
+    feature = "schema",
+    derive(schemars::JsonSchema),
+    schemars(transform = crate::schema::KindTag("DevInfo"))
+)]
 #[serde(rename_all = "camelCase", tag = "kind")]
 pub struct DevInfo {
     pub dev_sn: u64,
@@ -194,8 +225,12 @@ pub struct DevInfo {
     pub status: DevStatus,
     pub last_active: DateTime<Utc>,
     pub dev_type: DevType,
-    pub fwver: Option<u32>,
-    pub fwver_name: Option<String>,
+    #[serde(flatten, with = "crate::firmware::wire::camel_case")]
+    #[cfg_attr(
+        feature = "schema",
+        schemars(with = "crate::firmware::wire::camel_case::Fields")
+    )]
+    pub firmware: DeviceFirmware,
     pub uptime: Option<i64>,
     pub connected_devices: Vec<DevicesConnected>,
 }
@@ -204,10 +239,13 @@ impl DevInfo {
         self.uptime.map(|u| u as u32)
     }
     pub fn set_fwver(&mut self, fwver: u32) {
-        self.fwver = Some(fwver);
+        self.firmware.fwver = Some(fwver);
     }
     pub fn set_fwtag(&mut self, fwtag: String) {
-        self.fwver_name = Some(fwtag);
+        self.firmware.fwver_name = Some(fwtag);
+    }
+    pub fn set_firmware_version(&mut self, version: &FirmwareVersion) {
+        self.firmware = version.into();
     }
     pub fn status(&self) -> DevStatus {
         self.status.map_active(self.last_active())
@@ -228,8 +266,7 @@ impl DevInfo {
             status: DevStatus::Offline,
             last_active: Utc::now(),
             dev_type: DevType::Unknown(255),
-            fwver: None,
-            fwver_name: None,
+            firmware: DeviceFirmware::default(),
             uptime: None,
             connected_devices: vec![],
         }
@@ -249,10 +286,7 @@ impl Dev for DevInfo {
     fn dev_type(&self) -> &'static str {
         self.dev_type.into()
     }
-    fn fwver(&self) -> Option<[u8; 4]> {
-        self.fwver.map(|v| v.to_be_bytes())
-    }
-    fn fwver_name(&self) -> Option<String> {
-        self.fwver_name.clone()
+    fn firmware(&self) -> Option<&DeviceFirmware> {
+        Some(&self.firmware)
     }
 }
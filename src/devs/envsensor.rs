@@ -1,8 +1,10 @@
 use crate::devs::SensorReading;
+use crate::firmware::DeviceFirmware;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use std::time::Duration;
 #[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct EnvSensor {
     id: u64,
     pub name: Option<String>,
@@ -11,8 +13,12 @@ pub struct EnvSensor {
     pub battery: Option<SensorReading>,
     pub uptime: Option<u32>,
     pub last_active: DateTime<Utc>,
-    pub fwver: Option<u32>,
-    pub fwver_name: Option<String>,
+    #[serde(flatten, with = "crate::firmware::wire::snake_case")]
+    #[cfg_attr(
+        feature = "schema",
+        schemars(with = "crate::firmware::wire::snake_case::Fields")
+    )]
+    pub firmware: DeviceFirmware,
     #[serde(default)]
     pub status: crate::devs::hb::DevStatus,
 }
@@ -26,8 +32,7 @@ impl EnvSensor {
             battery: None,
             uptime: None,
             last_active: Utc::now(),
-            fwver: None,
-            fwver_name: None,
+            firmware: DeviceFirmware::default(),
             status: crate::devs::hb::DevStatus::Unknown(0),
         }
     }
@@ -61,11 +66,8 @@ impl super::Dev for EnvSensor {
         "Environmental Sensor"
     }
 
-    fn fwver(&self) -> Option<[u8; 4]> {
-        self.fwver.map(|v| v.to_be_bytes())
-    }
-    fn fwver_name(&self) -> Option<String> {
-        self.fwver_name.clone()
+    fn firmware(&self) -> Option<&DeviceFirmware> {
+        Some(&self.firmware)
     }
     fn status(&self) -> crate::devs::hb::DevStatus {
         self.status
@@ -3,7 +3,10 @@ use serde::{Deserialize, Serialize};
 
 use std::fmt::Display;
 
+use crate::firmware::FirmwareVersion;
+
 #[derive(Serialize, Deserialize, PartialEq, Debug)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct HeartBeatZephyr {
     pub id: i64,
     pub fwver: u32,
@@ -12,12 +15,18 @@ pub struct HeartBeatZephyr {
     pub rloc16: u16,
 }
 #[derive(Serialize, Deserialize, PartialEq, Debug, Default, Clone, Copy)]
+#[cfg_attr(
+    feature = "schema",
+    derive(schemars::JsonSchema),
+    schemars(transform = crate::schema::KindTag("HeartBeat"))
+)]
 #[serde(tag = "kind")]
 pub struct HeartBeat {
     #[serde(
         serialize_with = "serialize_u64_as_string",
         deserialize_with = "deserialize_string_as_u64"
     )]
+    #[cfg_attr(feature = "schema", schemars(with = "String"))]
     pub id: u64,
     #[serde(rename = "firmware")]
     pub fwver: u32,
@@ -81,6 +90,14 @@ impl HeartBeat {
         self.fwver = fwver;
         self
     }
+    // The heartbeat carries no pre-release tag, only the u32
+    pub fn firmware(mut self, version: &FirmwareVersion) -> Self {
+        self.fwver = version.fwver();
+        self
+    }
+    pub fn firmware_version(&self) -> FirmwareVersion {
+        self.fwver.into()
+    }
 
     pub fn devtype(mut self, devtype: u8) -> Self {
         self.devtype = devtype.into();
@@ -141,6 +158,19 @@ impl<'a> Serialize for DevType {
         u8::from(*self).serialize(serializer)
     }
 }
+#[cfg(feature = "schema")]
+impl schemars::JsonSchema for DevType {
+    fn schema_name() -> std::borrow::Cow<'static, str> {
+        "DevType".into()
+    }
+    fn json_schema(_: &mut schemars::SchemaGenerator) -> schemars::Schema {
+        let known = (0..=u8::MAX)
+            .map(DevType::from)
+            .filter(|t| !matches!(t, DevType::Unknown(_)))
+            .map(|t| (u8::from(t).into(), t.to_string()));
+        crate::schema::wire_enum("Device type", known)
+    }
+}
 impl<'a> Deserialize<'a> for DevType {
     fn deserialize<D>(deserializer: D) -> Result<DevType, D::Error>
     where
@@ -276,6 +306,19 @@ impl Serialize for DevStatus {
         u8::from(*self).serialize(serializer)
     }
 }
+#[cfg(feature = "schema")]
+impl schemars::JsonSchema for DevStatus {
+    fn schema_name() -> std::borrow::Cow<'static, str> {
+        "DevStatus".into()
+    }
+    fn json_schema(_: &mut schemars::SchemaGenerator) -> schemars::Schema {
+        let known = (0..=u8::MAX)
+            .map(DevStatus::from)
+            .filter(|s| !matches!(s, DevStatus::Unknown(_)))
+            .map(|s| (u8::from(s).into(), s.to_string()));
+        crate::schema::wire_enum("Device status", known)
+    }
+}
 impl<'a> Deserialize<'a> for DevStatus {
     fn deserialize<D>(deserializer: D) -> Result<DevStatus, D::Error>
     where
@@ -3,18 +3,25 @@ use serde::{Deserialize, Serialize};
 use std::time::Duration;
 
 use crate::devices_connected::DevicesConnectedTypes;
+use crate::firmware::DeviceFirmware;
 #[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct LedPanel {
     id: u64,
     pub name: Option<String>,
     pub uptime: Option<u32>,
     pub last_active: DateTime<Utc>,
-    pub fwver: Option<u32>,
-    pub fwver_name: Option<String>,
+    #[serde(flatten, with = "crate::firmware::wire::snake_case")]
+    #[cfg_attr(
+        feature = "schema",
+        schemars(with = "crate::firmware::wire::snake_case::Fields")
+    )]
+    pub firmware: DeviceFirmware,
     pub status: crate::devs::hb::DevStatus,
     connected_devices: Vec<(DevicesConnectedTypes, u16)>,
 }
 impl LedPanel {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         id: u64,
         name: Option<String>,
@@ -30,8 +37,7 @@ impl LedPanel {
             name,
             uptime,
             last_active,
-            fwver,
-            fwver_name,
+            firmware: DeviceFirmware::new(fwver, fwver_name),
             status,
             connected_devices,
         }
@@ -58,11 +64,8 @@ impl super::Dev for LedPanel {
         "LED Panel"
     }
 
-    fn fwver(&self) -> Option<[u8; 4]> {
-        self.fwver.map(|v| v.to_be_bytes())
-    }
-    fn fwver_name(&self) -> Option<String> {
-        self.fwver_name.clone()
+    fn firmware(&self) -> Option<&DeviceFirmware> {
+        Some(&self.firmware)
     }
     fn status(&self) -> crate::devs::hb::DevStatus {
         self.status.map_active(self.last_active)
@@ -1,14 +1,20 @@
+use crate::firmware::DeviceFirmware;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use std::time::Duration;
 #[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct Router {
     id: u64,
     name: Option<String>,
     uptime: Option<u32>,
     last_active: DateTime<Utc>,
-    fwver: Option<u32>,
-    fwver_name: Option<String>,
+    #[serde(flatten, with = "crate::firmware::wire::snake_case")]
+    #[cfg_attr(
+        feature = "schema",
+        schemars(with = "crate::firmware::wire::snake_case::Fields")
+    )]
+    firmware: DeviceFirmware,
     status: crate::devs::hb::DevStatus,
 }
 impl Router {
@@ -26,8 +32,7 @@ impl Router {
             name,
             uptime,
             last_active,
-            fwver,
-            fwver_name,
+            firmware: DeviceFirmware::new(fwver, fwver_name),
             status,
         }
     }
@@ -58,11 +63,8 @@ impl super::Dev for Router {
         "Router"
     }
 
-    fn fwver(&self) -> Option<[u8; 4]> {
-        self.fwver.map(|v| v.to_be_bytes())
-    }
-    fn fwver_name(&self) -> Option<String> {
-        self.fwver_name.clone()
+    fn firmware(&self) -> Option<&DeviceFirmware> {
+        Some(&self.firmware)
     }
     fn status(&self) -> crate::devs::hb::DevStatus {
         self.status.map_active(self.last_active)
@@ -1,10 +1,12 @@
 use super::Battery;
 use super::Dev;
 use crate::devs::SensorReading;
+use crate::firmware::DeviceFirmware;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use std::time::Duration;
 #[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[serde(rename_all = "camelCase")]
 pub struct SoilSensor {
     name: Option<String>,
@@ -16,8 +18,12 @@ pub struct SoilSensor {
     battery: Option<SensorReading>,
     uptime: Option<u32>,
     last_active: DateTime<Utc>,
-    fwver_value: Option<u32>,
-    fwver_name: Option<String>,
+    #[serde(flatten, with = "crate::firmware::wire::value_name")]
+    #[cfg_attr(
+        feature = "schema",
+        schemars(with = "crate::firmware::wire::value_name::Fields")
+    )]
+    firmware: DeviceFirmware,
     #[serde(default)]
     status: crate::devs::hb::DevStatus,
 }
@@ -42,8 +48,7 @@ impl SoilSensor {
             battery: None,
             uptime,
             last_active,
-            fwver_value,
-            fwver_name,
+            firmware: DeviceFirmware::new(fwver_value, fwver_name),
             status,
         }
     }
@@ -78,10 +83,10 @@ impl SoilSensor {
         Some(self.battery?.to_float())
     }
     pub fn fwver(&self) -> Option<u32> {
-        self.fwver_value
+        self.firmware.fwver
     }
     pub fn fwver_name(&self) -> Option<&str> {
-        self.fwver_name.as_deref()
+        self.firmware.fwver_name.as_deref()
     }
     pub fn uptime(&self) -> Option<Duration> {
         Some(Duration::from_secs(self.uptime? as u64))
@@ -121,11 +126,8 @@ impl Dev for SoilSensor {
     fn dev_type(&self) -> &'static str {
         "Soil Sensor"
     }
-    fn fwver(&self) -> Option<[u8; 4]> {
-        self.fwver_value.map(|v| v.to_be_bytes())
-    }
-    fn fwver_name(&self) -> Option<String> {
-        self.fwver_name.clone()
+    fn firmware(&self) -> Option<&DeviceFirmware> {
+        Some(&self.firmware)
     }
     fn status(&self) -> crate::devs::hb::DevStatus {
         self.status
@@ -151,8 +153,7 @@ mod tests {
             battery: Some(SensorReading { h: 2, l: 400000 }),
             uptime: None,
             last_active: Utc::now(),
-            fwver_value: None,
-            fwver_name: None,
+            firmware: DeviceFirmware::default(),
             status: crate::devs::hb::DevStatus::RunningOk,
         };
         assert_eq!(sensor.dev_id(), "0x12345678");
@@ -1,8 +1,10 @@
+use crate::firmware::DeviceFirmware;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use std::time::Duration;
 
 #[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[serde(rename_all = "camelCase")]
 pub struct TeLys {
     name: Option<String>,
@@ -10,13 +12,18 @@ pub struct TeLys {
     battery: Option<f32>,
     uptime: Option<u32>,
     last_active: DateTime<Utc>,
-    fwver: Option<u32>,
-    fwver_name: Option<String>,
+    #[serde(flatten, with = "crate::firmware::wire::camel_case")]
+    #[cfg_attr(
+        feature = "schema",
+        schemars(with = "crate::firmware::wire::camel_case::Fields")
+    )]
+    firmware: DeviceFirmware,
     #[serde(default)]
     status: crate::devs::hb::DevStatus,
 }
 
 impl TeLys {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         device_sn: u64,
         name: Option<String>,
@@ -32,8 +39,7 @@ impl TeLys {
             device_sn,
             uptime,
             last_active,
-            fwver,
-            fwver_name,
+            firmware: DeviceFirmware::new(fwver, fwver_name),
             status,
             battery,
         }
@@ -64,11 +70,8 @@ impl super::Dev for TeLys {
         "TeLys"
     }
 
-    fn fwver(&self) -> Option<[u8; 4]> {
-        self.fwver.map(|v| v.to_be_bytes())
-    }
-    fn fwver_name(&self) -> Option<String> {
-        self.fwver_name.clone()
+    fn firmware(&self) -> Option<&DeviceFirmware> {
+        Some(&self.firmware)
     }
     fn status(&self) -> crate::devs::hb::DevStatus {
         self.status
@@ -0,0 +1,350 @@
+pub mod mcuboot;
+pub mod ota;
+pub mod wire;
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::fmt::Display;
+use std::str::FromStr;
+
+// Firmware version, stored on the wire as fwver: u32 (major << 24 | minor << 16 | patch << 8 | build)
+// and fwver_name, the git describe output of the build, e.g. "v1.4.2-rc1-5-g3f2a9c1-dirty".
+//
+// The parts are as wide as in the MCUboot image header, fwver only has a byte for each.
+//
+// Versions order like semver: a pre-release sorts before its release and pre-release tags
+// compare per dot-separated identifier, numbers numerically and before alphanumeric ones.
+// build is compared last. Tags that only differ in leading zeros ("rc.01", "rc.1") are
+// ordered by their text, so only equal versions compare equal.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub struct FirmwareVersion {
+    pub major: u8,
+    pub minor: u8,
+    pub patch: u16,
+    pub build: u32,
+    pub pre: Option<String>,
+}
+impl FirmwareVersion {
+    pub fn new(major: u8, minor: u8, patch: u16) -> Self {
+        Self {
+            major,
+            minor,
+            patch,
+            ..Default::default()
+        }
+    }
+    // Numbers from fwver, pre-release tag from fwver_name. The tag is dropped when the name
+    // is for a different major.minor.patch than fwver.
+    pub fn from_parts(fwver: Option<u32>, fwver_name: Option<&str>) -> Option<FirmwareVersion> {
+        let named = fwver_name.and_then(|n| n.parse::<FirmwareVersion>().ok());
+        match (fwver, named) {
+            (Some(fwver), named) => {
+                let version = FirmwareVersion::from(fwver);
+                let pre = named
+                    .filter(|n| n.core() == version.core())
+                    .and_then(|n| n.pre);
+                Some(FirmwareVersion { pre, ..version })
+            }
+            (None, named) => named,
+        }
+    }
+    fn core(&self) -> (u8, u8, u16) {
+        (self.major, self.minor, self.patch)
+    }
+    // patch and build saturate at 255
+    pub fn fwver(&self) -> u32 {
+        let byte = |n: u32| n.min(u8::MAX.into()) as u8;
+        u32::from_be_bytes([
+            self.major,
+            self.minor,
+            byte(self.patch.into()),
+            byte(self.build),
+        ])
+    }
+    pub fn is_prerelease(&self) -> bool {
+        self.pre.is_some()
+    }
+    pub fn is_older_than(&self, other: &FirmwareVersion) -> bool {
+        self < other
+    }
+}
+// fwver and fwver_name as the device reported them, the device structs keep them so they are
+// sent back unchanged. version() is the FirmwareVersion they describe.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub struct DeviceFirmware {
+    pub fwver: Option<u32>,
+    pub fwver_name: Option<String>,
+}
+impl DeviceFirmware {
+    pub fn new(fwver: Option<u32>, fwver_name: Option<String>) -> Self {
+        Self { fwver, fwver_name }
+    }
+    pub fn version(&self) -> Option<FirmwareVersion> {
+        FirmwareVersion::from_parts(self.fwver, self.fwver_name.as_deref())
+    }
+}
+impl From<&FirmwareVersion> for DeviceFirmware {
+    fn from(value: &FirmwareVersion) -> Self {
+        Self {
+            fwver: Some(value.fwver()),
+            fwver_name: Some(value.to_string()),
+        }
+    }
+}
+impl From<u32> for FirmwareVersion {
+    fn from(value: u32) -> Self {
+        let [major, minor, patch, build] = value.to_be_bytes();
+        Self {
+            major,
+            minor,
+            patch: patch.into(),
+            build: build.into(),
+            pre: None,
+        }
+    }
+}
+impl From<[u8; 4]> for FirmwareVersion {
+    fn from(value: [u8; 4]) -> Self {
+        u32::from_be_bytes(value).into()
+    }
+}
+impl From<&FirmwareVersion> for u32 {
+    fn from(value: &FirmwareVersion) -> Self {
+        value.fwver()
+    }
+}
+impl Ord for FirmwareVersion {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.core()
+            .cmp(&other.core())
+            .then_with(|| match (&self.pre, &other.pre) {
+                (None, None) => Ordering::Equal,
+                (None, Some(_)) => Ordering::Greater,
+                (Some(_), None) => Ordering::Less,
+                (Some(a), Some(b)) => cmp_pre(a, b),
+            })
+            .then_with(|| self.build.cmp(&other.build))
+    }
+}
+fn cmp_pre(a: &str, b: &str) -> Ordering {
+    fn identifier(id: &str) -> (u8, u64, &str) {
+        match id.parse::<u64>() {
+            Ok(n) if id.bytes().all(|c| c.is_ascii_digit()) => (0, n, ""),
+            _ => (1, 0, id),
+        }
+    }
+    let mut ids_a = a.split('.').map(identifier);
+    let mut ids_b = b.split('.').map(identifier);
+    loop {
+        match (ids_a.next(), ids_b.next()) {
+            (None, None) => return a.cmp(b),
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(a), Some(b)) => match a.cmp(&b) {
+                Ordering::Equal => continue,
+                order => return order,
+            },
+        }
+    }
+}
+impl PartialOrd for FirmwareVersion {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+// major.minor.patch[-pre][+build]
+impl Display for FirmwareVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)?;
+        if let Some(pre) = &self.pre {
+            write!(f, "-{pre}")?;
+        }
+        if self.build != 0 {
+            write!(f, "+{}", self.build)?;
+        }
+        Ok(())
+    }
+}
+// Accepts the Display format, major.minor.patch.build and git describe output.
+// For git describe the number of commits since the tag becomes the build number.
+impl FromStr for FirmwareVersion {
+    type Err = ParseVersionError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let err = || ParseVersionError(s.to_string());
+        let version = s.trim();
+        let version = version.strip_prefix('v').unwrap_or(version);
+        let version = version.strip_suffix("-dirty").unwrap_or(version);
+        let (version, build) = match version.split_once('+') {
+            Some((v, build)) => (v, Some(build.parse::<u32>().map_err(|_| err())?)),
+            None => (version, None),
+        };
+        let mut parts: Vec<&str> = version.split('-').collect();
+        let mut commits = None;
+        if let [.., n, hash] = parts[..] {
+            if hash.starts_with('g') && n.chars().all(|c| c.is_ascii_digit()) {
+                commits = Some(n.parse::<u32>().map_err(|_| err())?);
+                parts.truncate(parts.len() - 2);
+            }
+        }
+        let core = parts.first().ok_or_else(err)?;
+        let pre = match parts[1..].join("-") {
+            pre if pre.is_empty() => None,
+            pre => Some(pre),
+        };
+        let (major, minor, patch, numbered_build) = match core.split('.').collect::<Vec<_>>()[..] {
+            [major, minor, patch] => (major, minor, patch, None),
+            [major, minor, patch, build] => (major, minor, patch, Some(build)),
+            _ => return Err(err()),
+        };
+        let major = major.parse().map_err(|_| err())?;
+        let minor = minor.parse().map_err(|_| err())?;
+        let patch = patch.parse().map_err(|_| err())?;
+        let numbered_build = match numbered_build {
+            Some(build) => Some(build.parse().map_err(|_| err())?),
+            None => None,
+        };
+        Ok(FirmwareVersion {
+            major,
+            minor,
+            patch,
+            build: build.or(numbered_build).or(commits).unwrap_or(0),
+            pre,
+        })
+    }
+}
+impl Serialize for FirmwareVersion {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.collect_str(self)
+    }
+}
+#[cfg(feature = "schema")]
+impl schemars::JsonSchema for FirmwareVersion {
+    fn schema_name() -> std::borrow::Cow<'static, str> {
+        "FirmwareVersion".into()
+    }
+    fn json_schema(_: &mut schemars::SchemaGenerator) -> schemars::Schema {
+        schemars::json_schema!({
+            "type": "string",
+            "pattern": "^v?[0-9]+\\.[0-9]+\\.[0-9]+",
+            "examples": ["1.4.2", "1.5.0-rc1+3"]
+        })
+    }
+}
+impl<'de> Deserialize<'de> for FirmwareVersion {
+    fn deserialize<D>(deserializer: D) -> Result<FirmwareVersion, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseVersionError(String);
+impl Display for ParseVersionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Invalid firmware version: {}", self.0)
+    }
+}
+impl std::error::Error for ParseVersionError {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn v(s: &str) -> FirmwareVersion {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn parse_git_describe() {
+        assert_eq!(
+            v("v1.4.2-rc1-5-g3f2a9c1-dirty"),
+            FirmwareVersion {
+                major: 1,
+                minor: 4,
+                patch: 2,
+                build: 5,
+                pre: Some("rc1".to_string()),
+            }
+        );
+        assert_eq!(
+            v("v1.4.2-12-g3f2a9c1"),
+            FirmwareVersion {
+                build: 12,
+                ..FirmwareVersion::new(1, 4, 2)
+            }
+        );
+        assert_eq!(v("1.4.2"), FirmwareVersion::new(1, 4, 2));
+        assert_eq!(
+            v("1.4.2.7"),
+            FirmwareVersion {
+                build: 7,
+                ..FirmwareVersion::new(1, 4, 2)
+            }
+        );
+        assert!("1.4".parse::<FirmwareVersion>().is_err());
+        assert!("main".parse::<FirmwareVersion>().is_err());
+        assert!("1.256.2".parse::<FirmwareVersion>().is_err());
+        assert!("1.4.65536".parse::<FirmwareVersion>().is_err());
+        assert_eq!(
+            v("1.4.300.70000"),
+            FirmwareVersion {
+                build: 70000,
+                ..FirmwareVersion::new(1, 4, 300)
+            }
+        );
+        assert_eq!(v("v1.4.2-300-g3f2a9c1").build, 300);
+    }
+
+    #[test]
+    fn display_roundtrip() {
+        for s in ["1.4.2", "1.4.2-rc1", "1.4.2-rc.1+5", "0.0.1+255"] {
+            assert_eq!(v(s).to_string(), s);
+        }
+    }
+
+    #[test]
+    fn ordering() {
+        assert!(v("1.4.1").is_older_than(&v("1.4.2")));
+        assert!(v("1.4.2-rc1").is_older_than(&v("1.4.2")));
+        assert!(v("1.4.2-rc1") < v("1.4.2-rc2"));
+        assert!(v("1.4.2") < v("1.4.2+1"));
+        assert!(v("1.10.0") > v("1.9.9"));
+        assert!(v("1.4.2-rc.2") < v("1.4.2-rc.10"));
+        assert!(v("1.4.2-alpha") < v("1.4.2-alpha.1"));
+        assert!(v("1.4.2-alpha.1") < v("1.4.2-alpha.beta"));
+        assert!(v("1.4.2-1") < v("1.4.2-alpha"));
+        assert!(v("1.4.2-beta.11") < v("1.4.2-rc.1"));
+        assert!(v("1.4.255") < v("1.4.300"));
+        assert!(v("1.4.2+255") < v("1.4.2+256"));
+        // Ord agrees with Eq
+        assert_ne!(v("1.4.2-rc.01"), v("1.4.2-rc.1"));
+        assert_ne!(v("1.4.2-rc.01").cmp(&v("1.4.2-rc.1")), Ordering::Equal);
+        assert!(v("1.4.2-rc.01") < v("1.4.2-rc.2"));
+    }
+
+    #[test]
+    fn fwver_u32() {
+        let version = FirmwareVersion::from(0x01040203);
+        assert_eq!(version.to_string(), "1.4.2+3");
+        assert_eq!(version.fwver(), 0x01040203);
+        assert_eq!(v("1.4.300+70000").fwver(), 0x0104ffff);
+        assert_eq!(
+            FirmwareVersion::from_parts(Some(0x01040200), Some("v1.4.2-rc1-0-gabcdef0")),
+            Some(v("1.4.2-rc1"))
+        );
+        assert_eq!(
+            FirmwareVersion::from_parts(None, Some("v1.4.2")),
+            Some(v("1.4.2"))
+        );
+        assert_eq!(FirmwareVersion::from_parts(None, None), None);
+        assert_eq!(
+            FirmwareVersion::from_parts(Some(0x01040200), Some("v1.5.0-rc1")),
+            Some(v("1.4.2"))
+        );
+    }
+}
@@ -0,0 +1,468 @@
+use serde::{Deserialize, Serialize};
+use std::fmt::Display;
+
+use super::ota::ImageManifest;
+use super::FirmwareVersion;
+use crate::devs::hb::{DevType, HeartBeat};
+
+// MCUboot signed image, all fields little-endian:
+//
+// | header (32 bytes) | image (img_size) | protected TLVs | unprotected TLVs |
+//
+// header: magic: u32 | load_addr: u32 | hdr_size: u16 | protect_tlv_size: u16 | img_size: u32
+//         flags: u32 | major: u8 | minor: u8 | revision: u16 | build_num: u32 | pad: u32
+// TLV area: magic: u16 | total length incl. this info: u16 | [type: u8 | pad: u8 | len: u16 | data]
+pub const IMAGE_MAGIC: u32 = 0x96f3_b83d;
+pub const TLV_INFO_MAGIC: u16 = 0x6907;
+pub const TLV_PROT_INFO_MAGIC: u16 = 0x6908;
+const HEADER_LEN: usize = 32;
+const TLV_INFO_LEN: usize = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct ImageVersion {
+    pub major: u8,
+    pub minor: u8,
+    pub revision: u16,
+    pub build_num: u32,
+}
+impl ImageVersion {
+    // revision and build_num saturate at 255 to fit the fwver bytes
+    pub fn fwver(&self) -> u32 {
+        self.firmware_version().fwver()
+    }
+    pub fn firmware_version(&self) -> FirmwareVersion {
+        FirmwareVersion {
+            build: self.build_num,
+            ..FirmwareVersion::new(self.major, self.minor, self.revision)
+        }
+    }
+}
+impl Display for ImageVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}.{}.{}+{}",
+            self.major, self.minor, self.revision, self.build_num
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct ImageHeader {
+    pub load_addr: u32,
+    pub hdr_size: u16,
+    pub protect_tlv_size: u16,
+    pub img_size: u32,
+    pub flags: u32,
+    pub version: ImageVersion,
+}
+impl ImageHeader {
+    pub fn from_bytes(bytes: &[u8]) -> Result<ImageHeader, ImageError> {
+        let header: &[u8; HEADER_LEN] = bytes
+            .get(..HEADER_LEN)
+            .and_then(|h| h.try_into().ok())
+            .ok_or(ImageError::Truncated)?;
+        let u16_at = |n: usize| u16::from_le_bytes([header[n], header[n + 1]]);
+        let u32_at = |n: usize| u32::from_le_bytes(header[n..n + 4].try_into().unwrap());
+        let magic = u32_at(0);
+        if magic != IMAGE_MAGIC {
+            return Err(ImageError::BadMagic(magic));
+        }
+        let hdr = ImageHeader {
+            load_addr: u32_at(4),
+            hdr_size: u16_at(8),
+            protect_tlv_size: u16_at(10),
+            img_size: u32_at(12),
+            flags: u32_at(16),
+            version: ImageVersion {
+                major: header[20],
+                minor: header[21],
+                revision: u16_at(22),
+                build_num: u32_at(24),
+            },
+        };
+        if usize::from(hdr.hdr_size) < HEADER_LEN {
+            return Err(ImageError::BadHeaderSize(hdr.hdr_size));
+        }
+        Ok(hdr)
+    }
+    pub fn to_bytes(&self) -> [u8; HEADER_LEN] {
+        let mut ret = [0; HEADER_LEN];
+        ret[0..4].copy_from_slice(&IMAGE_MAGIC.to_le_bytes());
+        ret[4..8].copy_from_slice(&self.load_addr.to_le_bytes());
+        ret[8..10].copy_from_slice(&self.hdr_size.to_le_bytes());
+        ret[10..12].copy_from_slice(&self.protect_tlv_size.to_le_bytes());
+        ret[12..16].copy_from_slice(&self.img_size.to_le_bytes());
+        ret[16..20].copy_from_slice(&self.flags.to_le_bytes());
+        ret[20] = self.version.major;
+        ret[21] = self.version.minor;
+        ret[22..24].copy_from_slice(&self.version.revision.to_le_bytes());
+        ret[24..28].copy_from_slice(&self.version.build_num.to_le_bytes());
+        ret
+    }
+    // Offset of the first TLV area, None when it does not fit in usize
+    pub fn tlv_offset(&self) -> Option<usize> {
+        usize::from(self.hdr_size).checked_add(usize::try_from(self.img_size).ok()?)
+    }
+    // Length of what MCUboot hashes for the SHA-256 TLV: header, image and protected TLVs
+    pub fn hashed_len(&self) -> Option<usize> {
+        self.tlv_offset()?
+            .checked_add(usize::from(self.protect_tlv_size))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[repr(u8)]
+#[serde(into = "u8", from = "u8")]
+pub enum TlvType {
+    KeyHash,
+    PubKey,
+    Sha256,
+    Sha384,
+    Sha512,
+    Rsa2048Pss,
+    Ecdsa224,
+    EcdsaSig,
+    Rsa3072Pss,
+    Ed25519,
+    EncRsa2048,
+    EncKw,
+    EncEc256,
+    EncX25519,
+    Dependency,
+    SecCnt,
+    BootRecord,
+    Other(u8),
+}
+impl TlvType {
+    pub fn is_signature(&self) -> bool {
+        matches!(
+            self,
+            TlvType::Rsa2048Pss
+                | TlvType::Ecdsa224
+                | TlvType::EcdsaSig
+                | TlvType::Rsa3072Pss
+                | TlvType::Ed25519
+        )
+    }
+}
+impl From<u8> for TlvType {
+    fn from(value: u8) -> Self {
+        match value {
+            0x01 => TlvType::KeyHash,
+            0x02 => TlvType::PubKey,
+            0x10 => TlvType::Sha256,
+            0x11 => TlvType::Sha384,
+            0x12 => TlvType::Sha512,
+            0x20 => TlvType::Rsa2048Pss,
+            0x21 => TlvType::Ecdsa224,
+            0x22 => TlvType::EcdsaSig,
+            0x23 => TlvType::Rsa3072Pss,
+            0x24 => TlvType::Ed25519,
+            0x30 => TlvType::EncRsa2048,
+            0x31 => TlvType::EncKw,
+            0x32 => TlvType::EncEc256,
+            0x33 => TlvType::EncX25519,
+            0x40 => TlvType::Dependency,
+            0x50 => TlvType::SecCnt,
+            0x60 => TlvType::BootRecord,
+            n => TlvType::Other(n),
+        }
+    }
+}
+impl From<TlvType> for u8 {
+    fn from(value: TlvType) -> Self {
+        match value {
+            TlvType::KeyHash => 0x01,
+            TlvType::PubKey => 0x02,
+            TlvType::Sha256 => 0x10,
+            TlvType::Sha384 => 0x11,
+            TlvType::Sha512 => 0x12,
+            TlvType::Rsa2048Pss => 0x20,
+            TlvType::Ecdsa224 => 0x21,
+            TlvType::EcdsaSig => 0x22,
+            TlvType::Rsa3072Pss => 0x23,
+            TlvType::Ed25519 => 0x24,
+            TlvType::EncRsa2048 => 0x30,
+            TlvType::EncKw => 0x31,
+            TlvType::EncEc256 => 0x32,
+            TlvType::EncX25519 => 0x33,
+            TlvType::Dependency => 0x40,
+            TlvType::SecCnt => 0x50,
+            TlvType::BootRecord => 0x60,
+            TlvType::Other(n) => n,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct Tlv {
+    pub tlv_type: TlvType,
+    pub protected: bool,
+    pub data: Vec<u8>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageError {
+    Truncated,
+    BadMagic(u32),
+    BadHeaderSize(u16),
+    BadTlvMagic(u16),
+    // protect_tlv_size in the header and the length of the protected TLV area differ
+    BadProtectedTlvSize { header: u16, area: usize },
+    TooLarge(usize),
+}
+impl Display for ImageError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ImageError::Truncated => write!(f, "Image is truncated"),
+            ImageError::BadMagic(m) => write!(f, "Not an MCUboot image, magic {m:#010x}"),
+            ImageError::BadHeaderSize(s) => write!(f, "Invalid header size {s}"),
+            ImageError::BadTlvMagic(m) => write!(f, "Invalid TLV info magic {m:#06x}"),
+            ImageError::BadProtectedTlvSize { header, area } => write!(
+                f,
+                "Protected TLV size {header} does not match protected TLV area of {area} bytes"
+            ),
+            ImageError::TooLarge(size) => write!(f, "Image of {size} bytes is too large"),
+        }
+    }
+}
+impl std::error::Error for ImageError {}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct McubootImage {
+    pub header: ImageHeader,
+    pub tlvs: Vec<Tlv>,
+    // Header, image and TLVs, without any padding after the trailer
+    pub size: u32,
+}
+impl McubootImage {
+    pub fn parse(bytes: &[u8]) -> Result<McubootImage, ImageError> {
+        let header = ImageHeader::from_bytes(bytes)?;
+        // Offsets past usize can not be in `bytes` either
+        let mut offset = header.tlv_offset().ok_or(ImageError::Truncated)?;
+        let mut tlvs = Vec::new();
+        if header.protect_tlv_size > 0 {
+            let end = parse_tlv_area(bytes, offset, TLV_PROT_INFO_MAGIC, &mut tlvs)?;
+            if end - offset != usize::from(header.protect_tlv_size) {
+                return Err(ImageError::BadProtectedTlvSize {
+                    header: header.protect_tlv_size,
+                    area: end - offset,
+                });
+            }
+            offset = end;
+        }
+        offset = parse_tlv_area(bytes, offset, TLV_INFO_MAGIC, &mut tlvs)?;
+        Ok(McubootImage {
+            header,
+            tlvs,
+            size: u32::try_from(offset).map_err(|_| ImageError::TooLarge(offset))?,
+        })
+    }
+    pub fn version(&self) -> FirmwareVersion {
+        self.header.version.firmware_version()
+    }
+    pub fn fwver(&self) -> u32 {
+        self.header.version.fwver()
+    }
+    pub fn tlv(&self, tlv_type: TlvType) -> Option<&Tlv> {
+        self.tlvs.iter().find(|t| t.tlv_type == tlv_type)
+    }
+    pub fn sha256(&self) -> Option<[u8; 32]> {
+        self.tlv(TlvType::Sha256)?.data.as_slice().try_into().ok()
+    }
+    pub fn signatures(&self) -> impl Iterator<Item = &Tlv> {
+        self.tlvs.iter().filter(|t| t.tlv_type.is_signature())
+    }
+    pub fn is_signed(&self) -> bool {
+        self.signatures().next().is_some()
+    }
+    pub fn is_newer_than(&self, fwver: u32) -> bool {
+        FirmwareVersion::from(fwver) < self.version()
+    }
+    pub fn is_newer_than_device(&self, hb: &HeartBeat) -> bool {
+        hb.firmware_version() < self.version()
+    }
+    // Manifest for offering the image over OTA, None without a SHA-256 TLV. size is the
+    // hashed length so size and sha256 describe the same bytes, not the whole file.
+    pub fn manifest(&self, dev_type: DevType) -> Option<ImageManifest> {
+        Some(ImageManifest {
+            dev_type,
+            version: self.version(),
+            size: u32::try_from(self.header.hashed_len()?).ok()?,
+            sha256: self.sha256()?,
+        })
+    }
+}
+
+// Parses one TLV info block and its entries, returns the offset after the area
+fn parse_tlv_area(
+    bytes: &[u8],
+    offset: usize,
+    magic: u16,
+    tlvs: &mut Vec<Tlv>,
+) -> Result<usize, ImageError> {
+    let start = offset
+        .checked_add(TLV_INFO_LEN)
+        .ok_or(ImageError::Truncated)?;
+    let info = bytes.get(offset..start).ok_or(ImageError::Truncated)?;
+    let found = u16::from_le_bytes([info[0], info[1]]);
+    if found != magic {
+        return Err(ImageError::BadTlvMagic(found));
+    }
+    let end = offset
+        .checked_add(usize::from(u16::from_le_bytes([info[2], info[3]])))
+        .ok_or(ImageError::Truncated)?;
+    let area = bytes.get(start..end).ok_or(ImageError::Truncated)?;
+    let mut rest = area;
+    while let [tlv_type, _, l1, l2, data @ ..] = rest {
+        let len = usize::from(u16::from_le_bytes([*l1, *l2]));
+        let data = data.get(..len).ok_or(ImageError::Truncated)?;
+        tlvs.push(Tlv {
+            tlv_type: (*tlv_type).into(),
+            protected: magic == TLV_PROT_INFO_MAGIC,
+            data: data.to_vec(),
+        });
+        rest = &rest[4 + len..];
+    }
+    if !rest.is_empty() {
+        return Err(ImageError::Truncated);
+    }
+    Ok(end)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // Hand-built in the layout of `imgtool sign --version 1.4.2+7 --header-size 0x200 --pad-header`
+    // with a 16 byte payload. The SHA-256 and Ed25519 TLVs are filler, not a real hash and signature.
+    fn image() -> Vec<u8> {
+        let header = ImageHeader {
+            load_addr: 0,
+            hdr_size: 0x200,
+            protect_tlv_size: 0,
+            img_size: 16,
+            flags: 0,
+            version: ImageVersion {
+                major: 1,
+                minor: 4,
+                revision: 2,
+                build_num: 7,
+            },
+        };
+        let mut bytes = header.to_bytes().to_vec();
+        bytes.resize(0x200, 0);
+        bytes.extend_from_slice(&[0x5a; 16]);
+        bytes.extend_from_slice(&[0x07, 0x69, 0x34, 0x00]);
+        bytes.extend_from_slice(&[0x10, 0x00, 0x20, 0x00]);
+        bytes.extend_from_slice(&[0xab; 32]);
+        bytes.extend_from_slice(&[0x24, 0x00, 0x08, 0x00]);
+        bytes.extend_from_slice(&[0xcd; 8]);
+        bytes
+    }
+
+    #[test]
+    fn parse_image() {
+        let bytes = image();
+        assert_eq!(
+            &bytes[..12],
+            &[0x3d, 0xb8, 0xf3, 0x96, 0, 0, 0, 0, 0x00, 0x02, 0, 0]
+        );
+        let image = McubootImage::parse(&bytes).unwrap();
+        assert_eq!(image.header.version.to_string(), "1.4.2+7");
+        assert_eq!(image.fwver(), 0x01040207);
+        assert_eq!(image.sha256(), Some([0xab; 32]));
+        assert!(image.is_signed());
+        assert_eq!(
+            image.signatures().next().unwrap().tlv_type,
+            TlvType::Ed25519
+        );
+        assert_eq!(image.size as usize, bytes.len());
+
+        let manifest = image.manifest(DevType::HortiLed).unwrap();
+        assert_eq!(manifest.version.to_string(), "1.4.2+7");
+        assert_eq!(manifest.size, 0x200 + 16);
+        assert_eq!(Some(manifest.size as usize), image.header.hashed_len());
+    }
+
+    #[test]
+    fn manifest_covers_protected_tlvs() {
+        let mut bytes = image();
+        bytes[10] = 12;
+        let unprotected = bytes.split_off(0x210);
+        // Security counter
+        bytes.extend_from_slice(&[0x08, 0x69, 0x0c, 0x00, 0x50, 0x00, 0x04, 0x00, 1, 0, 0, 0]);
+        bytes.extend_from_slice(&unprotected);
+        let image = McubootImage::parse(&bytes).unwrap();
+        assert!(image.tlv(TlvType::SecCnt).unwrap().protected);
+        let manifest = image.manifest(DevType::HortiLed).unwrap();
+        assert_eq!(manifest.size, 0x200 + 16 + 12);
+        assert!((manifest.size as usize) < bytes.len());
+
+        bytes[10] = 16;
+        assert_eq!(
+            McubootImage::parse(&bytes),
+            Err(ImageError::BadProtectedTlvSize {
+                header: 16,
+                area: 12
+            })
+        );
+    }
+
+    #[test]
+    fn newer_than_device() {
+        let image = McubootImage::parse(&image()).unwrap();
+        assert!(image.is_newer_than_device(&HeartBeat::new().fwver(0x01040100)));
+        assert!(image.is_newer_than_device(&HeartBeat::new().fwver(0x01040206)));
+        assert!(!image.is_newer_than_device(&HeartBeat::new().fwver(0x01040207)));
+        assert!(!image.is_newer_than(0x02000000));
+    }
+
+    #[test]
+    fn versions_above_255() {
+        let mut bytes = image();
+        bytes[22..24].copy_from_slice(&300u16.to_le_bytes());
+        let image = McubootImage::parse(&bytes).unwrap();
+        assert_eq!(image.version().to_string(), "1.4.300+7");
+        assert!(image.is_newer_than(0x0104ff07));
+        bytes[22..28].copy_from_slice(&[2, 0, 0x2c, 0x01, 0, 0]);
+        let image = McubootImage::parse(&bytes).unwrap();
+        assert_eq!(image.version().to_string(), "1.4.2+300");
+        assert!(image.is_newer_than(0x010402ff));
+    }
+
+    #[test]
+    fn invalid_images() {
+        let bytes = image();
+        assert_eq!(
+            McubootImage::parse(&bytes[..20]),
+            Err(ImageError::Truncated)
+        );
+        assert_eq!(
+            McubootImage::parse(&bytes[..bytes.len() - 1]),
+            Err(ImageError::Truncated)
+        );
+        let mut bad = bytes.clone();
+        bad[0] = 0;
+        assert_eq!(
+            McubootImage::parse(&bad),
+            Err(ImageError::BadMagic(0x96f3b800))
+        );
+        let mut bad = bytes;
+        bad[0x210] = 0x08;
+        assert_eq!(
+            McubootImage::parse(&bad),
+            Err(ImageError::BadTlvMagic(0x6908))
+        );
+    }
+}
@@ -0,0 +1,433 @@
+use base64::engine::general_purpose::STANDARD;
+use base64_serde::base64_serde_type;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::fmt::Display;
+
+use super::FirmwareVersion;
+use crate::devs::hb::{DevStatus, DevType};
+
+base64_serde_type!(Base64Bitmap, STANDARD);
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct ImageManifest {
+    pub dev_type: DevType,
+    pub version: FirmwareVersion,
+    // Length of the bytes sha256 covers. For MCUboot images that is header, image and
+    // protected TLVs, the unprotected TLVs with the signatures come after it.
+    pub size: u32,
+    #[serde(with = "hex_sha256")]
+    #[cfg_attr(
+        feature = "schema",
+        schemars(with = "String", regex(pattern = "^[0-9a-f]{64}$"))
+    )]
+    pub sha256: [u8; 32],
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "camelCase")]
+pub enum OtaState {
+    Offered,
+    Downloading,
+    Verifying,
+    Flashing,
+    Rebooting,
+    Confirmed,
+    RolledBack,
+    TimedOut,
+}
+impl OtaState {
+    pub fn is_finished(&self) -> bool {
+        matches!(
+            self,
+            OtaState::Confirmed | OtaState::RolledBack | OtaState::TimedOut
+        )
+    }
+    fn can_become(&self, next: OtaState) -> bool {
+        matches!(
+            (self, next),
+            (OtaState::Offered, OtaState::Downloading)
+                | (OtaState::Downloading, OtaState::Verifying)
+                | (OtaState::Verifying, OtaState::Downloading)
+                | (OtaState::Verifying, OtaState::Flashing)
+                | (OtaState::Flashing, OtaState::Rebooting)
+                | (OtaState::Rebooting, OtaState::Confirmed)
+                | (OtaState::Rebooting, OtaState::RolledBack)
+        ) || (!self.is_finished() && next == OtaState::TimedOut)
+    }
+}
+impl Display for OtaState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{self:?}")
+    }
+}
+
+// Longest time a session may stay in a state, the download timeout counts from the last block
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct OtaTimeouts {
+    pub offer: u32,
+    pub block: u32,
+    pub verify: u32,
+    pub flash: u32,
+    pub reboot: u32,
+}
+impl Default for OtaTimeouts {
+    fn default() -> Self {
+        Self {
+            offer: 600,
+            block: 120,
+            verify: 60,
+            flash: 300,
+            reboot: 180,
+        }
+    }
+}
+impl OtaTimeouts {
+    fn for_state(&self, state: OtaState) -> Option<Duration> {
+        let seconds = match state {
+            OtaState::Offered => self.offer,
+            OtaState::Downloading => self.block,
+            OtaState::Verifying => self.verify,
+            OtaState::Flashing => self.flash,
+            OtaState::Rebooting => self.reboot,
+            _ => return None,
+        };
+        Some(Duration::seconds(seconds.into()))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OtaError {
+    InvalidTransition { from: OtaState, to: OtaState },
+    BlockOutOfRange(u32),
+    Incomplete { missing: u32 },
+}
+impl Display for OtaError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OtaError::InvalidTransition { from, to } => {
+                write!(f, "Invalid OTA state change from {from} to {to}")
+            }
+            OtaError::BlockOutOfRange(n) => write!(f, "Block {n} is outside the image"),
+            OtaError::Incomplete { missing } => write!(f, "{missing} blocks still missing"),
+        }
+    }
+}
+impl std::error::Error for OtaError {}
+
+// Deserializing checks that block_size and the received bitmap fit the manifest
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[cfg_attr(
+    feature = "schema",
+    derive(schemars::JsonSchema),
+    schemars(transform = crate::schema::KindTag("OtaSession"))
+)]
+#[serde(rename_all = "camelCase", tag = "kind", rename = "OtaSession")]
+pub struct OtaSession {
+    dev_sn: u64,
+    manifest: ImageManifest,
+    block_size: u16,
+    #[serde(with = "Base64Bitmap")]
+    #[cfg_attr(feature = "schema", schemars(with = "String"))]
+    received: Vec<u8>,
+    state: OtaState,
+    started: DateTime<Utc>,
+    last_activity: DateTime<Utc>,
+    timeouts: OtaTimeouts,
+}
+impl OtaSession {
+    pub fn new(dev_sn: u64, manifest: ImageManifest, block_size: u16, now: DateTime<Utc>) -> Self {
+        let block_size = block_size.max(1);
+        let blocks = manifest.size.div_ceil(block_size.into());
+        Self {
+            dev_sn,
+            manifest,
+            block_size,
+            received: vec![0; blocks.div_ceil(8) as usize],
+            state: OtaState::Offered,
+            started: now,
+            last_activity: now,
+            timeouts: OtaTimeouts::default(),
+        }
+    }
+    pub fn with_timeouts(mut self, timeouts: OtaTimeouts) -> Self {
+        self.timeouts = timeouts;
+        self
+    }
+    pub fn dev_sn(&self) -> u64 {
+        self.dev_sn
+    }
+    pub fn manifest(&self) -> &ImageManifest {
+        &self.manifest
+    }
+    pub fn block_size(&self) -> u16 {
+        self.block_size
+    }
+    pub fn state(&self) -> OtaState {
+        self.state
+    }
+    pub fn started(&self) -> DateTime<Utc> {
+        self.started
+    }
+    pub fn last_activity(&self) -> DateTime<Utc> {
+        self.last_activity
+    }
+    pub fn block_count(&self) -> u32 {
+        self.manifest.size.div_ceil(self.block_size.into())
+    }
+    pub fn has_block(&self, block: u32) -> bool {
+        block < self.block_count() && self.received[(block / 8) as usize] & (1 << (block % 8)) != 0
+    }
+    pub fn received_blocks(&self) -> u32 {
+        self.received.iter().map(|b| b.count_ones()).sum()
+    }
+    pub fn missing_blocks(&self) -> Vec<u32> {
+        (0..self.block_count())
+            .filter(|b| !self.has_block(*b))
+            .collect()
+    }
+    pub fn is_complete(&self) -> bool {
+        self.received_blocks() == self.block_count()
+    }
+    // Percent of the image received
+    pub fn progress(&self) -> f32 {
+        match self.block_count() {
+            0 => 100.0,
+            n => self.received_blocks() as f32 * 100.0 / n as f32,
+        }
+    }
+    // The first block moves an offered session to Downloading
+    pub fn mark_block(&mut self, block: u32, now: DateTime<Utc>) -> Result<(), OtaError> {
+        if block >= self.block_count() {
+            return Err(OtaError::BlockOutOfRange(block));
+        }
+        if self.state == OtaState::Offered {
+            self.advance(OtaState::Downloading, now)?;
+        }
+        if self.state != OtaState::Downloading {
+            return Err(OtaError::InvalidTransition {
+                from: self.state,
+                to: OtaState::Downloading,
+            });
+        }
+        self.received[(block / 8) as usize] |= 1 << (block % 8);
+        self.last_activity = now;
+        Ok(())
+    }
+    pub fn advance(&mut self, next: OtaState, now: DateTime<Utc>) -> Result<(), OtaError> {
+        if !self.state.can_become(next) {
+            return Err(OtaError::InvalidTransition {
+                from: self.state,
+                to: next,
+            });
+        }
+        if next == OtaState::Verifying && !self.is_complete() {
+            return Err(OtaError::Incomplete {
+                missing: self.block_count() - self.received_blocks(),
+            });
+        }
+        self.state = next;
+        self.last_activity = now;
+        Ok(())
+    }
+    // Moves the session to TimedOut when it stayed too long in its state, returns true if it did
+    pub fn check_timeout(&mut self, now: DateTime<Utc>) -> bool {
+        match self.timeouts.for_state(self.state) {
+            Some(timeout) if now - self.last_activity > timeout => {
+                self.state = OtaState::TimedOut;
+                true
+            }
+            _ => false,
+        }
+    }
+    pub fn dev_status(&self) -> DevStatus {
+        match self.state {
+            OtaState::Offered | OtaState::Downloading | OtaState::Verifying => {
+                DevStatus::Downloading
+            }
+            OtaState::Flashing => DevStatus::Flashing,
+            OtaState::Rebooting => DevStatus::Rebooting,
+            OtaState::Confirmed => DevStatus::RunningOk,
+            OtaState::RolledBack | OtaState::TimedOut => DevStatus::Error,
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for OtaSession {
+    fn deserialize<D>(deserializer: D) -> Result<OtaSession, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        #[serde(rename_all = "camelCase", tag = "kind", rename = "OtaSession")]
+        struct Fields {
+            dev_sn: u64,
+            manifest: ImageManifest,
+            block_size: u16,
+            #[serde(with = "Base64Bitmap")]
+            received: Vec<u8>,
+            state: OtaState,
+            started: DateTime<Utc>,
+            last_activity: DateTime<Utc>,
+            timeouts: OtaTimeouts,
+        }
+        let fields = Fields::deserialize(deserializer)?;
+        if fields.block_size == 0 {
+            return Err(serde::de::Error::custom("blockSize must be at least 1"));
+        }
+        let blocks = fields.manifest.size.div_ceil(fields.block_size.into());
+        if fields.received.len() != blocks.div_ceil(8) as usize {
+            return Err(serde::de::Error::custom(format!(
+                "received has {} bytes, {blocks} blocks need {}",
+                fields.received.len(),
+                blocks.div_ceil(8)
+            )));
+        }
+        // Bits past the last block of the last byte
+        let unused = fields.received.last().map_or(0, |last| match blocks % 8 {
+            0 => 0,
+            n => last >> n,
+        });
+        if unused != 0 {
+            return Err(serde::de::Error::custom(format!(
+                "received marks blocks past block {blocks}"
+            )));
+        }
+        Ok(OtaSession {
+            dev_sn: fields.dev_sn,
+            manifest: fields.manifest,
+            block_size: fields.block_size,
+            received: fields.received,
+            state: fields.state,
+            started: fields.started,
+            last_activity: fields.last_activity,
+            timeouts: fields.timeouts,
+        })
+    }
+}
+
+mod hex_sha256 {
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(hash: &[u8; 32], serializer: S) -> Result<S::Ok, S::Error> {
+        let hex: String = hash.iter().map(|b| format!("{b:02x}")).collect();
+        serializer.serialize_str(&hex)
+    }
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<[u8; 32], D::Error> {
+        let hex = String::deserialize(deserializer)?;
+        let invalid = || serde::de::Error::custom("sha256 must be 64 hex characters");
+        if hex.len() != 64 || !hex.is_ascii() {
+            return Err(invalid());
+        }
+        let mut ret = [0; 32];
+        for (n, b) in ret.iter_mut().enumerate() {
+            *b = u8::from_str_radix(&hex[n * 2..n * 2 + 2], 16).map_err(|_| invalid())?;
+        }
+        Ok(ret)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    fn session() -> OtaSession {
+        let manifest = ImageManifest {
+            dev_type: DevType::HortiLed,
+            version: "1.4.2".parse().unwrap(),
+            size: 1000,
+            sha256: [0xab; 32],
+        };
+        OtaSession::new(1337, manifest, 256, DateTime::from_timestamp(0, 0).unwrap())
+    }
+    fn at(seconds: i64) -> DateTime<Utc> {
+        DateTime::from_timestamp(seconds, 0).unwrap()
+    }
+
+    #[test]
+    fn download_progress() {
+        let mut ota = session();
+        assert_eq!(ota.block_count(), 4);
+        assert_eq!(ota.progress(), 0.0);
+        ota.mark_block(0, at(1)).unwrap();
+        ota.mark_block(2, at(2)).unwrap();
+        assert_eq!(ota.state(), OtaState::Downloading);
+        assert_eq!(ota.progress(), 50.0);
+        assert_eq!(ota.missing_blocks(), vec![1, 3]);
+        assert_eq!(ota.mark_block(4, at(3)), Err(OtaError::BlockOutOfRange(4)));
+        assert_eq!(
+            ota.advance(OtaState::Verifying, at(3)),
+            Err(OtaError::Incomplete { missing: 2 })
+        );
+        ota.mark_block(1, at(3)).unwrap();
+        ota.mark_block(3, at(4)).unwrap();
+        assert!(ota.is_complete());
+        for state in [
+            OtaState::Verifying,
+            OtaState::Flashing,
+            OtaState::Rebooting,
+            OtaState::Confirmed,
+        ] {
+            ota.advance(state, at(5)).unwrap();
+        }
+        assert_eq!(ota.dev_status(), DevStatus::RunningOk);
+        assert!(ota.advance(OtaState::Downloading, at(6)).is_err());
+    }
+
+    #[test]
+    fn timeouts() {
+        let mut ota = session();
+        assert!(!ota.check_timeout(at(600)));
+        ota.mark_block(0, at(600)).unwrap();
+        assert!(!ota.check_timeout(at(720)));
+        assert!(ota.check_timeout(at(721)));
+        assert_eq!(ota.state(), OtaState::TimedOut);
+        assert_eq!(ota.dev_status(), DevStatus::Error);
+        assert!(!ota.check_timeout(at(10000)));
+    }
+
+    #[test]
+    fn json_roundtrip() {
+        let mut ota = session();
+        ota.mark_block(1, at(1)).unwrap();
+        let value = serde_json::to_value(&ota).unwrap();
+        assert_eq!(value["kind"], "OtaSession");
+        assert_eq!(value["received"], "Ag==");
+        assert_eq!(value["manifest"]["version"], "1.4.2");
+        assert_eq!(value["manifest"]["sha256"], "ab".repeat(32));
+        assert_eq!(
+            serde_json::from_value::<OtaSession>(value.clone()).unwrap(),
+            ota
+        );
+
+        for (field, invalid, error) in [
+            ("blockSize", json!(0), "blockSize must be at least 1"),
+            (
+                "received",
+                json!("AAA="),
+                "received has 2 bytes, 4 blocks need 1",
+            ),
+            (
+                "received",
+                json!(""),
+                "received has 0 bytes, 4 blocks need 1",
+            ),
+            (
+                "received",
+                json!("EA=="),
+                "received marks blocks past block 4",
+            ),
+        ] {
+            let mut value = value.clone();
+            value[field] = invalid;
+            let err = serde_json::from_value::<OtaSession>(value).unwrap_err();
+            assert_eq!(err.to_string(), error);
+        }
+    }
+}
@@ -0,0 +1,101 @@
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use super::DeviceFirmware;
+
+// Serde helpers that keep the wire format from before DeviceFirmware: fwver is the u32
+// (major << 24 | minor << 16 | patch << 8 | build) and the name a string, both sent as
+// received.
+//
+// The device structs name the pair differently, there is one module per naming. Use them
+// with #[serde(flatten, with = "...")] on a DeviceFirmware.
+macro_rules! version_fields {
+    ($module:ident, $fwver:literal, $fwver_name:literal) => {
+        pub mod $module {
+            use super::*;
+
+            #[derive(Serialize, Deserialize)]
+            #[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+            #[cfg_attr(feature = "schema", schemars(inline))]
+            pub struct Fields {
+                #[serde(rename = $fwver, default)]
+                fwver: Option<u32>,
+                #[serde(rename = $fwver_name, default)]
+                fwver_name: Option<String>,
+            }
+
+            pub fn serialize<S>(firmware: &DeviceFirmware, serializer: S) -> Result<S::Ok, S::Error>
+            where
+                S: Serializer,
+            {
+                Fields {
+                    fwver: firmware.fwver,
+                    fwver_name: firmware.fwver_name.clone(),
+                }
+                .serialize(serializer)
+            }
+            pub fn deserialize<'de, D>(deserializer: D) -> Result<DeviceFirmware, D::Error>
+            where
+                D: Deserializer<'de>,
+            {
+                let fields = Fields::deserialize(deserializer)?;
+                Ok(DeviceFirmware::new(fields.fwver, fields.fwver_name))
+            }
+        }
+    };
+}
+
+// fwver, fwver_name
+version_fields!(snake_case, "fwver", "fwver_name");
+// fwver, fwverName
+version_fields!(camel_case, "fwver", "fwverName");
+// fwverValue, fwverName
+version_fields!(value_name, "fwverValue", "fwverName");
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::firmware::FirmwareVersion;
+    use serde_json::json;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct Device {
+        id: u64,
+        #[serde(flatten, with = "camel_case")]
+        firmware: DeviceFirmware,
+    }
+
+    #[test]
+    fn same_keys_as_before() {
+        for text in [
+            r#"{"id":1,"fwver":17039877,"fwverName":"v1.4.2-rc1-5-g3f2a9c1-dirty"}"#,
+            r#"{"id":1,"fwver":17039872,"fwverName":"main"}"#,
+            r#"{"id":1,"fwver":null,"fwverName":"v1.4.2"}"#,
+            r#"{"id":1,"fwver":null,"fwverName":null}"#,
+        ] {
+            let device: Device = serde_json::from_str(text).unwrap();
+            assert_eq!(serde_json::to_string(&device).unwrap(), text);
+        }
+
+        let device: Device = serde_json::from_value(json!({
+            "id": 1,
+            "fwver": 0x01040205,
+            "fwverName": "v1.4.2-rc1-5-g3f2a9c1-dirty"
+        }))
+        .unwrap();
+        assert_eq!(
+            device.firmware.version(),
+            Some("1.4.2-rc1+5".parse().unwrap())
+        );
+        let device: Device =
+            serde_json::from_value(json!({"id": 1, "fwver": 0x01040200, "fwverName": "main"}))
+                .unwrap();
+        assert_eq!(
+            device.firmware.version(),
+            Some(FirmwareVersion::new(1, 4, 2))
+        );
+        assert_eq!(device.firmware.fwver_name.as_deref(), Some("main"));
+        let device: Device = serde_json::from_value(json!({"id": 1})).unwrap();
+        assert_eq!(device.firmware, DeviceFirmware::default());
+    }
+}
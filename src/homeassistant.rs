@@ -0,0 +1,547 @@
+use serde_json::{json, Value};
+use std::fmt;
+
+use crate::devices_connected::DevicesConnectedTypes;
+use crate::devs::hb::{DevStatus, DevType};
+use crate::devs::{Dev, DevInfo, Device};
+use crate::measurement::{Measurement, MeasurementType, SensorChannel};
+use crate::settings::typed::{DoorlockMode, LedMode, Setting, TypedSetting};
+use crate::settings::{ApiDevSettings, DevSetting};
+
+// Home Assistant MQTT discovery.
+//
+// Config messages go to <prefix>/<component>/horti_<devSn>/<object id>/config and are
+// retained. The gateway publishes states and reads commands below <base>/<devSn>:
+//
+//   <base>/<devSn>/availability           online | offline
+//   <base>/<devSn>/light/<channel>        {"state": "ON", "brightness": 255}, commands on .../set
+//   <base>/<devSn>/lock/<channel>         LOCKED | UNLOCKED, commands LOCK | UNLOCK | OPEN on .../set
+//   <base>/<devSn>/sensor/<name>_<ch>     value in MeasurementType::unit()
+//
+// LED panels become lights, door locks become locks, measurements become sensors.
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DiscoveryMessage {
+    pub topic: String,
+    pub payload: Value,
+}
+impl DiscoveryMessage {
+    pub fn payload_bytes(&self) -> Vec<u8> {
+        serde_json::to_vec(&self.payload).unwrap_or_default()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum HaError {
+    UnknownTopic(String),
+    InvalidPayload(String),
+}
+impl fmt::Display for HaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HaError::UnknownTopic(topic) => write!(f, "Not a command topic: {topic}"),
+            HaError::InvalidPayload(payload) => write!(f, "Invalid command payload: {payload}"),
+        }
+    }
+}
+impl std::error::Error for HaError {}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Discovery {
+    prefix: String,
+    base: String,
+}
+impl Default for Discovery {
+    fn default() -> Self {
+        Discovery {
+            prefix: "homeassistant".to_string(),
+            base: "horti".to_string(),
+        }
+    }
+}
+
+// What the configs are built from, DevInfo and Device carry it differently
+struct Target {
+    dev_sn: u64,
+    name: String,
+    dev_type: DevType,
+    sw_version: Option<String>,
+    peripherals: Vec<(DevicesConnectedTypes, u16)>,
+}
+
+impl Discovery {
+    pub fn new(base_topic: &str) -> Self {
+        Discovery {
+            base: base_topic.to_string(),
+            ..Default::default()
+        }
+    }
+    // Discovery prefix configured in Home Assistant, "homeassistant" by default
+    pub fn prefix(mut self, prefix: &str) -> Self {
+        self.prefix = prefix.to_string();
+        self
+    }
+
+    pub fn availability_topic(&self, dev_sn: u64) -> String {
+        format!("{}/{dev_sn}/availability", self.base)
+    }
+    pub fn light_topic(&self, dev_sn: u64, channel: u16) -> String {
+        format!("{}/{dev_sn}/light/{channel}", self.base)
+    }
+    pub fn lock_topic(&self, dev_sn: u64, channel: u16) -> String {
+        format!("{}/{dev_sn}/lock/{channel}", self.base)
+    }
+    pub fn sensor_topic(
+        &self,
+        dev_sn: u64,
+        measurement_type: &MeasurementType,
+        channel: u8,
+    ) -> String {
+        format!(
+            "{}/{dev_sn}/sensor/{}_{channel}",
+            self.base,
+            measurement_type.snake_name()
+        )
+    }
+
+    // measurements lists the types and channels the device reports
+    pub fn dev_info(
+        &self,
+        dev: &DevInfo,
+        measurements: &[(MeasurementType, u8)],
+    ) -> Vec<DiscoveryMessage> {
+        let target = Target {
+            dev_sn: dev.dev_sn,
+            name: dev.display_name(),
+            dev_type: dev.dev_type,
+            sw_version: dev.firmware.version().map(|v| v.to_string()),
+            peripherals: dev
+                .connected_devices
+                .iter()
+                .map(|d| (d.device_id(), d.idx()))
+                .collect(),
+        };
+        self.configs(&target, measurements)
+    }
+    pub fn device(
+        &self,
+        dev: &Device,
+        measurements: &[(MeasurementType, u8)],
+    ) -> Vec<DiscoveryMessage> {
+        let (dev_type, peripherals) = match dev {
+            Device::Led(panel) => (DevType::HortiLed, panel.get_connected_devices().to_vec()),
+            Device::Soil(_) => (DevType::HortiPlantSensor, vec![]),
+            Device::Env(_) => (DevType::EnvironmentSensor, vec![]),
+            Device::Router(_) => (DevType::BorderRouter, vec![]),
+            Device::TeLys(_) => (DevType::TeLys, vec![]),
+        };
+        let target = Target {
+            dev_sn: dev.dev_sn(),
+            name: dev.display_name(),
+            dev_type,
+            sw_version: dev.firmware_version().map(|v| v.to_string()),
+            peripherals,
+        };
+        self.configs(&target, measurements)
+    }
+
+    fn configs(
+        &self,
+        target: &Target,
+        measurements: &[(MeasurementType, u8)],
+    ) -> Vec<DiscoveryMessage> {
+        let channels = |types: &[DevicesConnectedTypes], dev_types: &[DevType]| {
+            let mut channels: Vec<u16> = target
+                .peripherals
+                .iter()
+                .filter(|(t, _)| types.contains(t))
+                .map(|(_, idx)| *idx)
+                .collect();
+            // Devices that are a light or lock themselves do not always list the peripheral
+            if channels.is_empty() && dev_types.contains(&target.dev_type) {
+                channels.push(0);
+            }
+            channels
+        };
+        let mut ret = vec![];
+        for channel in channels(
+            &[
+                DevicesConnectedTypes::HortiLed,
+                DevicesConnectedTypes::FlickeringLed,
+            ],
+            &[DevType::HortiLed],
+        ) {
+            ret.push(self.light(target, channel));
+        }
+        for channel in channels(
+            &[DevicesConnectedTypes::DoorLock],
+            &[DevType::GetshopLock, DevType::StayIdlock],
+        ) {
+            ret.push(self.lock(target, channel));
+        }
+        for (measurement_type, channel) in measurements {
+            ret.push(self.sensor(target, measurement_type, *channel));
+        }
+        ret
+    }
+
+    fn config_topic(&self, component: &str, dev_sn: u64, object_id: &str) -> String {
+        format!(
+            "{}/{component}/horti_{dev_sn}/{object_id}/config",
+            self.prefix
+        )
+    }
+
+    fn common(&self, target: &Target, unique_id: &str, name: String) -> Value {
+        let mut device = json!({
+            "identifiers": [format!("horti_{}", target.dev_sn)],
+            "name": target.name,
+            "model": Into::<&'static str>::into(target.dev_type),
+            "serial_number": target.dev_sn.to_string(),
+        });
+        if let Some(version) = &target.sw_version {
+            device["sw_version"] = version.as_str().into();
+        }
+        json!({
+            "name": name,
+            "unique_id": format!("horti_{}_{unique_id}", target.dev_sn),
+            "availability_topic": self.availability_topic(target.dev_sn),
+            "payload_available": "online",
+            "payload_not_available": "offline",
+            "device": device,
+        })
+    }
+
+    fn light(&self, target: &Target, channel: u16) -> DiscoveryMessage {
+        let object_id = format!("light_{channel}");
+        let mut payload = self.common(target, &object_id, format!("LED {channel}"));
+        let state_topic = self.light_topic(target.dev_sn, channel);
+        payload["schema"] = "json".into();
+        payload["command_topic"] = format!("{state_topic}/set").into();
+        payload["state_topic"] = state_topic.into();
+        payload["brightness"] = true.into();
+        payload["brightness_scale"] = u8::MAX.into();
+        payload["supported_color_modes"] = json!(["brightness"]);
+        DiscoveryMessage {
+            topic: self.config_topic("light", target.dev_sn, &object_id),
+            payload,
+        }
+    }
+
+    fn lock(&self, target: &Target, channel: u16) -> DiscoveryMessage {
+        let object_id = format!("lock_{channel}");
+        let mut payload = self.common(target, &object_id, format!("Lock {channel}"));
+        let state_topic = self.lock_topic(target.dev_sn, channel);
+        payload["command_topic"] = format!("{state_topic}/set").into();
+        payload["state_topic"] = state_topic.into();
+        payload["payload_lock"] = "LOCK".into();
+        payload["payload_unlock"] = "UNLOCK".into();
+        payload["payload_open"] = "OPEN".into();
+        payload["state_locked"] = "LOCKED".into();
+        payload["state_unlocked"] = "UNLOCKED".into();
+        DiscoveryMessage {
+            topic: self.config_topic("lock", target.dev_sn, &object_id),
+            payload,
+        }
+    }
+
+    fn sensor(
+        &self,
+        target: &Target,
+        measurement_type: &MeasurementType,
+        channel: u8,
+    ) -> DiscoveryMessage {
+        let object_id = format!("{}_{channel}", measurement_type.snake_name());
+        let mut payload = self.common(target, &object_id, format!("{measurement_type} {channel}"));
+        payload["state_topic"] = self
+            .sensor_topic(target.dev_sn, measurement_type, channel)
+            .into();
+        payload["state_class"] = "measurement".into();
+        if let Some(class) = device_class(measurement_type) {
+            payload["device_class"] = class.into();
+        }
+        match measurement_type.unit() {
+            // pH has no unit in Home Assistant
+            "" | "pH" => {}
+            // Home Assistant writes micro with the Greek mu (U+03BC), not the micro sign
+            "\u{b5}g/m³" => payload["unit_of_measurement"] = "\u{3bc}g/m³".into(),
+            unit => payload["unit_of_measurement"] = unit.into(),
+        }
+        DiscoveryMessage {
+            topic: self.config_topic("sensor", target.dev_sn, &object_id),
+            payload,
+        }
+    }
+
+    // Turns a message on a command topic into the settings to send to the device
+    pub fn parse_command(&self, topic: &str, payload: &[u8]) -> Result<ApiDevSettings, HaError> {
+        let unknown = || HaError::UnknownTopic(topic.to_string());
+        let parts: Vec<&str> = topic
+            .strip_prefix(&format!("{}/", self.base))
+            .ok_or_else(unknown)?
+            .split('/')
+            .collect();
+        let [dev_sn, component, channel, "set"] = parts.as_slice() else {
+            return Err(unknown());
+        };
+        let dev_sn: u64 = dev_sn.parse().map_err(|_| unknown())?;
+        let channel: i32 = channel.parse().map_err(|_| unknown())?;
+        let payload = String::from_utf8_lossy(payload);
+        let invalid = || HaError::InvalidPayload(payload.to_string());
+        let settings = match *component {
+            "light" => {
+                let command: Value = serde_json::from_str(&payload).map_err(|_| invalid())?;
+                let mut settings = vec![];
+                match command.get("state").and_then(Value::as_str) {
+                    Some("ON") => settings.push(Setting::LedMode(LedMode::On)),
+                    Some("OFF") => settings.push(Setting::LedMode(LedMode::Off)),
+                    Some(_) => return Err(invalid()),
+                    None => {}
+                }
+                if let Some(brightness) = command.get("brightness") {
+                    let brightness = brightness
+                        .as_u64()
+                        .and_then(|b| u8::try_from(b).ok())
+                        .ok_or_else(invalid)?;
+                    settings.push(Setting::PwmVal(brightness));
+                }
+                if settings.is_empty() {
+                    return Err(invalid());
+                }
+                settings
+            }
+            "lock" => match payload.trim() {
+                "LOCK" => vec![Setting::DoorlockMode(DoorlockMode::Locked)],
+                "UNLOCK" | "OPEN" => vec![Setting::DoorlockMode(DoorlockMode::Unlocked)],
+                _ => return Err(invalid()),
+            },
+            _ => return Err(unknown()),
+        };
+        let settings = settings
+            .into_iter()
+            .map(|s| TypedSetting::new(channel, s).and_then(DevSetting::try_from))
+            .collect::<Result<_, _>>()
+            .map_err(|_| invalid())?;
+        Ok(ApiDevSettings::from_vec(dev_sn, settings))
+    }
+}
+
+pub fn availability(status: DevStatus) -> &'static str {
+    match status {
+        DevStatus::Offline => "offline",
+        _ => "online",
+    }
+}
+
+// Schedule and effect modes are lit
+pub fn light_state(mode: LedMode, brightness: u8) -> Value {
+    match mode {
+        LedMode::Off => json!({"state": "OFF"}),
+        _ => json!({"state": "ON", "brightness": brightness}),
+    }
+}
+
+// Code mode is locked until a code is entered
+pub fn lock_state(mode: DoorlockMode) -> &'static str {
+    match mode {
+        DoorlockMode::Locked | DoorlockMode::Code => "LOCKED",
+        DoorlockMode::Unlocked => "UNLOCKED",
+    }
+}
+
+pub fn sensor_state(measurement: &Measurement) -> String {
+    measurement.value().to_string()
+}
+
+// None for channels that do not fit in the sensor topics
+pub fn sensor_channel(measurement: &Measurement) -> Option<u8> {
+    let channel: i32 = SensorChannel::into(measurement.channel);
+    u8::try_from(channel).ok()
+}
+
+// Sensor device classes of Home Assistant that accept MeasurementType::unit()
+fn device_class(measurement_type: &MeasurementType) -> Option<&'static str> {
+    let class = match measurement_type {
+        MeasurementType::DieTemp
+        | MeasurementType::AmbientTemperature
+        | MeasurementType::GaugeTemperature => "temperature",
+        MeasurementType::Pressure => "pressure",
+        MeasurementType::Humidity => "humidity",
+        MeasurementType::IlluminanceVisible
+        | MeasurementType::IlluminanceInfraRed
+        | MeasurementType::IlluminanceRed
+        | MeasurementType::IlluminanceGreen
+        | MeasurementType::IlluminanceBlue => "illuminance",
+        MeasurementType::PM1_0 => "pm1",
+        MeasurementType::PM2_5 => "pm25",
+        MeasurementType::PM10 => "pm10",
+        MeasurementType::Co2Level => "carbon_dioxide",
+        MeasurementType::VocLevel => "volatile_organic_compounds_parts",
+        MeasurementType::Voltage
+        | MeasurementType::ShuntVoltage
+        | MeasurementType::GaugeVoltage
+        | MeasurementType::GaugeDesignVoltage
+        | MeasurementType::GaugeDesiredVoltage => "voltage",
+        MeasurementType::Current
+        | MeasurementType::GaugeAvgCurrent
+        | MeasurementType::GaugeStandbyCurrent
+        | MeasurementType::GaugeMaxLoadCurrent
+        | MeasurementType::GaugeDesiredChargingCurrent => "current",
+        MeasurementType::Power | MeasurementType::GaugeAvgPower => "power",
+        MeasurementType::GaugeStateOfCharge => "battery",
+        MeasurementType::Altitude | MeasurementType::Distance => "distance",
+        MeasurementType::GaugeTimeToEmpty
+        | MeasurementType::GaugeTimeToFull
+        | MeasurementType::UptimeCounter => "duration",
+        MeasurementType::PhSensor => "ph",
+        _ => return None,
+    };
+    Some(class)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::devices_connected::DevicesConnected;
+    use crate::firmware::FirmwareVersion;
+    use crate::settings::SettingsType;
+
+    fn led_panel() -> DevInfo {
+        let mut dev = DevInfo::unknown_device(0x400);
+        dev.dev_sn = 1234567;
+        dev.name = Some("North wall".to_string());
+        dev.dev_type = DevType::HortiLed;
+        dev.set_firmware_version(&FirmwareVersion::new(1, 4, 2));
+        dev.connected_devices = vec![
+            DevicesConnected::new_idx(DevicesConnectedTypes::HortiLed, 1),
+            DevicesConnected::new_idx(DevicesConnectedTypes::DoorLock, 2),
+        ];
+        dev
+    }
+
+    #[test]
+    fn light_lock_and_sensor_configs() {
+        let discovery = Discovery::default();
+        let configs = discovery.dev_info(
+            &led_panel(),
+            &[
+                (MeasurementType::AmbientTemperature, 0),
+                (MeasurementType::PhSensor, 3),
+                (MeasurementType::PM2_5, 0),
+            ],
+        );
+        let topics: Vec<&str> = configs.iter().map(|c| c.topic.as_str()).collect();
+        assert_eq!(
+            topics,
+            [
+                "homeassistant/light/horti_1234567/light_1/config",
+                "homeassistant/lock/horti_1234567/lock_2/config",
+                "homeassistant/sensor/horti_1234567/ambient_temperature_0/config",
+                "homeassistant/sensor/horti_1234567/ph_sensor_3/config",
+                "homeassistant/sensor/horti_1234567/pm2_5_0/config",
+            ]
+        );
+        let light = &configs[0].payload;
+        assert_eq!(light["unique_id"], "horti_1234567_light_1");
+        assert_eq!(light["schema"], "json");
+        assert_eq!(light["state_topic"], "horti/1234567/light/1");
+        assert_eq!(light["command_topic"], "horti/1234567/light/1/set");
+        assert_eq!(light["availability_topic"], "horti/1234567/availability");
+        assert_eq!(
+            light["device"],
+            json!({
+                "identifiers": ["horti_1234567"],
+                "name": "North wall",
+                "model": "Horticulture: LED-panel",
+                "serial_number": "1234567",
+                "sw_version": "1.4.2",
+            })
+        );
+        assert_eq!(configs[1].payload["payload_lock"], "LOCK");
+        let sensor = &configs[2].payload;
+        assert_eq!(sensor["device_class"], "temperature");
+        assert_eq!(sensor["unit_of_measurement"], "°C");
+        assert_eq!(
+            sensor["state_topic"],
+            "horti/1234567/sensor/ambient_temperature_0"
+        );
+        assert_eq!(configs[3].payload["device_class"], "ph");
+        assert!(configs[3].payload.get("unit_of_measurement").is_none());
+        assert_eq!(configs[4].payload["device_class"], "pm25");
+        assert_eq!(configs[4].payload["unit_of_measurement"], "\u{3bc}g/m³");
+
+        let mut lock = DevInfo::unknown_device(0x800);
+        lock.dev_type = DevType::StayIdlock;
+        let configs = Discovery::new("gw").prefix("ha").dev_info(&lock, &[]);
+        assert_eq!(configs.len(), 1);
+        assert_eq!(configs[0].topic, "ha/lock/horti_2048/lock_0/config");
+        assert_eq!(configs[0].payload["state_topic"], "gw/2048/lock/0");
+    }
+
+    #[test]
+    fn commands_to_settings() {
+        let discovery = Discovery::default();
+        let settings = discovery
+            .parse_command(
+                "horti/1234567/light/1/set",
+                br#"{"state": "ON", "brightness": 128}"#,
+            )
+            .unwrap();
+        assert_eq!(settings.id(), "1234567");
+        let items = settings.as_slice();
+        assert_eq!(items.len(), 2);
+        assert_eq!(
+            (
+                items[0].settings_type(),
+                items[0].channel(),
+                items[0].value()
+            ),
+            (SettingsType::LedMode, 1, 1)
+        );
+        assert_eq!(
+            (items[1].settings_type(), items[1].value()),
+            (SettingsType::PwmVal, 128)
+        );
+        let settings = discovery
+            .parse_command("horti/42/lock/2/set", b"UNLOCK")
+            .unwrap();
+        assert_eq!(
+            (
+                settings.as_slice()[0].settings_type(),
+                settings.as_slice()[0].value()
+            ),
+            (SettingsType::DoorlockMode, 1)
+        );
+
+        assert_eq!(
+            discovery.parse_command("horti/42/light/1/set", br#"{"brightness": 300}"#),
+            Err(HaError::InvalidPayload(
+                r#"{"brightness": 300}"#.to_string()
+            ))
+        );
+        assert_eq!(
+            discovery.parse_command("horti/42/lock/2", b"LOCK"),
+            Err(HaError::UnknownTopic("horti/42/lock/2".to_string()))
+        );
+        assert_eq!(
+            discovery.parse_command("other/42/lock/2/set", b"LOCK"),
+            Err(HaError::UnknownTopic("other/42/lock/2/set".to_string()))
+        );
+    }
+
+    #[test]
+    fn states() {
+        assert_eq!(availability(DevStatus::Offline), "offline");
+        assert_eq!(availability(DevStatus::RunningOk), "online");
+        assert_eq!(light_state(LedMode::Off, 200), json!({"state": "OFF"}));
+        assert_eq!(
+            light_state(LedMode::Schedule, 200),
+            json!({"state": "ON", "brightness": 200})
+        );
+        assert_eq!(lock_state(DoorlockMode::Code), "LOCKED");
+        let measurement =
+            Measurement::with_value(3.into(), MeasurementType::Humidity, 55.5).unwrap();
+        assert_eq!(sensor_state(&measurement), "55.5");
+        assert_eq!(sensor_channel(&measurement), Some(3));
+    }
+}
@@ -0,0 +1,670 @@
+use chrono::{DateTime, Utc};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+
+use crate::devs::hb::{DevStatus, DevType, HeartBeat};
+use crate::devs::DevInfo;
+use crate::measurement::{ApiMeasurements, Measurement, MeasurementType, SensorChannel};
+use crate::neighbors::Neighbor;
+
+// InfluxDB line protocol for measurements, heartbeats and neighbors.
+//
+//   measurement,channel=0,devSn=1234567,measurementType=Humidity,name=North,type=HortiPlantSensor value=55.5,value1=55i,value2=500000i 1700000000
+//   heartbeat,devSn=42,type=HortiLed firmware=16909056i,infobits=0i,rloc16=1024i,status=2i,uptime=3600i 1700000000
+//   neighbor,devSn=42,rloc16=0x0c00 averageRssi=-62i,child=false,fnd=false,ftd=true,linkQuality=3i,rssi=-60i,rxOnIdle=true 1700000000
+//
+// name and type are only tagged for devices the exporter knows, see Exporter::device.
+
+pub const MEASUREMENT: &str = "measurement";
+pub const HEART_BEAT: &str = "heartbeat";
+pub const NEIGHBOR: &str = "neighbor";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Precision {
+    #[default]
+    Seconds,
+    Milliseconds,
+    Microseconds,
+    Nanoseconds,
+}
+impl Precision {
+    // Value of the precision parameter of the write API
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Precision::Seconds => "s",
+            Precision::Milliseconds => "ms",
+            Precision::Microseconds => "us",
+            Precision::Nanoseconds => "ns",
+        }
+    }
+    pub fn timestamp(&self, time: DateTime<Utc>) -> i64 {
+        match self {
+            Precision::Seconds => time.timestamp(),
+            Precision::Milliseconds => time.timestamp_millis(),
+            Precision::Microseconds => time.timestamp_micros(),
+            Precision::Nanoseconds => time.timestamp_nanos_opt().unwrap_or(i64::MAX),
+        }
+    }
+    pub fn time(&self, timestamp: i64) -> Option<DateTime<Utc>> {
+        match self {
+            Precision::Seconds => DateTime::from_timestamp(timestamp, 0),
+            Precision::Milliseconds => DateTime::from_timestamp_millis(timestamp),
+            Precision::Microseconds => DateTime::from_timestamp_micros(timestamp),
+            Precision::Nanoseconds => Some(DateTime::from_timestamp_nanos(timestamp)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum FieldValue {
+    Float(f64),
+    Integer(i64),
+    UInteger(u64),
+    Bool(bool),
+    String(String),
+}
+impl FieldValue {
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            FieldValue::Integer(i) => Some(*i),
+            FieldValue::UInteger(u) => i64::try_from(*u).ok(),
+            _ => None,
+        }
+    }
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            FieldValue::Float(f) => Some(*f),
+            FieldValue::Integer(i) => Some(*i as f64),
+            FieldValue::UInteger(u) => Some(*u as f64),
+            _ => None,
+        }
+    }
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            FieldValue::Bool(b) => Some(*b),
+            _ => None,
+        }
+    }
+}
+impl fmt::Display for FieldValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            // Debug keeps the decimal point, 1.0 stays a float
+            FieldValue::Float(v) => write!(f, "{v:?}"),
+            FieldValue::Integer(v) => write!(f, "{v}i"),
+            FieldValue::UInteger(v) => write!(f, "{v}u"),
+            FieldValue::Bool(v) => write!(f, "{v}"),
+            // Line breaks would end the line here as well
+            FieldValue::String(v) => write!(
+                f,
+                "\"{}\"",
+                v.replace(['\n', '\r'], " ")
+                    .replace('\\', "\\\\")
+                    .replace('"', "\\\"")
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum LineError {
+    Syntax { line: usize, reason: String },
+    MissingTag(&'static str),
+    MissingField(&'static str),
+    InvalidValue(String),
+    WrongMeasurement(String),
+}
+impl fmt::Display for LineError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LineError::Syntax { line, reason } => write!(f, "Line {line}: {reason}"),
+            LineError::MissingTag(tag) => write!(f, "Missing tag {tag}"),
+            LineError::MissingField(field) => write!(f, "Missing field {field}"),
+            LineError::InvalidValue(value) => write!(f, "Invalid value {value}"),
+            LineError::WrongMeasurement(name) => write!(f, "Unexpected measurement {name}"),
+        }
+    }
+}
+impl std::error::Error for LineError {}
+
+// Tags are kept sorted, as InfluxDB recommends
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Point {
+    pub measurement: String,
+    pub tags: BTreeMap<String, String>,
+    pub fields: BTreeMap<String, FieldValue>,
+    pub time: Option<DateTime<Utc>>,
+}
+impl Point {
+    pub fn new(measurement: &str) -> Self {
+        Point {
+            measurement: measurement.to_string(),
+            ..Default::default()
+        }
+    }
+    pub fn tag(mut self, key: &str, value: impl ToString) -> Self {
+        self.tags.insert(key.to_string(), value.to_string());
+        self
+    }
+    pub fn field(mut self, key: &str, value: FieldValue) -> Self {
+        self.fields.insert(key.to_string(), value);
+        self
+    }
+    pub fn time(mut self, time: DateTime<Utc>) -> Self {
+        self.time = Some(time);
+        self
+    }
+    pub fn to_line(&self, precision: Precision) -> String {
+        let mut line = escape(&self.measurement, &[',', ' ']);
+        for (key, value) in &self.tags {
+            // Empty tag values are not allowed
+            if !value.is_empty() {
+                line += &format!(",{}={}", escape(key, TAG), escape(value, TAG));
+            }
+        }
+        let fields: Vec<String> = self
+            .writable_fields()
+            .map(|(key, value)| format!("{}={value}", escape(key, TAG)))
+            .collect();
+        line += " ";
+        line += &fields.join(",");
+        if let Some(time) = self.time {
+            line += &format!(" {}", precision.timestamp(time));
+        }
+        line
+    }
+    // NaN and infinity can not be written, those fields are left out
+    fn writable_fields(&self) -> impl Iterator<Item = (&String, &FieldValue)> {
+        self.fields
+            .iter()
+            .filter(|(_, value)| !matches!(value, FieldValue::Float(v) if !v.is_finite()))
+    }
+    pub fn parse(line: &str, precision: Precision) -> Result<Point, String> {
+        let line = line.trim();
+        if line.is_empty() {
+            return Err("empty line".to_string());
+        }
+        // Quotes only delimit string field values, in the series they are plain characters
+        let series = split_unescaped(line, ' ', false)[0];
+        let rest = line[series.len()..].trim_start();
+        let mut parts = split_unescaped(rest, ' ', true).into_iter();
+        let fields = parts.next().filter(|f| !f.is_empty()).ok_or("no fields")?;
+        let time = parts.next();
+        if parts.next().is_some() {
+            return Err("trailing characters".to_string());
+        }
+        let mut series = split_unescaped(series, ',', false).into_iter();
+        let mut point = Point::new(&unescape(series.next().unwrap_or_default()));
+        if point.measurement.is_empty() {
+            return Err("no measurement".to_string());
+        }
+        for tag in series {
+            let (key, value) = split_pair(tag, false)?;
+            point.tags.insert(unescape(key), unescape(value));
+        }
+        for field in split_unescaped(fields, ',', true) {
+            let (key, value) = split_pair(field, true)?;
+            point.fields.insert(unescape(key), parse_field(value)?);
+        }
+        if point.fields.is_empty() {
+            return Err("no fields".to_string());
+        }
+        if let Some(time) = time {
+            let timestamp = time
+                .parse()
+                .map_err(|_| format!("invalid timestamp {time}"))?;
+            point.time = Some(
+                precision
+                    .time(timestamp)
+                    .ok_or(format!("timestamp {time} out of range"))?,
+            );
+        }
+        Ok(point)
+    }
+    fn tag_value(&self, tag: &'static str) -> Result<&str, LineError> {
+        self.tags
+            .get(tag)
+            .map(String::as_str)
+            .ok_or(LineError::MissingTag(tag))
+    }
+    fn field_value(&self, field: &'static str) -> Result<&FieldValue, LineError> {
+        self.fields.get(field).ok_or(LineError::MissingField(field))
+    }
+    fn int<T: TryFrom<i64>>(&self, field: &'static str) -> Result<T, LineError> {
+        let value = self.field_value(field)?;
+        value
+            .as_i64()
+            .and_then(|v| T::try_from(v).ok())
+            .ok_or_else(|| LineError::InvalidValue(format!("{field}={value}")))
+    }
+    fn bool(&self, field: &'static str) -> Result<bool, LineError> {
+        let value = self.field_value(field)?;
+        value
+            .as_bool()
+            .ok_or_else(|| LineError::InvalidValue(format!("{field}={value}")))
+    }
+    fn dev_sn(&self) -> Result<u64, LineError> {
+        let dev_sn = self.tag_value("devSn")?;
+        dev_sn
+            .parse()
+            .map_err(|_| LineError::InvalidValue(format!("devSn={dev_sn}")))
+    }
+    fn expect(&self, measurement: &str) -> Result<(), LineError> {
+        match self.measurement == measurement {
+            true => Ok(()),
+            false => Err(LineError::WrongMeasurement(self.measurement.clone())),
+        }
+    }
+
+    // value1 and value2 are exact, value is used when they are missing
+    pub fn to_measurement(&self) -> Result<(u64, Measurement), LineError> {
+        self.expect(MEASUREMENT)?;
+        let channel = self.tag_value("channel")?;
+        let channel = SensorChannel::from(
+            channel
+                .parse::<u8>()
+                .map_err(|_| LineError::InvalidValue(format!("channel={channel}")))?,
+        );
+        let name = self.tag_value("measurementType")?;
+        let measurement_type = match name
+            .strip_prefix("Other(")
+            .and_then(|n| n.strip_suffix(')'))
+        {
+            Some(n) => n.parse::<u8>().ok().map(MeasurementType::from),
+            None => Some(MeasurementType::from(name)).filter(|t| t.to_string() == name),
+        }
+        .ok_or_else(|| LineError::InvalidValue(format!("measurementType={name}")))?;
+        let measurement = match (self.int("value1"), self.int("value2")) {
+            (Ok(value1), Ok(value2)) => Measurement {
+                channel,
+                measurement_type,
+                value1,
+                value2,
+            },
+            _ => {
+                let value = self.field_value("value")?;
+                let value = value
+                    .as_f64()
+                    .ok_or_else(|| LineError::InvalidValue(format!("value={value}")))?;
+                Measurement::with_value(channel, measurement_type, value)
+                    .ok_or_else(|| LineError::InvalidValue(format!("value={value}")))?
+            }
+        };
+        Ok((self.dev_sn()?, measurement))
+    }
+    pub fn to_heartbeat(&self) -> Result<HeartBeat, LineError> {
+        self.expect(HEART_BEAT)?;
+        Ok(HeartBeat {
+            id: self.dev_sn()?,
+            fwver: self.int::<u32>("firmware")?,
+            devtype: match self.tags.get("type") {
+                Some(name) => DevType::from(name.as_str()),
+                None => DevType::from(self.int::<u8>("devType")?),
+            },
+            rloc16: self.int("rloc16")?,
+            status: DevStatus::from(self.int::<u8>("status")?),
+            uptime: self.int("uptime")?,
+            infobits: self.int("infobits")?,
+        })
+    }
+    pub fn to_neighbor(&self) -> Result<(u64, Neighbor), LineError> {
+        self.expect(NEIGHBOR)?;
+        let rloc16 = self.tag_value("rloc16")?;
+        let neighbor = Neighbor {
+            rloc16: u16::from_str_radix(rloc16.trim_start_matches("0x"), 16)
+                .map_err(|_| LineError::InvalidValue(format!("rloc16={rloc16}")))?,
+            m_last_rssi: self.int("rssi")?,
+            m_link_quality: self.int("linkQuality")?,
+            m_average_rssi: self.int("averageRssi")?,
+            rx_on_idle: self.bool("rxOnIdle")?,
+            child: self.bool("child")?,
+            ftd: self.bool("ftd")?,
+            fnd: self.bool("fnd")?,
+        };
+        Ok((self.dev_sn()?, neighbor))
+    }
+}
+
+// Skips empty lines and comments
+pub fn parse_lines(text: &str, precision: Precision) -> Result<Vec<Point>, LineError> {
+    text.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty() && !line.trim_start().starts_with('#'))
+        .map(|(n, line)| {
+            Point::parse(line, precision).map_err(|reason| LineError::Syntax {
+                line: n + 1,
+                reason,
+            })
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Exporter {
+    precision: Precision,
+    devices: HashMap<u64, (Option<String>, DevType)>,
+}
+impl Exporter {
+    pub fn new(precision: Precision) -> Self {
+        Exporter {
+            precision,
+            devices: HashMap::new(),
+        }
+    }
+    // Adds the name and type tags to the points of the device
+    pub fn device(mut self, dev_info: &DevInfo) -> Self {
+        self.devices
+            .insert(dev_info.dev_sn, (dev_info.name.clone(), dev_info.dev_type));
+        self
+    }
+    pub fn precision(&self) -> Precision {
+        self.precision
+    }
+    fn device_point(&self, measurement: &str, dev_sn: u64) -> Point {
+        let point = Point::new(measurement).tag("devSn", dev_sn);
+        match self.devices.get(&dev_sn) {
+            Some((name, dev_type)) => point
+                .tag("name", name.as_deref().unwrap_or(""))
+                .tag("type", dev_type),
+            None => point,
+        }
+    }
+    pub fn measurement(
+        &self,
+        dev_sn: u64,
+        measurement: &Measurement,
+        time: DateTime<Utc>,
+    ) -> Point {
+        let channel: i32 = measurement.channel.into();
+        self.device_point(MEASUREMENT, dev_sn)
+            .tag("channel", channel)
+            .tag("measurementType", &measurement.measurement_type)
+            .field("value", FieldValue::Float(measurement.value()))
+            .field("value1", FieldValue::Integer(measurement.value1.into()))
+            .field("value2", FieldValue::Integer(measurement.value2.into()))
+            .time(time)
+    }
+    // The id of ApiMeasurements is the device serial, every point gets the updated time
+    pub fn measurements(&self, measurements: &ApiMeasurements) -> Vec<Point> {
+        let dev_sn = measurements.id().parse().unwrap_or_default();
+        measurements
+            .as_slice()
+            .iter()
+            .map(|m| self.measurement(dev_sn, m, measurements.updated()))
+            .collect()
+    }
+    pub fn heartbeat(&self, heartbeat: &HeartBeat, time: DateTime<Utc>) -> Point {
+        let point = self.device_point(HEART_BEAT, heartbeat.id);
+        // The heartbeat carries the type, known devices are tagged already
+        let point = match point.tags.contains_key("type") {
+            true => point,
+            false => point.tag("type", heartbeat.devtype),
+        };
+        point
+            .field("firmware", FieldValue::Integer(heartbeat.fwver.into()))
+            .field(
+                "status",
+                FieldValue::Integer(u8::from(heartbeat.status).into()),
+            )
+            .field("rloc16", FieldValue::Integer(heartbeat.rloc16.into()))
+            .field("uptime", FieldValue::Integer(heartbeat.uptime.into()))
+            .field("infobits", FieldValue::Integer(heartbeat.infobits.into()))
+            .time(time)
+    }
+    pub fn neighbor(&self, dev_sn: u64, neighbor: &Neighbor, time: DateTime<Utc>) -> Point {
+        self.device_point(NEIGHBOR, dev_sn)
+            .tag("rloc16", format!("{:#06x}", neighbor.rloc16))
+            .field("rssi", FieldValue::Integer(neighbor.m_last_rssi.into()))
+            .field(
+                "averageRssi",
+                FieldValue::Integer(neighbor.m_average_rssi.into()),
+            )
+            .field(
+                "linkQuality",
+                FieldValue::Integer(neighbor.m_link_quality.into()),
+            )
+            .field("rxOnIdle", FieldValue::Bool(neighbor.rx_on_idle))
+            .field("child", FieldValue::Bool(neighbor.child))
+            .field("ftd", FieldValue::Bool(neighbor.ftd))
+            .field("fnd", FieldValue::Bool(neighbor.fnd))
+            .time(time)
+    }
+    // One line per point, ready for the write API with precision() as parameter
+    pub fn lines(&self, points: &[Point]) -> String {
+        // A point without fields is not a valid line
+        points
+            .iter()
+            .filter(|p| p.writable_fields().next().is_some())
+            .map(|p| p.to_line(self.precision) + "\n")
+            .collect()
+    }
+}
+
+const TAG: &[char] = &[',', '=', ' '];
+
+// Line breaks can not be escaped, they would end the line. They become spaces.
+fn escape(s: &str, special: &[char]) -> String {
+    let mut ret = String::with_capacity(s.len());
+    for c in s.chars() {
+        let c = match c {
+            '\n' | '\r' => ' ',
+            c => c,
+        };
+        if special.contains(&c) || c == '\\' {
+            ret.push('\\');
+        }
+        ret.push(c);
+    }
+    ret
+}
+
+fn unescape(s: &str) -> String {
+    let mut ret = String::with_capacity(s.len());
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => ret.extend(chars.next()),
+            c => ret.push(c),
+        }
+    }
+    ret
+}
+
+// Splits on separators that are not escaped, with `quotes` also not inside double quotes
+fn split_unescaped(s: &str, separator: char, quotes: bool) -> Vec<&str> {
+    let mut parts = vec![];
+    let (mut start, mut escaped, mut quoted) = (0, false, false);
+    for (n, c) in s.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '"' if quotes => quoted = !quoted,
+            c if c == separator && !quoted => {
+                parts.push(&s[start..n]);
+                start = n + c.len_utf8();
+            }
+            _ => {}
+        }
+    }
+    parts.push(&s[start..]);
+    parts
+}
+
+fn split_pair(s: &str, quotes: bool) -> Result<(&str, &str), String> {
+    match split_unescaped(s, '=', quotes).as_slice() {
+        [key, value] if !key.is_empty() => Ok((key, value)),
+        _ => Err(format!("invalid key=value {s}")),
+    }
+}
+
+fn parse_field(value: &str) -> Result<FieldValue, String> {
+    let invalid = || format!("invalid field value {value}");
+    let field = match value {
+        "t" | "T" | "true" | "True" | "TRUE" => FieldValue::Bool(true),
+        "f" | "F" | "false" | "False" | "FALSE" => FieldValue::Bool(false),
+        v if v.len() >= 2 && v.starts_with('"') && v.ends_with('"') => {
+            FieldValue::String(unescape(&v[1..v.len() - 1]))
+        }
+        v if v.ends_with('i') => {
+            FieldValue::Integer(v[..v.len() - 1].parse().map_err(|_| invalid())?)
+        }
+        v if v.ends_with('u') => {
+            FieldValue::UInteger(v[..v.len() - 1].parse().map_err(|_| invalid())?)
+        }
+        v => FieldValue::Float(v.parse().map_err(|_| invalid())?),
+    };
+    Ok(field)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn time() -> DateTime<Utc> {
+        DateTime::from_timestamp(1_700_000_000, 250_000_000).unwrap()
+    }
+
+    fn dev_info() -> DevInfo {
+        let mut dev_info = DevInfo::unknown_device(0x400);
+        dev_info.dev_sn = 1234567;
+        dev_info.name = Some("North wall, 2=B".to_string());
+        dev_info.dev_type = DevType::HortiPlantSensor;
+        dev_info
+    }
+
+    #[test]
+    fn measurement_lines() {
+        let exporter = Exporter::new(Precision::Milliseconds).device(&dev_info());
+        let measurement =
+            Measurement::with_value(0.into(), MeasurementType::Humidity, 55.5).unwrap();
+        let point = exporter.measurement(1234567, &measurement, time());
+        let line = point.to_line(exporter.precision());
+        assert_eq!(
+            line,
+            "measurement,channel=0,devSn=1234567,measurementType=Humidity,name=North\\ wall\\,\\ 2\\=B,type=HortiPlantSensor value=55.5,value1=55i,value2=500000i 1700000000250"
+        );
+        let parsed = Point::parse(&line, Precision::Milliseconds).unwrap();
+        assert_eq!(parsed, point);
+        assert_eq!(parsed.tags["name"], "North wall, 2=B");
+        assert_eq!(parsed.to_measurement().unwrap(), (1234567, measurement));
+    }
+
+    #[test]
+    fn names_with_quotes_and_line_breaks() {
+        let mut dev_info = dev_info();
+        dev_info.name = Some("Bob\"s panel\nmeasurement,devSn=1 value=1\r".to_string());
+        let exporter = Exporter::new(Precision::Seconds).device(&dev_info);
+        let measurement =
+            Measurement::with_value(0.into(), MeasurementType::Humidity, 55.5).unwrap();
+        let point = exporter
+            .measurement(1234567, &measurement, time())
+            .tag("a\nb", "x")
+            .field("c\rd", FieldValue::String("say \"hi\"\r\nbye".to_string()));
+        let text = exporter.lines(&[point]);
+        assert_eq!(text.lines().count(), 1);
+        let parsed = parse_lines(&text, Precision::Seconds).unwrap();
+        assert_eq!(parsed.len(), 1);
+        assert_eq!(
+            parsed[0].tags["name"],
+            "Bob\"s panel measurement,devSn=1 value=1 "
+        );
+        assert_eq!(parsed[0].tags["a b"], "x");
+        assert_eq!(
+            parsed[0].fields["c d"],
+            FieldValue::String("say \"hi\"  bye".to_string())
+        );
+        assert_eq!(parsed[0].to_measurement().unwrap(), (1234567, measurement));
+    }
+
+    #[test]
+    fn non_finite_fields_are_left_out() {
+        let exporter = Exporter::new(Precision::Seconds);
+        let point = Point::new("m")
+            .field("a", FieldValue::Float(f64::NAN))
+            .field("b", FieldValue::Float(1.5))
+            .time(time());
+        let empty = Point::new("m").field("a", FieldValue::Float(f64::INFINITY));
+        assert_eq!(exporter.lines(&[point, empty]), "m b=1.5 1700000000\n");
+    }
+
+    #[test]
+    fn heartbeat_and_neighbor_round_trip() {
+        let exporter = Exporter::new(Precision::Seconds);
+        let heartbeat = HeartBeat::new()
+            .id(42)
+            .devtype(1)
+            .status(2)
+            .fwver(0x01020300)
+            .rloc16(0x400)
+            .uptime(std::time::Duration::from_secs(3600));
+        let mut neighbor = Neighbor::new();
+        neighbor.rloc16(0x0c00);
+        neighbor.m_last_rssi = -60;
+        neighbor.m_average_rssi = -62;
+        neighbor.m_link_quality = 3;
+        let text = exporter.lines(&[
+            exporter.heartbeat(&heartbeat, time()),
+            exporter.neighbor(42, &neighbor, time()),
+        ]);
+        assert_eq!(
+            text,
+            "heartbeat,devSn=42,type=HortiLed firmware=16909056i,infobits=0i,rloc16=1024i,status=2i,uptime=3600i 1700000000\n\
+             neighbor,devSn=42,rloc16=0x0c00 averageRssi=-62i,child=false,fnd=false,ftd=true,linkQuality=3i,rssi=-60i,rxOnIdle=true 1700000000\n"
+        );
+        let points = parse_lines(&format!("# backfill\n\n{text}"), Precision::Seconds).unwrap();
+        assert_eq!(
+            points[0].time,
+            Some(DateTime::from_timestamp(1_700_000_000, 0).unwrap())
+        );
+        assert_eq!(points[0].to_heartbeat().unwrap(), heartbeat);
+        assert_eq!(points[1].to_neighbor().unwrap(), (42, neighbor));
+        assert_eq!(
+            points[1].to_heartbeat(),
+            Err(LineError::WrongMeasurement("neighbor".to_string()))
+        );
+    }
+
+    #[test]
+    fn parse_field_types() {
+        let point = Point::parse(
+            r#"m\,x,t\ 1=a\=b f="say \"hi\", ok",i=-3i,u=7u,b=t,x=1e3"#,
+            Precision::Nanoseconds,
+        )
+        .unwrap();
+        assert_eq!(point.measurement, "m,x");
+        assert_eq!(point.tags["t 1"], "a=b");
+        assert_eq!(
+            point.fields["f"],
+            FieldValue::String("say \"hi\", ok".to_string())
+        );
+        assert_eq!(point.fields["i"], FieldValue::Integer(-3));
+        assert_eq!(point.fields["u"], FieldValue::UInteger(7));
+        assert_eq!(point.fields["b"], FieldValue::Bool(true));
+        assert_eq!(point.fields["x"], FieldValue::Float(1000.0));
+        assert_eq!(point.time, None);
+        assert_eq!(
+            Point::parse(&point.to_line(Precision::Seconds), Precision::Seconds),
+            Ok(point)
+        );
+
+        let err = parse_lines("ok v=1\nbroken", Precision::Seconds).unwrap_err();
+        assert_eq!(err.to_string(), "Line 2: no fields");
+        let err = Point::parse("m v=1 x", Precision::Seconds).unwrap_err();
+        assert_eq!(err, "invalid timestamp x");
+        let point =
+            Point::parse("measurement,devSn=1,channel=0 value=1", Precision::Seconds).unwrap();
+        assert_eq!(
+            point.to_measurement(),
+            Err(LineError::MissingTag("measurementType"))
+        );
+        let point = Point::parse(
+            "measurement,devSn=1,channel=0,measurementType=Humidity value=1e12",
+            Precision::Seconds,
+        )
+        .unwrap();
+        assert_eq!(
+            point.to_measurement(),
+            Err(LineError::InvalidValue("value=1000000000000".to_string()))
+        );
+    }
+}
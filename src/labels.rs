@@ -0,0 +1,522 @@
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::path::Path;
+
+use crate::devices_connected::DevicesConnectedTypes;
+use crate::devs::hb::{DevStatus, DevType};
+use crate::measurement::MeasurementType;
+use crate::settings::SettingsType;
+
+pub const EN: &str = "en";
+pub const NB: &str = "nb";
+
+// Translations are flat JSON objects from label key to text, e.g.
+// { "devType.HortiLed": "Horticulture: LED-panel", "unit.rpm": "rpm" }
+// Lookups fall back to English, then to the identifier itself.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Labels {
+    locales: HashMap<String, HashMap<String, String>>,
+}
+impl Default for Labels {
+    fn default() -> Self {
+        let mut ret = Labels {
+            locales: HashMap::new(),
+        };
+        ret.add_table(EN, LABELS_EN);
+        ret.add_table(NB, LABELS_NB);
+        ret
+    }
+}
+impl Labels {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    fn add_table(&mut self, locale: &str, table: &[(&str, &str)]) {
+        let labels = self.locales.entry(locale.to_string()).or_default();
+        for (key, label) in table {
+            labels.insert(key.to_string(), label.to_string());
+        }
+    }
+    // Adds to or replaces labels of a locale
+    pub fn load_str(&mut self, locale: &str, json: &str) -> Result<(), LabelError> {
+        let table: HashMap<String, String> = serde_json::from_str(json)?;
+        self.locales
+            .entry(locale.to_string())
+            .or_default()
+            .extend(table);
+        Ok(())
+    }
+    pub fn load_file(&mut self, locale: &str, path: impl AsRef<Path>) -> Result<(), LabelError> {
+        let json = std::fs::read_to_string(path)?;
+        self.load_str(locale, &json)
+    }
+    pub fn locales(&self) -> Vec<&str> {
+        let mut ret: Vec<&str> = self.locales.keys().map(|l| l.as_str()).collect();
+        ret.sort();
+        ret
+    }
+    pub fn get(&self, locale: &str, key: &str) -> Option<&str> {
+        self.locales
+            .get(locale)
+            .and_then(|l| l.get(key))
+            .or_else(|| self.locales.get(EN).and_then(|l| l.get(key)))
+            .map(|l| l.as_str())
+    }
+    pub fn label(&self, locale: &str, item: &impl Label) -> String {
+        match self.get(locale, &item.label_key()) {
+            Some(label) => label.to_string(),
+            None => item.fallback_label(),
+        }
+    }
+    pub fn unit(&self, locale: &str, unit: &str) -> String {
+        self.get(locale, &format!("unit.{unit}"))
+            .unwrap_or(unit)
+            .to_string()
+    }
+}
+
+pub trait Label {
+    fn label_key(&self) -> String;
+    fn fallback_label(&self) -> String;
+}
+impl Label for DevType {
+    fn label_key(&self) -> String {
+        match self {
+            DevType::Unknown(_) => "devType.Unknown".to_string(),
+            t => format!("devType.{t}"),
+        }
+    }
+    fn fallback_label(&self) -> String {
+        self.to_string()
+    }
+}
+impl Label for DevStatus {
+    fn label_key(&self) -> String {
+        match self {
+            DevStatus::Unknown(_) => "devStatus.Unknown".to_string(),
+            s => format!("devStatus.{s}"),
+        }
+    }
+    fn fallback_label(&self) -> String {
+        self.to_string()
+    }
+}
+impl Label for MeasurementType {
+    fn label_key(&self) -> String {
+        match self {
+            MeasurementType::Other(_) => "measurementType.Other".to_string(),
+            t => format!("measurementType.{t}"),
+        }
+    }
+    fn fallback_label(&self) -> String {
+        self.to_string()
+    }
+}
+impl Label for DevicesConnectedTypes {
+    fn label_key(&self) -> String {
+        match self {
+            DevicesConnectedTypes::Other(_) => "peripheralType.Other".to_string(),
+            t => format!("peripheralType.{t:?}"),
+        }
+    }
+    fn fallback_label(&self) -> String {
+        format!("{self:?}")
+    }
+}
+impl Label for SettingsType {
+    fn label_key(&self) -> String {
+        match self {
+            SettingsType::Unknown(_) => "settingType.Unknown".to_string(),
+            t => format!("settingType.{t:?}"),
+        }
+    }
+    fn fallback_label(&self) -> String {
+        format!("{self:?}")
+    }
+}
+
+#[derive(Debug)]
+pub enum LabelError {
+    Io(std::io::Error),
+    Parse(serde_json::Error),
+}
+impl Display for LabelError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LabelError::Io(e) => write!(f, "Unable to read translations: {e}"),
+            LabelError::Parse(e) => write!(f, "Invalid translation file: {e}"),
+        }
+    }
+}
+impl std::error::Error for LabelError {}
+impl From<std::io::Error> for LabelError {
+    fn from(value: std::io::Error) -> Self {
+        LabelError::Io(value)
+    }
+}
+impl From<serde_json::Error> for LabelError {
+    fn from(value: serde_json::Error) -> Self {
+        LabelError::Parse(value)
+    }
+}
+
+const LABELS_EN: &[(&str, &str)] = &[
+    ("devType.BorderRouter", "BorderRouter"),
+    ("devType.HortiLed", "Horticulture: LED-panel"),
+    ("devType.HortiPlantSensor", "Horticulture: Soil Sensor"),
+    ("devType.WeatherStation", "Weather Station"),
+    ("devType.EnvironmentSensor", "Environment sensor"),
+    ("devType.GarageDoor", "Garage door control"),
+    ("devType.GetshopModule", "GetShop Module 1.5"),
+    ("devType.GetshopLock", "GetShop Module 1.9"),
+    ("devType.StaySerosModule", "StaySeros Module"),
+    ("devType.StayIdlock", "StayIdlock"),
+    ("devType.TeLys", "TeLys"),
+    ("devType.Unknown", "Unknown device"),
+    ("devStatus.Unknown", "Unknown"),
+    ("devStatus.Error", "Error"),
+    ("devStatus.RunningOk", "Running"),
+    ("devStatus.Downloading", "Downloading firmware"),
+    ("devStatus.Flashing", "Updating firmware"),
+    ("devStatus.Rebooting", "Rebooting"),
+    ("devStatus.Offline", "Offline"),
+    ("measurementType.AccelX", "Acceleration X"),
+    ("measurementType.AccelY", "Acceleration Y"),
+    ("measurementType.AccelZ", "Acceleration Z"),
+    ("measurementType.AccelXYZ", "Acceleration"),
+    ("measurementType.GyroX", "Rotation rate X"),
+    ("measurementType.GyroY", "Rotation rate Y"),
+    ("measurementType.GyroZ", "Rotation rate Z"),
+    ("measurementType.GyroXYZ", "Rotation rate"),
+    ("measurementType.MagnX", "Magnetic field X"),
+    ("measurementType.MagnY", "Magnetic field Y"),
+    ("measurementType.MagnZ", "Magnetic field Z"),
+    ("measurementType.MagnXYZ", "Magnetic field"),
+    ("measurementType.DieTemp", "Chip temperature"),
+    ("measurementType.AmbientTemperature", "Temperature"),
+    ("measurementType.Pressure", "Pressure"),
+    ("measurementType.Proximity", "Proximity"),
+    ("measurementType.Humidity", "Humidity"),
+    ("measurementType.IlluminanceVisible", "Light"),
+    ("measurementType.IlluminanceInfraRed", "Infrared light"),
+    ("measurementType.IlluminanceRed", "Red light"),
+    ("measurementType.IlluminanceGreen", "Green light"),
+    ("measurementType.IlluminanceBlue", "Blue light"),
+    ("measurementType.Altitude", "Altitude"),
+    ("measurementType.PM1_0", "PM1.0"),
+    ("measurementType.PM2_5", "PM2.5"),
+    ("measurementType.PM10", "PM10"),
+    ("measurementType.Distance", "Distance"),
+    ("measurementType.Co2Level", "CO₂"),
+    ("measurementType.O2Level", "O₂"),
+    ("measurementType.VocLevel", "VOC"),
+    (
+        "measurementType.GasSensorResistance",
+        "Gas sensor resistance",
+    ),
+    ("measurementType.Voltage", "Voltage"),
+    ("measurementType.ShuntVoltage", "Shunt voltage"),
+    ("measurementType.Current", "Current"),
+    ("measurementType.Power", "Power"),
+    ("measurementType.Resistance", "Resistance"),
+    ("measurementType.Rotation", "Rotation"),
+    ("measurementType.PositionDeltaX", "Position change X"),
+    ("measurementType.PositionDeltaY", "Position change Y"),
+    ("measurementType.PositionDeltaZ", "Position change Z"),
+    ("measurementType.RPM", "Speed"),
+    ("measurementType.GaugeVoltage", "Battery voltage"),
+    ("measurementType.GaugeAvgCurrent", "Battery average current"),
+    (
+        "measurementType.GaugeStandbyCurrent",
+        "Battery standby current",
+    ),
+    (
+        "measurementType.GaugeMaxLoadCurrent",
+        "Battery max load current",
+    ),
+    ("measurementType.GaugeTemperature", "Battery temperature"),
+    ("measurementType.GaugeStateOfCharge", "Battery charge"),
+    (
+        "measurementType.GaugeFullChargeCapacity",
+        "Battery full charge capacity",
+    ),
+    (
+        "measurementType.GaugeRemainingChargeCapacity",
+        "Battery remaining capacity",
+    ),
+    (
+        "measurementType.GaugeNominalAvailableCapacity",
+        "Battery nominal available capacity",
+    ),
+    (
+        "measurementType.GaugeFullAvailableCapacity",
+        "Battery full available capacity",
+    ),
+    ("measurementType.GaugeAvgPower", "Battery average power"),
+    ("measurementType.GaugeStateOfHealth", "Battery health"),
+    ("measurementType.GaugeTimeToEmpty", "Battery time to empty"),
+    ("measurementType.GaugeTimeToFull", "Battery time to full"),
+    ("measurementType.GaugeCycleCount", "Battery cycle count"),
+    (
+        "measurementType.GaugeDesignVoltage",
+        "Battery design voltage",
+    ),
+    (
+        "measurementType.GaugeDesiredVoltage",
+        "Battery charging voltage",
+    ),
+    (
+        "measurementType.GaugeDesiredChargingCurrent",
+        "Battery charging current",
+    ),
+    ("measurementType.SensorChanF1_415", "Spectrum 415 nm"),
+    ("measurementType.SensorChanF2_445", "Spectrum 445 nm"),
+    ("measurementType.SensorChanF3_480", "Spectrum 480 nm"),
+    ("measurementType.SensorChanF4_515", "Spectrum 515 nm"),
+    ("measurementType.SensorChanF5_555", "Spectrum 555 nm"),
+    ("measurementType.SensorChanF6_590", "Spectrum 590 nm"),
+    ("measurementType.SensorChanF7_630", "Spectrum 630 nm"),
+    ("measurementType.SensorChanF8_680", "Spectrum 680 nm"),
+    ("measurementType.SensorChanNir", "Near infrared"),
+    ("measurementType.PhSensor", "pH"),
+    ("measurementType.Tds", "Total dissolved solids"),
+    ("measurementType.All", "All"),
+    ("measurementType.DoorlockLogs", "Door lock log"),
+    ("measurementType.UptimeCounter", "Uptime"),
+    ("measurementType.Other", "Other"),
+    ("settingType.DevType", "Device type"),
+    ("settingType.FwBranch", "Firmware branch"),
+    ("settingType.NetworkId", "Network id"),
+    ("settingType.DimTime", "Dim time"),
+    ("settingType.TimeOn", "Time on"),
+    ("settingType.TimeOff", "Time off"),
+    ("settingType.PwmVal", "Intensity"),
+    ("settingType.LedMode", "LED mode"),
+    ("settingType.LightEffect", "Light effect"),
+    ("settingType.LogInterval", "Log interval"),
+    ("settingType.DefaultPos", "Default position"),
+    ("settingType.DefaultSpeed", "Default speed"),
+    ("settingType.DoorlockMode", "Lock mode"),
+    ("settingType.DoorlockOpenTime", "Open time"),
+    ("settingType.DoorlockCode", "Door code"),
+    ("settingType.DoorlockCodeValid", "Code valid until"),
+    ("settingType.Unknown", "Unknown setting"),
+    ("peripheralType.Default", "Default"),
+    ("peripheralType.HortiLed", "LED-panel"),
+    (
+        "peripheralType.Shmt3xSensor",
+        "Temperature and humidity sensor",
+    ),
+    ("peripheralType.WateringPump", "Watering pump"),
+    ("peripheralType.FanController", "Fan controller"),
+    ("peripheralType.FlickeringLed", "Effect light"),
+    ("peripheralType.StepperMotorDriver", "Stepper motor"),
+    ("peripheralType.DoorLock", "Door lock"),
+    ("peripheralType.DoorSensor", "Door sensor"),
+    ("peripheralType.Other", "Other peripheral"),
+];
+
+const LABELS_NB: &[(&str, &str)] = &[
+    ("devType.BorderRouter", "Grenseruter"),
+    ("devType.HortiLed", "Hagebruk: LED-panel"),
+    ("devType.HortiPlantSensor", "Hagebruk: Jordsensor"),
+    ("devType.WeatherStation", "Værstasjon"),
+    ("devType.EnvironmentSensor", "Miljøsensor"),
+    ("devType.GarageDoor", "Garasjeportstyring"),
+    ("devType.GetshopModule", "GetShop-modul 1.5"),
+    ("devType.GetshopLock", "GetShop-modul 1.9"),
+    ("devType.StaySerosModule", "StaySeros-modul"),
+    ("devType.StayIdlock", "StayIdlock"),
+    ("devType.TeLys", "TeLys"),
+    ("devType.Unknown", "Ukjent enhet"),
+    ("devStatus.Unknown", "Ukjent"),
+    ("devStatus.Error", "Feil"),
+    ("devStatus.RunningOk", "I drift"),
+    ("devStatus.Downloading", "Laster ned fastvare"),
+    ("devStatus.Flashing", "Oppdaterer fastvare"),
+    ("devStatus.Rebooting", "Starter på nytt"),
+    ("devStatus.Offline", "Frakoblet"),
+    ("measurementType.AccelX", "Akselerasjon X"),
+    ("measurementType.AccelY", "Akselerasjon Y"),
+    ("measurementType.AccelZ", "Akselerasjon Z"),
+    ("measurementType.AccelXYZ", "Akselerasjon"),
+    ("measurementType.GyroX", "Rotasjonshastighet X"),
+    ("measurementType.GyroY", "Rotasjonshastighet Y"),
+    ("measurementType.GyroZ", "Rotasjonshastighet Z"),
+    ("measurementType.GyroXYZ", "Rotasjonshastighet"),
+    ("measurementType.MagnX", "Magnetfelt X"),
+    ("measurementType.MagnY", "Magnetfelt Y"),
+    ("measurementType.MagnZ", "Magnetfelt Z"),
+    ("measurementType.MagnXYZ", "Magnetfelt"),
+    ("measurementType.DieTemp", "Brikketemperatur"),
+    ("measurementType.AmbientTemperature", "Temperatur"),
+    ("measurementType.Pressure", "Trykk"),
+    ("measurementType.Proximity", "Nærhet"),
+    ("measurementType.Humidity", "Luftfuktighet"),
+    ("measurementType.IlluminanceVisible", "Lys"),
+    ("measurementType.IlluminanceInfraRed", "Infrarødt lys"),
+    ("measurementType.IlluminanceRed", "Rødt lys"),
+    ("measurementType.IlluminanceGreen", "Grønt lys"),
+    ("measurementType.IlluminanceBlue", "Blått lys"),
+    ("measurementType.Altitude", "Høyde over havet"),
+    ("measurementType.PM1_0", "PM1,0"),
+    ("measurementType.PM2_5", "PM2,5"),
+    ("measurementType.PM10", "PM10"),
+    ("measurementType.Distance", "Avstand"),
+    ("measurementType.Co2Level", "CO₂"),
+    ("measurementType.O2Level", "O₂"),
+    ("measurementType.VocLevel", "VOC"),
+    ("measurementType.GasSensorResistance", "Gassensormotstand"),
+    ("measurementType.Voltage", "Spenning"),
+    ("measurementType.ShuntVoltage", "Shuntspenning"),
+    ("measurementType.Current", "Strøm"),
+    ("measurementType.Power", "Effekt"),
+    ("measurementType.Resistance", "Motstand"),
+    ("measurementType.Rotation", "Rotasjon"),
+    ("measurementType.PositionDeltaX", "Posisjonsendring X"),
+    ("measurementType.PositionDeltaY", "Posisjonsendring Y"),
+    ("measurementType.PositionDeltaZ", "Posisjonsendring Z"),
+    ("measurementType.RPM", "Turtall"),
+    ("measurementType.GaugeVoltage", "Batterispenning"),
+    (
+        "measurementType.GaugeAvgCurrent",
+        "Batteri gjennomsnittsstrøm",
+    ),
+    ("measurementType.GaugeStandbyCurrent", "Batteri hvilestrøm"),
+    (
+        "measurementType.GaugeMaxLoadCurrent",
+        "Batteri maks belastning",
+    ),
+    ("measurementType.GaugeTemperature", "Batteritemperatur"),
+    ("measurementType.GaugeStateOfCharge", "Batterinivå"),
+    (
+        "measurementType.GaugeFullChargeCapacity",
+        "Batterikapasitet fulladet",
+    ),
+    (
+        "measurementType.GaugeRemainingChargeCapacity",
+        "Gjenværende batterikapasitet",
+    ),
+    (
+        "measurementType.GaugeNominalAvailableCapacity",
+        "Nominell tilgjengelig batterikapasitet",
+    ),
+    (
+        "measurementType.GaugeFullAvailableCapacity",
+        "Full tilgjengelig batterikapasitet",
+    ),
+    (
+        "measurementType.GaugeAvgPower",
+        "Batteri gjennomsnittseffekt",
+    ),
+    ("measurementType.GaugeStateOfHealth", "Batterihelse"),
+    ("measurementType.GaugeTimeToEmpty", "Tid til tomt batteri"),
+    ("measurementType.GaugeTimeToFull", "Tid til fulladet"),
+    ("measurementType.GaugeCycleCount", "Antall ladesykluser"),
+    (
+        "measurementType.GaugeDesignVoltage",
+        "Batteri merkespenning",
+    ),
+    ("measurementType.GaugeDesiredVoltage", "Ladespenning"),
+    ("measurementType.GaugeDesiredChargingCurrent", "Ladestrøm"),
+    ("measurementType.SensorChanF1_415", "Spektrum 415 nm"),
+    ("measurementType.SensorChanF2_445", "Spektrum 445 nm"),
+    ("measurementType.SensorChanF3_480", "Spektrum 480 nm"),
+    ("measurementType.SensorChanF4_515", "Spektrum 515 nm"),
+    ("measurementType.SensorChanF5_555", "Spektrum 555 nm"),
+    ("measurementType.SensorChanF6_590", "Spektrum 590 nm"),
+    ("measurementType.SensorChanF7_630", "Spektrum 630 nm"),
+    ("measurementType.SensorChanF8_680", "Spektrum 680 nm"),
+    ("measurementType.SensorChanNir", "Nær-infrarødt"),
+    ("measurementType.PhSensor", "pH"),
+    ("measurementType.Tds", "Totalt oppløst stoff"),
+    ("measurementType.All", "Alle"),
+    ("measurementType.DoorlockLogs", "Dørlåslogg"),
+    ("measurementType.UptimeCounter", "Oppetid"),
+    ("measurementType.Other", "Annet"),
+    ("settingType.DevType", "Enhetstype"),
+    ("settingType.FwBranch", "Fastvaregren"),
+    ("settingType.NetworkId", "Nettverks-id"),
+    ("settingType.DimTime", "Dimmetid"),
+    ("settingType.TimeOn", "Tid på"),
+    ("settingType.TimeOff", "Tid av"),
+    ("settingType.PwmVal", "Lysstyrke"),
+    ("settingType.LedMode", "LED-modus"),
+    ("settingType.LightEffect", "Lyseffekt"),
+    ("settingType.LogInterval", "Loggintervall"),
+    ("settingType.DefaultPos", "Standardposisjon"),
+    ("settingType.DefaultSpeed", "Standardhastighet"),
+    ("settingType.DoorlockMode", "Låsemodus"),
+    ("settingType.DoorlockOpenTime", "Åpningstid"),
+    ("settingType.DoorlockCode", "Dørkode"),
+    ("settingType.DoorlockCodeValid", "Kode gyldig til"),
+    ("settingType.Unknown", "Ukjent innstilling"),
+    ("peripheralType.Default", "Standard"),
+    ("peripheralType.HortiLed", "LED-panel"),
+    ("peripheralType.Shmt3xSensor", "Temperatur- og fuktsensor"),
+    ("peripheralType.WateringPump", "Vanningspumpe"),
+    ("peripheralType.FanController", "Viftestyring"),
+    ("peripheralType.FlickeringLed", "Effektlys"),
+    ("peripheralType.StepperMotorDriver", "Trinnmotor"),
+    ("peripheralType.DoorLock", "Dørlås"),
+    ("peripheralType.DoorSensor", "Dørsensor"),
+    ("peripheralType.Other", "Annen enhet"),
+    ("unit.rpm", "o/min"),
+    ("unit.steps", "steg"),
+    ("unit.steps/s", "steg/s"),
+    ("unit.unix time", "unix-tid"),
+];
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn bundled_locales() {
+        let labels = Labels::new();
+        assert_eq!(labels.locales(), vec![EN, NB]);
+        assert_eq!(
+            labels.label(EN, &DevType::HortiLed),
+            <DevType as Into<&'static str>>::into(DevType::HortiLed)
+        );
+        assert_eq!(labels.label(NB, &DevType::HortiLed), "Hagebruk: LED-panel");
+        assert_eq!(labels.label(NB, &DevStatus::Unknown(9)), "Ukjent");
+        assert_eq!(
+            labels.label(NB, &MeasurementType::AmbientTemperature),
+            "Temperatur"
+        );
+        assert_eq!(labels.label(NB, &SettingsType::TimeOn), "Tid på");
+        assert_eq!(labels.unit(NB, "rpm"), "o/min");
+        assert_eq!(labels.unit(NB, "°C"), "°C");
+    }
+
+    #[test]
+    fn every_type_has_a_label() {
+        let labels = Labels::new();
+        for locale in [EN, NB] {
+            for n in 0..=255u8 {
+                for key in [
+                    DevType::from(n).label_key(),
+                    DevStatus::from(n).label_key(),
+                    MeasurementType::from(n).label_key(),
+                    SettingsType::from(i32::from(n)).label_key(),
+                    DevicesConnectedTypes::from(u16::from(n)).label_key(),
+                ] {
+                    assert!(labels.locales[locale].contains_key(&key), "{locale}: {key}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn load_translations() {
+        let mut labels = Labels::new();
+        labels
+            .load_str("sv", r#"{"devStatus.Offline": "Frånkopplad"}"#)
+            .unwrap();
+        assert_eq!(labels.label("sv", &DevStatus::Offline), "Frånkopplad");
+        assert_eq!(labels.label("sv", &DevStatus::Error), "Error");
+        assert!(labels.load_str("sv", "[1, 2]").is_err());
+        assert!(labels.load_file("sv", "/nonexistent/sv.json").is_err());
+    }
+}
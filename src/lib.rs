@@ -1,9 +1,24 @@
 pub mod api;
 pub mod api_json;
+pub mod command;
 pub mod devices_connected;
 pub mod devs;
+pub mod firmware;
+pub mod homeassistant;
+pub mod influx;
 pub mod joiner;
+pub mod labels;
+pub mod light_effect;
 pub mod measurement;
+pub mod metrics;
 pub mod neighbors;
+#[cfg(feature = "openapi")]
+pub mod openapi;
 pub mod otnet;
+#[cfg(feature = "schema")]
+pub mod schema;
 pub mod settings;
+#[cfg(feature = "cbor")]
+pub mod smp;
+#[cfg(feature = "typescript")]
+pub mod typescript;
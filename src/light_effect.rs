@@ -0,0 +1,307 @@
+use serde::{Deserialize, Serialize};
+use std::f32::consts::PI;
+
+use crate::settings::{DevSetting, SettingsType};
+
+// Light effects for the FlickeringLed peripheral.
+//
+// speed: cycles per 10 seconds (Sunrise: ramp length in minutes), 0 is treated as 1
+// depth: modulation depth in percent, the light moves between (100 - depth)% and 100%
+// seed:  start value for the pseudo random flicker (Candle only)
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize, Hash, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[repr(u8)]
+#[serde(into = "u8", from = "u8")]
+pub enum EffectKind {
+    Steady,
+    Candle,
+    Breathe,
+    Strobe,
+    Sunrise,
+    Other(u8),
+}
+impl From<u8> for EffectKind {
+    fn from(value: u8) -> Self {
+        match value {
+            0 => EffectKind::Steady,
+            1 => EffectKind::Candle,
+            2 => EffectKind::Breathe,
+            3 => EffectKind::Strobe,
+            4 => EffectKind::Sunrise,
+            n => EffectKind::Other(n),
+        }
+    }
+}
+impl From<EffectKind> for u8 {
+    fn from(value: EffectKind) -> Self {
+        match value {
+            EffectKind::Steady => 0,
+            EffectKind::Candle => 1,
+            EffectKind::Breathe => 2,
+            EffectKind::Strobe => 3,
+            EffectKind::Sunrise => 4,
+            EffectKind::Other(n) => n,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize, Hash, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct LightEffect {
+    pub kind: EffectKind,
+    pub speed: u8,
+    pub depth: u8,
+    pub seed: u8,
+}
+impl LightEffect {
+    pub fn new(kind: EffectKind, speed: u8, depth: u8, seed: u8) -> Self {
+        Self {
+            kind,
+            speed,
+            depth: depth.min(100),
+            seed,
+        }
+    }
+    pub fn steady() -> Self {
+        Self::new(EffectKind::Steady, 0, 0, 0)
+    }
+    pub fn candle(speed: u8, depth: u8, seed: u8) -> Self {
+        Self::new(EffectKind::Candle, speed, depth, seed)
+    }
+    pub fn breathe(speed: u8, depth: u8) -> Self {
+        Self::new(EffectKind::Breathe, speed, depth, 0)
+    }
+    pub fn strobe(speed: u8, depth: u8) -> Self {
+        Self::new(EffectKind::Strobe, speed, depth, 0)
+    }
+    pub fn sunrise(minutes: u8, depth: u8) -> Self {
+        Self::new(EffectKind::Sunrise, minutes, depth, 0)
+    }
+
+    // The whole effect packed in a single setting value: kind | speed << 8 | depth << 16 | seed << 24
+    pub fn to_setting(&self, channel: i32, updated_at: i32) -> DevSetting {
+        DevSetting {
+            updated_at,
+            settings_type: SettingsType::LightEffect.into(),
+            channel,
+            value: i32::from_le_bytes(self.to_bytes()),
+        }
+    }
+    pub fn from_setting(setting: &DevSetting) -> Option<LightEffect> {
+        if setting.settings_type() != SettingsType::LightEffect {
+            return None;
+        }
+        Some(LightEffect::from_bytes(setting.value.to_le_bytes()))
+    }
+    pub fn to_bytes(&self) -> [u8; 4] {
+        [self.kind.into(), self.speed, self.depth, self.seed]
+    }
+    pub fn from_bytes(bytes: [u8; 4]) -> LightEffect {
+        LightEffect::new(bytes[0].into(), bytes[1], bytes[2], bytes[3])
+    }
+
+    fn period_ms(&self) -> u32 {
+        match self.kind {
+            EffectKind::Sunrise => u32::from(self.speed.max(1)) * 60_000,
+            _ => 10_000 / u32::from(self.speed.max(1)),
+        }
+    }
+    // Intensity in 0.0..=1.0 at `t_ms` milliseconds after the effect started
+    pub fn intensity_at(&self, t_ms: u32) -> f32 {
+        let low = 1.0 - f32::from(self.depth) / 100.0;
+        let period = self.period_ms();
+        let phase = (t_ms % period) as f32 / period as f32;
+        let level = match self.kind {
+            EffectKind::Steady | EffectKind::Other(_) => 1.0,
+            EffectKind::Candle => {
+                let step = t_ms / period;
+                let a = noise(self.seed, step);
+                let b = noise(self.seed, step + 1);
+                let s = phase * phase * (3.0 - 2.0 * phase);
+                a + (b - a) * s
+            }
+            EffectKind::Breathe => (1.0 + (2.0 * PI * phase).cos()) / 2.0,
+            EffectKind::Strobe => {
+                if phase < 0.1 {
+                    1.0
+                } else {
+                    0.0
+                }
+            }
+            EffectKind::Sunrise => {
+                if t_ms >= period {
+                    1.0
+                } else {
+                    phase * phase * (3.0 - 2.0 * phase)
+                }
+            }
+        };
+        low + (1.0 - low) * level
+    }
+    // Renders the intensity curve for `duration_ms`, one sample every `sample_ms`
+    pub fn preview(&self, duration_ms: u32, sample_ms: u32) -> Vec<f32> {
+        (0..duration_ms)
+            .step_by(sample_ms.max(1) as usize)
+            .map(|t| self.intensity_at(t))
+            .collect()
+    }
+}
+impl Default for LightEffect {
+    fn default() -> Self {
+        LightEffect::steady()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize, Hash, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct LightStep {
+    pub effect: LightEffect,
+    // Seconds to run this step, 0 keeps it running forever
+    pub duration: u16,
+}
+
+// A sequence of effects, sent to the device as a binary blob:
+//
+// | 'L' 'E' | version: u8 | step count: u8 | steps: [kind, speed, depth, seed, duration: u16 le] |
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, Default, Hash, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct LightProgram {
+    steps: Vec<LightStep>,
+}
+impl LightProgram {
+    const MAGIC: [u8; 2] = *b"LE";
+    const VERSION: u8 = 1;
+    const STEP_LEN: usize = 6;
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn add_step(&mut self, effect: LightEffect, duration: u16) {
+        self.steps.push(LightStep { effect, duration });
+    }
+    pub fn as_slice(&self) -> &[LightStep] {
+        &self.steps
+    }
+    pub fn len(&self) -> usize {
+        self.steps.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.steps.is_empty()
+    }
+    // None when the program has more steps than the step count byte can hold
+    pub fn to_bytes(&self) -> Option<Vec<u8>> {
+        let count = u8::try_from(self.steps.len()).ok()?;
+        let mut ret = Vec::with_capacity(4 + self.steps.len() * Self::STEP_LEN);
+        ret.extend_from_slice(&Self::MAGIC);
+        ret.push(Self::VERSION);
+        ret.push(count);
+        for step in &self.steps {
+            ret.extend_from_slice(&step.effect.to_bytes());
+            ret.extend_from_slice(&step.duration.to_le_bytes());
+        }
+        Some(ret)
+    }
+    pub fn from_bytes(bytes: &[u8]) -> Option<LightProgram> {
+        let [m1, m2, version, count, rest @ ..] = bytes else {
+            return None;
+        };
+        if [*m1, *m2] != Self::MAGIC || *version != Self::VERSION {
+            return None;
+        }
+        if rest.len() != *count as usize * Self::STEP_LEN {
+            return None;
+        }
+        let steps = rest
+            .chunks(Self::STEP_LEN)
+            .map(|s| LightStep {
+                effect: LightEffect::from_bytes([s[0], s[1], s[2], s[3]]),
+                duration: u16::from_le_bytes([s[4], s[5]]),
+            })
+            .collect();
+        Some(LightProgram { steps })
+    }
+    // Intensity at `t_ms` after the program started, the last step keeps running when done
+    pub fn intensity_at(&self, t_ms: u32) -> f32 {
+        let t_ms = u64::from(t_ms);
+        let mut start = 0;
+        for (n, step) in self.steps.iter().enumerate() {
+            let end = start + u64::from(step.duration) * 1000;
+            if step.duration == 0 || t_ms < end || n == self.steps.len() - 1 {
+                // start <= t_ms, so the offset fits in u32
+                return step.effect.intensity_at((t_ms - start) as u32);
+            }
+            start = end;
+        }
+        1.0
+    }
+    pub fn preview(&self, duration_ms: u32, sample_ms: u32) -> Vec<f32> {
+        (0..duration_ms)
+            .step_by(sample_ms.max(1) as usize)
+            .map(|t| self.intensity_at(t))
+            .collect()
+    }
+}
+
+// Deterministic value noise in 0.0..1.0, same sequence as the firmware for a given seed
+fn noise(seed: u8, step: u32) -> f32 {
+    let mut x = step.wrapping_mul(0x9e37_79b9) ^ (u32::from(seed) << 16 | 0x5bd1);
+    x ^= x >> 16;
+    x = x.wrapping_mul(0x7feb_352d);
+    x ^= x >> 15;
+    x = x.wrapping_mul(0x846c_a68b);
+    x ^= x >> 16;
+    (x >> 8) as f32 / (1u32 << 24) as f32
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn setting_roundtrip() {
+        let effect = LightEffect::candle(100, 40, 7);
+        let setting = effect.to_setting(2, 1691096258);
+        assert_eq!(setting.settings_type(), SettingsType::LightEffect);
+        assert_eq!(setting.channel, 2);
+        assert_eq!(LightEffect::from_setting(&setting), Some(effect));
+    }
+
+    #[test]
+    fn program_roundtrip() {
+        let mut program = LightProgram::new();
+        program.add_step(LightEffect::sunrise(15, 100), 900);
+        program.add_step(LightEffect::breathe(2, 30), 0);
+        let bytes = program.to_bytes().unwrap();
+        assert_eq!(bytes.len(), 4 + 2 * 6);
+        assert_eq!(LightProgram::from_bytes(&bytes), Some(program));
+        assert_eq!(LightProgram::from_bytes(&bytes[..bytes.len() - 1]), None);
+    }
+
+    #[test]
+    fn long_programs() {
+        let mut program = LightProgram::new();
+        for _ in 0..256 {
+            program.add_step(LightEffect::sunrise(1, 100), u16::MAX);
+        }
+        assert_eq!(program.to_bytes(), None);
+        // Steps add up to more than u32::MAX ms
+        assert_eq!(program.intensity_at(u32::MAX), 1.0);
+    }
+
+    #[test]
+    fn preview_stays_within_depth() {
+        let effect = LightEffect::candle(100, 40, 7);
+        let curve = effect.preview(10_000, 10);
+        assert_eq!(curve.len(), 1000);
+        assert!(curve.iter().all(|i| (0.6..=1.0).contains(i)));
+        assert_eq!(curve, effect.preview(10_000, 10));
+
+        let sunrise = LightEffect::sunrise(1, 100);
+        assert_eq!(sunrise.intensity_at(0), 0.0);
+        assert_eq!(sunrise.intensity_at(60_000), 1.0);
+        assert_eq!(LightEffect::breathe(10, 50).intensity_at(500), 0.5);
+    }
+}
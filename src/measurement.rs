@@ -2,7 +2,11 @@ use chrono::{DateTime, Utc};
 use log::*;
 use serde::{Deserialize, Serialize};
 use std::fmt;
+
+pub mod senml;
+
 #[derive(Serialize, Deserialize, PartialEq, Hash, Eq, Debug, Clone)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct Measurement {
     pub channel: SensorChannel,
     #[serde(rename = "type")]
@@ -25,8 +29,30 @@ impl Measurement {
             None
         }
     }
+    // value1 is the integer part, value2 the fractional part in millionths, as in zephyr's
+    // struct sensor_value. None when the integer part does not fit in an i32.
+    pub fn with_value(
+        channel: SensorChannel,
+        measurement_type: MeasurementType,
+        value: f64,
+    ) -> Option<Self> {
+        if !value.is_finite() {
+            return None;
+        }
+        let micro = (value * 1_000_000.0).round() as i64;
+        Some(Measurement {
+            channel,
+            measurement_type,
+            value1: i32::try_from(micro / 1_000_000).ok()?,
+            value2: (micro % 1_000_000) as i32,
+        })
+    }
+    pub fn value(&self) -> f64 {
+        self.value1 as f64 + self.value2 as f64 / 1_000_000.0
+    }
 }
 #[derive(Serialize, Deserialize, PartialEq, Hash, Eq, Debug, Clone)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct SensorDataZephyr {
     pub channel: u8,
     pub measurement_type: u8,
@@ -150,6 +176,19 @@ pub enum MeasurementType {
     // Fallback for unknown types
     Other(u8),
 }
+#[cfg(feature = "schema")]
+impl schemars::JsonSchema for MeasurementType {
+    fn schema_name() -> std::borrow::Cow<'static, str> {
+        "MeasurementType".into()
+    }
+    fn json_schema(_: &mut schemars::SchemaGenerator) -> schemars::Schema {
+        let known = (0..=u8::MAX)
+            .map(MeasurementType::from)
+            .filter(|t| !matches!(t, MeasurementType::Other(_)))
+            .map(|t| (Into::<i32>::into(t.clone()).into(), t.to_string()));
+        crate::schema::wire_enum("Measurement type", known)
+    }
+}
 impl From<u8> for MeasurementType {
     fn from(v: u8) -> Self {
         match v {
@@ -415,6 +454,98 @@ impl fmt::Display for MeasurementType {
         }
     }
 }
+impl MeasurementType {
+    // AmbientTemperature is ambient_temperature, Other(120) is other_120
+    pub fn snake_name(&self) -> String {
+        if let MeasurementType::Other(n) = self {
+            return format!("other_{n}");
+        }
+        let mut name = String::new();
+        let mut previous: Option<char> = None;
+        for c in self.to_string().chars() {
+            if c.is_ascii_uppercase()
+                && previous.is_some_and(|p| p.is_ascii_lowercase() || p.is_ascii_digit())
+            {
+                name.push('_');
+            }
+            name.push(c.to_ascii_lowercase());
+            previous = Some(c);
+        }
+        name
+    }
+    // Unit of value1.value2 as reported by the zephyr sensor API
+    pub fn unit(&self) -> &'static str {
+        match self {
+            MeasurementType::AccelX
+            | MeasurementType::AccelY
+            | MeasurementType::AccelZ
+            | MeasurementType::AccelXYZ => "m/s²",
+            MeasurementType::GyroX
+            | MeasurementType::GyroY
+            | MeasurementType::GyroZ
+            | MeasurementType::GyroXYZ => "rad/s",
+            MeasurementType::MagnX
+            | MeasurementType::MagnY
+            | MeasurementType::MagnZ
+            | MeasurementType::MagnXYZ => "G",
+            MeasurementType::DieTemp
+            | MeasurementType::AmbientTemperature
+            | MeasurementType::GaugeTemperature => "°C",
+            MeasurementType::Pressure => "kPa",
+            MeasurementType::Humidity
+            | MeasurementType::GaugeStateOfCharge
+            | MeasurementType::GaugeStateOfHealth => "%",
+            MeasurementType::IlluminanceVisible
+            | MeasurementType::IlluminanceInfraRed
+            | MeasurementType::IlluminanceRed
+            | MeasurementType::IlluminanceGreen
+            | MeasurementType::IlluminanceBlue => "lx",
+            MeasurementType::Altitude | MeasurementType::Distance => "m",
+            MeasurementType::PM1_0 | MeasurementType::PM2_5 | MeasurementType::PM10 => "µg/m³",
+            MeasurementType::Co2Level | MeasurementType::O2Level | MeasurementType::Tds => "ppm",
+            MeasurementType::VocLevel => "ppb",
+            MeasurementType::GasSensorResistance | MeasurementType::Resistance => "Ω",
+            MeasurementType::Voltage
+            | MeasurementType::GaugeVoltage
+            | MeasurementType::GaugeDesignVoltage
+            | MeasurementType::GaugeDesiredVoltage => "V",
+            MeasurementType::ShuntVoltage => "mV",
+            MeasurementType::Current
+            | MeasurementType::GaugeAvgCurrent
+            | MeasurementType::GaugeStandbyCurrent
+            | MeasurementType::GaugeMaxLoadCurrent => "A",
+            MeasurementType::GaugeDesiredChargingCurrent => "mA",
+            MeasurementType::Power => "W",
+            MeasurementType::GaugeAvgPower => "mW",
+            MeasurementType::GaugeFullChargeCapacity
+            | MeasurementType::GaugeRemainingChargeCapacity
+            | MeasurementType::GaugeNominalAvailableCapacity
+            | MeasurementType::GaugeFullAvailableCapacity => "mAh",
+            MeasurementType::GaugeTimeToEmpty | MeasurementType::GaugeTimeToFull => "min",
+            MeasurementType::Rotation => "°",
+            MeasurementType::RPM => "rpm",
+            MeasurementType::PhSensor => "pH",
+            MeasurementType::UptimeCounter => "s",
+            MeasurementType::Proximity
+            | MeasurementType::PositionDeltaX
+            | MeasurementType::PositionDeltaY
+            | MeasurementType::PositionDeltaZ
+            | MeasurementType::GaugeCycleCount
+            | MeasurementType::SensorChanF1_415
+            | MeasurementType::SensorChanF2_445
+            | MeasurementType::SensorChanF3_480
+            | MeasurementType::SensorChanF4_515
+            | MeasurementType::SensorChanF5_555
+            | MeasurementType::SensorChanF6_590
+            | MeasurementType::SensorChanF7_630
+            | MeasurementType::SensorChanF8_680
+            | MeasurementType::SensorChanNir
+            | MeasurementType::All
+            | MeasurementType::DoorlockLogs
+            | MeasurementType::Other(_) => "",
+        }
+    }
+}
 impl From<&str> for MeasurementType {
     fn from(v: &str) -> Self {
         match v {
@@ -496,6 +627,7 @@ impl From<&str> for MeasurementType {
     }
 }
 #[derive(Serialize, Deserialize, PartialEq, Hash, Eq, Debug, Clone, Copy)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[repr(i32)]
 #[serde(into = "i32", from = "i32")]
 pub enum SensorChannel {
@@ -524,6 +656,11 @@ impl Into<i32> for SensorChannel {
     }
 }
 #[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+#[cfg_attr(
+    feature = "schema",
+    derive(schemars::JsonSchema),
+    schemars(rename = "ApiMeasurements", transform = crate::schema::KindTag("Measurement"))
+)]
 #[serde(rename_all = "camelCase", tag = "kind", rename = "Measurement")]
 pub struct ApiMeasurements {
     id: String,
@@ -0,0 +1,441 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+use super::{ApiMeasurements, Measurement, MeasurementType, SensorChannel};
+
+#[cfg(feature = "cbor")]
+mod cbor;
+
+// SenML (RFC 8428) packs of measurements.
+//
+// The base name is the device serial, each record is named "<MeasurementType>/<channel>"
+// and carries the value in the SenML unit of the type, e.g.
+//
+//   [{"bn": "1234567:", "bt": 1.7e9, "n": "AmbientTemperature/0", "u": "Cel", "v": 21.5}]
+//
+// Values from the device are converted where the SenML registry has no matching unit,
+// kPa is sent as Pa and mAh as C. The CBOR encoding needs the "cbor" feature.
+
+// One record of a pack, base fields apply to the records that follow
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct SenmlRecord {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bn: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bt: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bu: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bv: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub n: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub u: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub v: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vs: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vb: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub t: Option<f64>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SenmlError {
+    // Not a unit of the SenML registry
+    UnknownUnit(String),
+    // A registered unit that does not fit the measurement type
+    UnitMismatch {
+        name: String,
+        unit: String,
+    },
+    UnknownName(String),
+    MissingValue(String),
+    MissingBaseName,
+    // Value that does not fit a Measurement
+    ValueOutOfRange {
+        name: String,
+        value: f64,
+    },
+    TimeOutOfRange(f64),
+    Json(String),
+    #[cfg(feature = "cbor")]
+    Cbor(String),
+}
+impl fmt::Display for SenmlError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SenmlError::UnknownUnit(unit) => write!(f, "Unknown SenML unit {unit}"),
+            SenmlError::UnitMismatch { name, unit } => {
+                write!(f, "Unit {unit} does not match {name}")
+            }
+            SenmlError::UnknownName(name) => write!(f, "Unknown measurement {name}"),
+            SenmlError::MissingValue(name) => write!(f, "No numeric value for {name}"),
+            SenmlError::MissingBaseName => write!(f, "Pack has no base name"),
+            SenmlError::ValueOutOfRange { name, value } => {
+                write!(f, "Value {value} of {name} is out of range")
+            }
+            SenmlError::TimeOutOfRange(t) => write!(f, "Time {t} is out of range"),
+            SenmlError::Json(e) => write!(f, "Invalid SenML JSON: {e}"),
+            #[cfg(feature = "cbor")]
+            SenmlError::Cbor(e) => write!(f, "Invalid SenML CBOR: {e}"),
+        }
+    }
+}
+impl std::error::Error for SenmlError {}
+
+// Units of RFC 8428 and RFC 8798
+const UNITS: &[&str] = &[
+    "m", "kg", "g", "s", "A", "K", "cd", "mol", "Hz", "rad", "sr", "N", "Pa", "J", "W", "C", "V",
+    "F", "Ohm", "S", "Wb", "T", "H", "Cel", "lm", "lx", "Bq", "Gy", "Sv", "kat", "m2", "m3", "l",
+    "m/s", "m/s2", "m3/s", "l/s", "W/m2", "cd/m2", "bit", "bit/s", "lat", "lon", "pH", "dB", "dBW",
+    "Bspl", "count", "/", "%", "%RH", "%EL", "EL", "1/s", "1/min", "beat/min", "beats", "S/m", "B",
+    "VA", "VAs", "var", "vars", "J/m", "kg/m3", "deg", "ug/m3", "ppm", "dBm", "mm/h", "m/h", "kWh",
+    "Wh", "kVA", "kvar", "MHz", "KiB", "GB", "Mbit/s", "B/s", "MB/s", "/100", "/1000", "km/h",
+];
+
+// Secondary units of RFC 8798 that scale a unit used here
+const SCALED: &[(&str, &str, f64)] = &[
+    ("ms", "s", 1e-3),
+    ("min", "s", 60.0),
+    ("h", "s", 3600.0),
+    ("mV", "V", 1e-3),
+    ("mA", "A", 1e-3),
+    ("kW", "W", 1e3),
+    ("Ah", "C", 3600.0),
+    ("hPa", "Pa", 100.0),
+    ("mm", "m", 1e-3),
+    ("cm", "m", 1e-2),
+    ("km", "m", 1e3),
+];
+
+impl MeasurementType {
+    // SenML unit and the factor from unit() to it, None for types without a unit
+    pub fn senml_unit(&self) -> Option<(&'static str, f64)> {
+        let unit = match (self.unit(), self) {
+            ("", _) => return None,
+            ("m/s²", _) => ("m/s2", 1.0),
+            ("G", _) => ("T", 1e-4),
+            ("°C", _) => ("Cel", 1.0),
+            ("kPa", _) => ("Pa", 1e3),
+            ("%", MeasurementType::Humidity) => ("%RH", 1.0),
+            ("%", MeasurementType::GaugeStateOfCharge) => ("%EL", 1.0),
+            ("µg/m³", _) => ("ug/m3", 1.0),
+            ("ppb", _) => ("ppm", 1e-3),
+            ("Ω", _) => ("Ohm", 1.0),
+            ("mV", _) => ("V", 1e-3),
+            ("mA", _) => ("A", 1e-3),
+            ("mW", _) => ("W", 1e-3),
+            ("mAh", _) => ("C", 3.6),
+            ("min", _) => ("s", 60.0),
+            ("°", _) => ("deg", 1.0),
+            ("rpm", _) => ("1/min", 1.0),
+            (unit, _) => (unit, 1.0),
+        };
+        Some(unit)
+    }
+}
+
+impl Measurement {
+    pub fn senml_name(&self) -> String {
+        let channel: i32 = self.channel.into();
+        match self.measurement_type {
+            MeasurementType::Other(n) => format!("Other-{n}/{channel}"),
+            ref t => format!("{t}/{channel}"),
+        }
+    }
+    pub fn to_senml(&self) -> SenmlRecord {
+        let (u, scale) = match self.measurement_type.senml_unit() {
+            Some((unit, scale)) => (Some(unit.to_string()), scale),
+            None => (None, 1.0),
+        };
+        SenmlRecord {
+            n: Some(self.senml_name()),
+            u,
+            v: Some(self.value() * scale),
+            ..Default::default()
+        }
+    }
+    // name without the base name, unit and value with base unit and base value applied
+    pub fn from_senml(name: &str, unit: Option<&str>, value: f64) -> Result<Self, SenmlError> {
+        let unknown = || SenmlError::UnknownName(name.to_string());
+        let (t, channel) = name.rsplit_once('/').ok_or_else(unknown)?;
+        let measurement_type = match t.strip_prefix("Other-") {
+            Some(n) => MeasurementType::from(n.parse::<u8>().map_err(|_| unknown())?),
+            None => match MeasurementType::from(t) {
+                MeasurementType::Other(_) => return Err(unknown()),
+                t => t,
+            },
+        };
+        let channel = SensorChannel::from(channel.parse::<u8>().map_err(|_| unknown())?);
+        let expected = measurement_type.senml_unit();
+        let scale = match unit {
+            None => expected.map_or(1.0, |(_, scale)| scale),
+            Some(unit) => {
+                let (base, factor) = match SCALED.iter().find(|(u, _, _)| *u == unit) {
+                    Some((_, base, factor)) => (*base, *factor),
+                    None if UNITS.contains(&unit) => (unit, 1.0),
+                    None => return Err(SenmlError::UnknownUnit(unit.to_string())),
+                };
+                match expected {
+                    Some((expected, scale)) if expected == base => scale / factor,
+                    // %RH and %EL are refinements of the registered %
+                    Some((expected, scale)) if base == "%" && expected.starts_with('%') => {
+                        scale / factor
+                    }
+                    _ => {
+                        return Err(SenmlError::UnitMismatch {
+                            name: name.to_string(),
+                            unit: unit.to_string(),
+                        })
+                    }
+                }
+            }
+        };
+        Measurement::with_value(channel, measurement_type, value / scale).ok_or_else(|| {
+            SenmlError::ValueOutOfRange {
+                name: name.to_string(),
+                value,
+            }
+        })
+    }
+}
+
+impl ApiMeasurements {
+    pub fn to_senml(&self) -> Vec<SenmlRecord> {
+        let mut records: Vec<SenmlRecord> = self.items.iter().map(Measurement::to_senml).collect();
+        // An empty set still sends the base fields, so the serial and time come back
+        if records.is_empty() {
+            records.push(SenmlRecord::default());
+        }
+        records[0].bn = Some(format!("{}:", self.id));
+        records[0].bt = Some(self.updated.timestamp_micros() as f64 / 1e6);
+        records
+    }
+    pub fn to_senml_json(&self) -> String {
+        serde_json::to_string(&self.to_senml()).unwrap_or_default()
+    }
+    // The device serial comes from the base name, updated is the latest record time
+    pub fn from_senml(records: &[SenmlRecord]) -> Result<Self, SenmlError> {
+        let now = Utc::now();
+        let (mut bn, mut bt, mut bu, mut bv) = (None, 0.0, None, 0.0);
+        let mut id = None;
+        let mut updated = None;
+        let mut items = vec![];
+        for record in records {
+            if let Some(name) = &record.bn {
+                bn = Some(name.as_str());
+            }
+            bt = record.bt.unwrap_or(bt);
+            bu = record.bu.as_deref().or(bu);
+            bv = record.bv.unwrap_or(bv);
+
+            let base = bn.ok_or(SenmlError::MissingBaseName)?;
+            let name = format!("{base}{}", record.n.as_deref().unwrap_or(""));
+            let (serial, name) = name.split_once(':').ok_or(SenmlError::MissingBaseName)?;
+            id.get_or_insert_with(|| serial.to_string());
+
+            let time = time(bt + record.t.unwrap_or(0.0), now)?;
+            let value = match (record.v, &record.vs, record.vb) {
+                (Some(v), _, _) => bv + v,
+                // String and boolean values are no measurements, they are skipped
+                (None, Some(_), _) | (None, _, Some(_)) => continue,
+                // Only base fields, as sent for an empty set
+                (None, None, None) if record.n.is_none() => {
+                    updated = updated.max(Some(time));
+                    continue;
+                }
+                (None, None, None) => return Err(SenmlError::MissingValue(name.to_string())),
+            };
+            items.push(Measurement::from_senml(
+                name,
+                record.u.as_deref().or(bu),
+                value,
+            )?);
+            updated = updated.max(Some(time));
+        }
+        Ok(ApiMeasurements {
+            id: id.ok_or(SenmlError::MissingBaseName)?,
+            current_item_count: items.len(),
+            updated: updated.unwrap_or(now),
+            items,
+        })
+    }
+    pub fn from_senml_json(json: &str) -> Result<Self, SenmlError> {
+        let records: Vec<SenmlRecord> =
+            serde_json::from_str(json).map_err(|e| SenmlError::Json(e.to_string()))?;
+        Self::from_senml(&records)
+    }
+}
+
+// Times below 2**28 are relative to now, RFC 8428 section 4.5.3
+fn time(t: f64, now: DateTime<Utc>) -> Result<DateTime<Utc>, SenmlError> {
+    // Saturates, out of range either way
+    let micros = (t * 1e6).round() as i64;
+    let time = if !t.is_finite() {
+        None
+    } else if t < (1u64 << 28) as f64 {
+        now.checked_add_signed(chrono::Duration::microseconds(micros))
+    } else {
+        DateTime::from_timestamp_micros(micros)
+    };
+    time.ok_or(SenmlError::TimeOutOfRange(t))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn measurements() -> ApiMeasurements {
+        let mut measurements = ApiMeasurements::new(1234567);
+        measurements.updated = DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+        measurements.add_measurement(
+            Measurement::with_value(0.into(), MeasurementType::AmbientTemperature, 21.5).unwrap(),
+        );
+        measurements.add_measurement(
+            Measurement::with_value(1.into(), MeasurementType::Pressure, 101.325).unwrap(),
+        );
+        measurements.add_measurement(
+            Measurement::with_value(0.into(), MeasurementType::Other(120), -3.25).unwrap(),
+        );
+        measurements
+    }
+
+    #[test]
+    fn json_pack() {
+        let measurements = measurements();
+        let json = measurements.to_senml_json();
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(&json).unwrap(),
+            serde_json::json!([
+                {"bn": "1234567:", "bt": 1700000000.0, "n": "AmbientTemperature/0", "u": "Cel", "v": 21.5},
+                {"n": "Pressure/1", "u": "Pa", "v": 101325.0},
+                {"n": "Other-120/0", "v": -3.25},
+            ])
+        );
+        assert_eq!(
+            ApiMeasurements::from_senml_json(&json).unwrap(),
+            measurements
+        );
+    }
+
+    #[cfg(feature = "cbor")]
+    #[test]
+    fn cbor_pack() {
+        let measurements = measurements();
+        let cbor = measurements.to_senml_cbor();
+        assert!(cbor.len() < measurements.to_senml_json().len());
+        // Array of 3, first record {-2: "1234567:", ...
+        assert_eq!(&cbor[..4], &[0x83, 0xa5, 0x21, 0x68]);
+        assert_eq!(
+            ApiMeasurements::from_senml_cbor(&cbor).unwrap(),
+            measurements
+        );
+    }
+
+    #[test]
+    fn base_fields_and_scaled_units() {
+        let measurements = ApiMeasurements::from_senml_json(
+            r#"[
+                {"bn": "42:", "bt": 1700000000, "bu": "V", "n": "Voltage/0", "v": 3.3},
+                {"n": "ShuntVoltage/0", "u": "mV", "v": 12, "t": 60},
+                {"n": "GaugeTimeToEmpty/0", "u": "h", "v": 2}
+            ]"#,
+        )
+        .unwrap();
+        assert_eq!(measurements.id(), "42");
+        assert_eq!(measurements.updated().timestamp(), 1_700_000_060);
+        let items = measurements.as_slice();
+        assert_eq!((items[0].value1, items[0].value2), (3, 300000));
+        assert_eq!((items[1].value1, items[1].value2), (12, 0));
+        assert_eq!((items[2].value1, items[2].value2), (120, 0));
+    }
+
+    #[test]
+    fn percent_and_skipped_records() {
+        let measurements = ApiMeasurements::from_senml_json(
+            r#"[
+                {"bn": "42:", "bt": 1700000000, "n": "Humidity/0", "u": "%", "v": 55.5},
+                {"n": "GaugeStateOfCharge/0", "u": "%", "v": 80},
+                {"n": "GaugeStateOfHealth/0", "u": "%", "v": 97},
+                {"n": "firmware", "vs": "1.4.2"},
+                {"n": "charging", "vb": true}
+            ]"#,
+        )
+        .unwrap();
+        let items = measurements.as_slice();
+        assert_eq!(items.len(), 3);
+        assert_eq!((items[0].value1, items[0].value2), (55, 500000));
+        assert_eq!((items[1].value1, items[2].value1), (80, 97));
+        let err =
+            ApiMeasurements::from_senml_json(r#"[{"bn": "1:", "n": "Humidity/0"}]"#).unwrap_err();
+        assert_eq!(err, SenmlError::MissingValue("Humidity/0".to_string()));
+    }
+
+    #[test]
+    fn empty_set() {
+        let mut measurements = ApiMeasurements::new(1234567);
+        measurements.updated = DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+        let json = measurements.to_senml_json();
+        assert_eq!(json, r#"[{"bn":"1234567:","bt":1700000000.0}]"#);
+        assert_eq!(
+            ApiMeasurements::from_senml_json(&json).unwrap(),
+            measurements
+        );
+        #[cfg(feature = "cbor")]
+        assert_eq!(
+            ApiMeasurements::from_senml_cbor(&measurements.to_senml_cbor()).unwrap(),
+            measurements
+        );
+    }
+
+    #[test]
+    fn unit_errors() {
+        let err = ApiMeasurements::from_senml_json(
+            r#"[{"bn": "1:", "n": "AmbientTemperature/0", "u": "degF", "v": 70}]"#,
+        )
+        .unwrap_err();
+        assert_eq!(err, SenmlError::UnknownUnit("degF".to_string()));
+        let err = ApiMeasurements::from_senml_json(
+            r#"[{"bn": "1:", "n": "AmbientTemperature/0", "u": "K", "v": 290}]"#,
+        )
+        .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Unit K does not match AmbientTemperature/0"
+        );
+        let err = ApiMeasurements::from_senml_json(r#"[{"bn": "1:", "n": "Foo/0", "v": 1}]"#)
+            .unwrap_err();
+        assert_eq!(err, SenmlError::UnknownName("Foo/0".to_string()));
+        let err = ApiMeasurements::from_senml_json(r#"[{"n": "Humidity/0", "v": 1}]"#).unwrap_err();
+        assert_eq!(err, SenmlError::MissingBaseName);
+    }
+
+    #[test]
+    fn out_of_range() {
+        for t in [-1e300, 1e300, -1e14] {
+            let err = ApiMeasurements::from_senml_json(&format!(
+                r#"[{{"bn": "1:", "bt": {t}, "n": "Humidity/0", "v": 1}}]"#
+            ))
+            .unwrap_err();
+            assert_eq!(err, SenmlError::TimeOutOfRange(t));
+        }
+        let err =
+            ApiMeasurements::from_senml_json(r#"[{"bn": "1:", "n": "Humidity/0", "v": 1e12}]"#)
+                .unwrap_err();
+        assert_eq!(
+            err,
+            SenmlError::ValueOutOfRange {
+                name: "Humidity/0".to_string(),
+                value: 1e12
+            }
+        );
+        let max = ApiMeasurements::from_senml_json(
+            r#"[{"bn": "1:", "n": "Humidity/0", "v": 2147483647.5}]"#,
+        )
+        .unwrap();
+        assert_eq!(max.items[0].value1, i32::MAX);
+    }
+}
@@ -0,0 +1,108 @@
+use ciborium::Value as Cbor;
+
+use super::{ApiMeasurements, SenmlError, SenmlRecord};
+
+// SenML CBOR (RFC 8428 section 6), enabled with the "cbor" feature. Labels replace the
+// field names, the records are the same as in JSON.
+impl ApiMeasurements {
+    pub fn to_senml_cbor(&self) -> Vec<u8> {
+        let pack = Cbor::Array(self.to_senml().iter().map(to_cbor).collect());
+        let mut bytes = vec![];
+        // Writing to a Vec does not fail
+        let _ = ciborium::into_writer(&pack, &mut bytes);
+        bytes
+    }
+    pub fn from_senml_cbor(bytes: &[u8]) -> Result<Self, SenmlError> {
+        let pack: Cbor =
+            ciborium::from_reader(bytes).map_err(|e| SenmlError::Cbor(e.to_string()))?;
+        let records = match pack {
+            Cbor::Array(records) => records
+                .iter()
+                .map(from_cbor)
+                .collect::<Result<Vec<_>, _>>()?,
+            _ => return Err(SenmlError::Cbor("pack is not an array".to_string())),
+        };
+        Self::from_senml(&records)
+    }
+}
+
+// CBOR labels, RFC 8428 section 6
+const BN: i64 = -2;
+const BT: i64 = -3;
+const BU: i64 = -4;
+const BV: i64 = -5;
+const N: i64 = 0;
+const U: i64 = 1;
+const V: i64 = 2;
+const VS: i64 = 3;
+const VB: i64 = 4;
+const T: i64 = 6;
+
+fn to_cbor(record: &SenmlRecord) -> Cbor {
+    let text = |label: i64, value: &Option<String>| {
+        value
+            .as_ref()
+            .map(|v| (Cbor::from(label), Cbor::Text(v.clone())))
+    };
+    let float = |label: i64, value: Option<f64>| value.map(|v| (Cbor::from(label), number(v)));
+    let entries = [
+        text(BN, &record.bn),
+        float(BT, record.bt),
+        text(BU, &record.bu),
+        float(BV, record.bv),
+        text(N, &record.n),
+        text(U, &record.u),
+        float(V, record.v),
+        text(VS, &record.vs),
+        record.vb.map(|v| (Cbor::from(VB), Cbor::Bool(v))),
+        float(T, record.t),
+    ];
+    Cbor::Map(entries.into_iter().flatten().collect())
+}
+
+// Whole numbers as integers, they are shorter
+fn number(v: f64) -> Cbor {
+    if v.fract() == 0.0 && v.abs() < 1e15 {
+        Cbor::from(v as i64)
+    } else {
+        Cbor::Float(v)
+    }
+}
+
+fn from_cbor(record: &Cbor) -> Result<SenmlRecord, SenmlError> {
+    let invalid = |what: &str| SenmlError::Cbor(format!("invalid {what}"));
+    let entries = record.as_map().ok_or_else(|| invalid("record"))?;
+    let mut ret = SenmlRecord::default();
+    for (label, value) in entries {
+        let label = label
+            .as_integer()
+            .and_then(|l| i64::try_from(l).ok())
+            .ok_or_else(|| invalid("label"))?;
+        let text = || {
+            value
+                .as_text()
+                .map(str::to_string)
+                .ok_or_else(|| invalid("text"))
+        };
+        let float = || match value {
+            Cbor::Integer(i) => Ok(i128::from(*i) as f64),
+            Cbor::Float(f) => Ok(*f),
+            _ => Err(invalid("number")),
+        };
+        match label {
+            BN => ret.bn = Some(text()?),
+            BT => ret.bt = Some(float()?),
+            BU => ret.bu = Some(text()?),
+            BV => ret.bv = Some(float()?),
+            N => ret.n = Some(text()?),
+            U => ret.u = Some(text()?),
+            V => ret.v = Some(float()?),
+            VS => ret.vs = Some(text()?),
+            VB => ret.vb = Some(value.as_bool().ok_or_else(|| invalid("boolean"))?),
+            T => ret.t = Some(float()?),
+            // Labels this version does not use, like sum and update time
+            _ => {}
+        }
+    }
+    Ok(ret)
+}
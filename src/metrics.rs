@@ -0,0 +1,467 @@
+use chrono::{DateTime, Utc};
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+use crate::devs::hb::{DevStatus, HeartBeat};
+use crate::devs::DevInfo;
+use crate::measurement::{ApiMeasurements, Measurement, MeasurementType};
+use crate::neighbors::ApiNeighbors;
+
+// OpenMetrics text exposition of the devices, for Prometheus and compatible scrapers.
+//
+//   let text = Metrics::new().device(&dev_info).heartbeat(&heartbeat).render();
+//
+// Every sample is labelled with dev_sn. Measurements are exposed in base units, one family
+// per measurement type, e.g. horti_ambient_temperature_celsius and horti_pressure_pascals.
+
+pub const PREFIX: &str = "horti";
+pub const CONTENT_TYPE: &str = "application/openmetrics-text; version=1.0.0; charset=utf-8";
+
+const STATES: &[DevStatus] = &[
+    DevStatus::Error,
+    DevStatus::RunningOk,
+    DevStatus::Downloading,
+    DevStatus::Flashing,
+    DevStatus::Rebooting,
+    DevStatus::Offline,
+    DevStatus::Unknown(0),
+];
+
+#[derive(Debug, Clone, Default)]
+pub struct Metrics {
+    devices: BTreeMap<u64, DevInfo>,
+    heartbeats: BTreeMap<u64, HeartBeat>,
+    neighbors: BTreeMap<u64, ApiNeighbors>,
+    // Latest per device, measurement type and channel
+    measurements: BTreeMap<(u64, String, i32), (Measurement, DateTime<Utc>)>,
+}
+impl Metrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn device(mut self, dev_info: &DevInfo) -> Self {
+        self.devices.insert(dev_info.dev_sn, dev_info.clone());
+        self
+    }
+    pub fn heartbeat(mut self, heartbeat: &HeartBeat) -> Self {
+        self.heartbeats.insert(heartbeat.id, *heartbeat);
+        self
+    }
+    // The id of ApiNeighbors and ApiMeasurements is the device serial
+    pub fn neighbors(mut self, neighbors: &ApiNeighbors) -> Self {
+        if let Ok(dev_sn) = neighbors.id().parse() {
+            self.neighbors.insert(dev_sn, neighbors.clone());
+        }
+        self
+    }
+    pub fn measurements(mut self, measurements: &ApiMeasurements) -> Self {
+        let Ok(dev_sn) = measurements.id().parse() else {
+            return self;
+        };
+        for m in measurements.as_slice() {
+            let key = (dev_sn, family_name(&m.measurement_type), m.channel.into());
+            match self.measurements.get(&key) {
+                Some((_, updated)) if *updated > measurements.updated() => {}
+                _ => {
+                    self.measurements
+                        .insert(key, (m.clone(), measurements.updated()));
+                }
+            }
+        }
+        self
+    }
+
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        self.device_info(&mut out);
+        self.uptime(&mut out);
+        self.status(&mut out);
+        self.firmware(&mut out);
+        self.battery(&mut out);
+        self.links(&mut out);
+        self.latest_measurements(&mut out);
+        out.push_str("# EOF\n");
+        out
+    }
+
+    fn device_info(&self, out: &mut String) {
+        let samples: Vec<Sample> = self
+            .devices
+            .values()
+            .map(|d| {
+                Sample::new("_info", d.dev_sn, 1.0)
+                    .label("name", d.name.as_deref().unwrap_or(""))
+                    .label("type", d.dev_type.to_string())
+            })
+            .collect();
+        family(
+            out,
+            "device",
+            "info",
+            None,
+            "Device name and type",
+            &samples,
+        );
+    }
+
+    fn uptime(&self, out: &mut String) {
+        let mut uptimes: BTreeMap<u64, f64> = self
+            .devices
+            .values()
+            .filter_map(|d| Some((d.dev_sn, d.uptime? as f64)))
+            .collect();
+        for heartbeat in self.heartbeats.values() {
+            uptimes.insert(heartbeat.id, heartbeat.uptime.into());
+        }
+        let samples: Vec<Sample> = uptimes
+            .into_iter()
+            .map(|(dev_sn, uptime)| Sample::new("", dev_sn, uptime))
+            .collect();
+        family(
+            out,
+            "device_uptime",
+            "gauge",
+            Some("seconds"),
+            "Time since the device booted",
+            &samples,
+        );
+    }
+
+    // The heartbeat is the newest status, devices without one are offline after a while
+    fn status(&self, out: &mut String) {
+        let mut statuses: BTreeMap<u64, DevStatus> = self
+            .devices
+            .values()
+            .map(|d| (d.dev_sn, d.status()))
+            .collect();
+        for heartbeat in self.heartbeats.values() {
+            statuses.insert(heartbeat.id, heartbeat.status);
+        }
+        let name = format!("{PREFIX}_device_status");
+        let mut samples = vec![];
+        for (dev_sn, status) in statuses {
+            for state in STATES {
+                let current = match status {
+                    DevStatus::Unknown(_) => matches!(state, DevStatus::Unknown(_)),
+                    status => status == *state,
+                };
+                samples.push(
+                    Sample::new("", dev_sn, if current { 1.0 } else { 0.0 })
+                        .label(&name, state_name(*state)),
+                );
+            }
+        }
+        family(
+            out,
+            "device_status",
+            "stateset",
+            None,
+            "Status reported by the device",
+            &samples,
+        );
+    }
+
+    fn firmware(&self, out: &mut String) {
+        let mut versions: BTreeMap<u64, String> = self
+            .devices
+            .values()
+            .filter_map(|d| Some((d.dev_sn, d.firmware.version()?.to_string())))
+            .collect();
+        for heartbeat in self.heartbeats.values() {
+            versions
+                .entry(heartbeat.id)
+                .or_insert_with(|| heartbeat.firmware_version().to_string());
+        }
+        let samples: Vec<Sample> = versions
+            .into_iter()
+            .map(|(dev_sn, version)| Sample::new("_info", dev_sn, 1.0).label("version", version))
+            .collect();
+        family(
+            out,
+            "firmware",
+            "info",
+            None,
+            "Firmware running on the device",
+            &samples,
+        );
+    }
+
+    // From the fuel gauge state of charge
+    fn battery(&self, out: &mut String) {
+        let samples: Vec<Sample> = self
+            .measurements
+            .iter()
+            .filter(|(_, (m, _))| m.measurement_type == MeasurementType::GaugeStateOfCharge)
+            .map(|((dev_sn, _, _), (m, _))| Sample::new("", *dev_sn, m.value() / 100.0))
+            .collect();
+        family(
+            out,
+            "battery_charge",
+            "gauge",
+            Some("ratio"),
+            "Battery state of charge, 1 is full",
+            &samples,
+        );
+    }
+
+    fn links(&self, out: &mut String) {
+        let mut rssi = vec![];
+        let mut lqi = vec![];
+        for (dev_sn, neighbors) in &self.neighbors {
+            for n in neighbors.as_slice() {
+                let neighbor = format!("{:#06x}", n.rloc16);
+                rssi.push(
+                    Sample::new("", *dev_sn, n.m_average_rssi.into()).label("neighbor", &neighbor),
+                );
+                lqi.push(
+                    Sample::new("", *dev_sn, n.m_link_quality.into()).label("neighbor", &neighbor),
+                );
+            }
+        }
+        family(
+            out,
+            "neighbor_rssi",
+            "gauge",
+            Some("dbm"),
+            "Average RSSI of the link to a Thread neighbor",
+            &rssi,
+        );
+        family(
+            out,
+            "neighbor_link_quality",
+            "gauge",
+            None,
+            "Link quality indicator of the link to a Thread neighbor, 0 to 3",
+            &lqi,
+        );
+    }
+
+    fn latest_measurements(&self, out: &mut String) {
+        let mut families: BTreeMap<&str, (MeasurementType, Vec<Sample>)> = BTreeMap::new();
+        for ((dev_sn, name, channel), (m, _)) in &self.measurements {
+            let (_, scale) = unit(&m.measurement_type);
+            families
+                .entry(name)
+                .or_insert_with(|| (m.measurement_type.clone(), vec![]))
+                .1
+                .push(Sample::new("", *dev_sn, m.value() * scale).label("channel", channel));
+        }
+        for (name, (measurement_type, samples)) in families {
+            let (unit, _) = unit(&measurement_type);
+            family(
+                out,
+                name.strip_prefix(&format!("{PREFIX}_")).unwrap_or(name),
+                "gauge",
+                unit,
+                &format!("Latest {measurement_type} measurement"),
+                &samples,
+            );
+        }
+    }
+}
+
+struct Sample {
+    suffix: &'static str,
+    labels: Vec<(String, String)>,
+    value: f64,
+}
+impl Sample {
+    fn new(suffix: &'static str, dev_sn: u64, value: f64) -> Self {
+        Sample {
+            suffix,
+            labels: vec![("dev_sn".to_string(), dev_sn.to_string())],
+            value,
+        }
+    }
+    fn label(mut self, name: &str, value: impl ToString) -> Self {
+        self.labels.push((name.to_string(), value.to_string()));
+        self
+    }
+}
+
+// The unit is the last part of the name
+fn family(
+    out: &mut String,
+    name: &str,
+    kind: &str,
+    unit: Option<&str>,
+    help: &str,
+    samples: &[Sample],
+) {
+    if samples.is_empty() {
+        return;
+    }
+    let name = match unit {
+        Some(unit) => format!("{PREFIX}_{name}_{unit}"),
+        None => format!("{PREFIX}_{name}"),
+    };
+    writeln!(out, "# TYPE {name} {kind}").unwrap();
+    if let Some(unit) = unit {
+        writeln!(out, "# UNIT {name} {unit}").unwrap();
+    }
+    writeln!(out, "# HELP {name} {}", escape(help)).unwrap();
+    for sample in samples {
+        let labels: Vec<String> = sample
+            .labels
+            .iter()
+            .map(|(k, v)| format!("{k}=\"{}\"", escape(v)))
+            .collect();
+        writeln!(
+            out,
+            "{name}{}{{{}}} {}",
+            sample.suffix,
+            labels.join(","),
+            value(sample.value)
+        )
+        .unwrap();
+    }
+}
+
+fn value(v: f64) -> String {
+    match v {
+        v if v.is_nan() => "NaN".to_string(),
+        v if v == f64::INFINITY => "+Inf".to_string(),
+        v if v == f64::NEG_INFINITY => "-Inf".to_string(),
+        v => v.to_string(),
+    }
+}
+
+fn escape(s: &str) -> String {
+    s.replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+fn state_name(status: DevStatus) -> &'static str {
+    match status {
+        DevStatus::Unknown(_) => "Unknown",
+        DevStatus::Error => "Error",
+        DevStatus::RunningOk => "RunningOk",
+        DevStatus::Downloading => "Downloading",
+        DevStatus::Flashing => "Flashing",
+        DevStatus::Rebooting => "Rebooting",
+        DevStatus::Offline => "Offline",
+    }
+}
+
+// horti_<measurement type in snake case>, without the unit
+fn family_name(measurement_type: &MeasurementType) -> String {
+    format!("{PREFIX}_{}", measurement_type.snake_name())
+}
+
+// Base unit for the name and the factor from MeasurementType::unit() to it
+fn unit(measurement_type: &MeasurementType) -> (Option<&'static str>, f64) {
+    let Some((senml, scale)) = measurement_type.senml_unit() else {
+        return (None, 1.0);
+    };
+    let unit = match senml {
+        "Cel" => "celsius",
+        "Pa" => "pascals",
+        "%RH" | "%EL" | "%" => return (Some("ratio"), scale / 100.0),
+        "lx" => "lux",
+        "m" => "meters",
+        "m/s2" => "meters_per_second_squared",
+        "rad/s" => "radians_per_second",
+        "T" => "teslas",
+        "ug/m3" => "micrograms_per_cubic_meter",
+        "ppm" => "ppm",
+        "Ohm" => "ohms",
+        "V" => "volts",
+        "A" => "amperes",
+        "W" => "watts",
+        "C" => "coulombs",
+        "s" => "seconds",
+        "deg" => "degrees",
+        "1/min" => "rpm",
+        _ => return (None, scale),
+    };
+    (Some(unit), scale)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::devs::hb::DevType;
+    use crate::firmware::FirmwareVersion;
+    use crate::neighbors::Neighbor;
+
+    fn dev_info() -> DevInfo {
+        let mut dev_info = DevInfo::unknown_device(0x400);
+        dev_info.dev_sn = 1234567;
+        dev_info.name = Some("North \"wall\"\n2".to_string());
+        dev_info.dev_type = DevType::HortiPlantSensor;
+        dev_info.status = DevStatus::RunningOk;
+        dev_info.set_firmware_version(&FirmwareVersion::new(1, 4, 2));
+        dev_info
+    }
+
+    #[test]
+    fn device_families() {
+        let heartbeat = HeartBeat::new()
+            .id(1234567)
+            .status(3)
+            .uptime(std::time::Duration::from_secs(3600));
+        let text = Metrics::new()
+            .device(&dev_info())
+            .heartbeat(&heartbeat)
+            .render();
+        assert!(text.starts_with(
+            "# TYPE horti_device info\n\
+             # HELP horti_device Device name and type\n\
+             horti_device_info{dev_sn=\"1234567\",name=\"North \\\"wall\\\"\\n2\",type=\"HortiPlantSensor\"} 1\n\
+             # TYPE horti_device_uptime_seconds gauge\n\
+             # UNIT horti_device_uptime_seconds seconds\n\
+             # HELP horti_device_uptime_seconds Time since the device booted\n\
+             horti_device_uptime_seconds{dev_sn=\"1234567\"} 3600\n"
+        ), "{text}");
+        assert!(text.contains("# TYPE horti_device_status stateset\n"));
+        assert!(text.contains(
+            "horti_device_status{dev_sn=\"1234567\",horti_device_status=\"RunningOk\"} 0\n\
+             horti_device_status{dev_sn=\"1234567\",horti_device_status=\"Downloading\"} 1\n"
+        ));
+        assert!(text.contains("horti_firmware_info{dev_sn=\"1234567\",version=\"1.4.2\"} 1\n"));
+        assert!(text.ends_with("# EOF\n"));
+    }
+
+    #[test]
+    fn links_and_measurements() {
+        let mut neighbor = Neighbor::new();
+        neighbor.rloc16(0x0c00);
+        neighbor.m_average_rssi = -62;
+        neighbor.m_link_quality = 3;
+        let mut older = ApiMeasurements::new(42);
+        older.add_measurement(
+            Measurement::with_value(0.into(), MeasurementType::AmbientTemperature, 19.0).unwrap(),
+        );
+        let mut latest = ApiMeasurements::new(42);
+        for (t, v) in [
+            (MeasurementType::AmbientTemperature, 21.5),
+            (MeasurementType::Pressure, 101.3),
+            (MeasurementType::Humidity, 55.0),
+            (MeasurementType::GaugeStateOfCharge, 80.0),
+            (MeasurementType::Co2Level, 410.0),
+        ] {
+            latest.add_measurement(Measurement::with_value(0.into(), t, v).unwrap());
+        }
+        let text = Metrics::new()
+            .neighbors(&ApiNeighbors::new(&[neighbor], 42))
+            .measurements(&latest)
+            .measurements(&older)
+            .render();
+        for line in [
+            "# UNIT horti_neighbor_rssi_dbm dbm\n",
+            "horti_neighbor_rssi_dbm{dev_sn=\"42\",neighbor=\"0x0c00\"} -62\n",
+            "horti_neighbor_link_quality{dev_sn=\"42\",neighbor=\"0x0c00\"} 3\n",
+            "horti_battery_charge_ratio{dev_sn=\"42\"} 0.8\n",
+            "# TYPE horti_ambient_temperature_celsius gauge\n",
+            "# HELP horti_ambient_temperature_celsius Latest AmbientTemperature measurement\n",
+            "horti_ambient_temperature_celsius{dev_sn=\"42\",channel=\"0\"} 21.5\n",
+            "horti_pressure_pascals{dev_sn=\"42\",channel=\"0\"} 101300\n",
+            "horti_humidity_ratio{dev_sn=\"42\",channel=\"0\"} 0.55\n",
+            "horti_co2_level_ppm{dev_sn=\"42\",channel=\"0\"} 410\n",
+        ] {
+            assert!(text.contains(line), "{line} missing in\n{text}");
+        }
+        assert!(!text.contains("horti_device_status"));
+    }
+}
@@ -6,6 +6,7 @@ use std::fmt::Display;
 use crate::devs::{self};
 
 #[derive(Serialize, Deserialize, PartialEq, Hash, Eq, Debug, Clone)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct Neighbor {
     pub rloc16: u16,
     #[serde(rename = "rssi")]
@@ -171,6 +172,7 @@ impl dbus::arg::Arg for Neighbor {
 }
 
 #[derive(Serialize, Deserialize, PartialEq, Hash, Eq, Debug, Clone)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[repr(C)]
 pub struct NeighborDataZephyr {
     pub mrloc16: u16,
@@ -213,6 +215,7 @@ impl NeighborDataZephyr {
     }
 }
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Hash, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct NetGraphEdge {
     pub source: usize,
     pub dest: usize,
@@ -224,6 +227,7 @@ pub struct NetGraphEdge {
     pub fnd: bool,
 }
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Hash, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct NetGraph {
     pub edges: Vec<NetGraphEdge>,
     pub nodes: Vec<devs::DevInfo>,
@@ -234,6 +238,11 @@ impl Into<(Vec<NetGraphEdge>, Vec<devs::DevInfo>)> for NetGraph {
     }
 }
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(
+    feature = "schema",
+    derive(schemars::JsonSchema),
+    schemars(rename = "ApiNeighbors", transform = crate::schema::KindTag("Neighbor"))
+)]
 #[serde(rename = "Neighbor", rename_all = "camelCase", tag = "kind")]
 pub struct ApiNeighbors {
     id: String,
@@ -248,6 +257,9 @@ impl ApiNeighbors {
             items: nd.to_vec(),
         }
     }
+    pub fn id(&self) -> &str {
+        &self.id
+    }
     pub fn len(&self) -> usize {
         self.items.len()
     }
@@ -0,0 +1,371 @@
+use serde_json::{json, Map, Value};
+use std::path::Path;
+
+use crate::api::API_VERSION;
+
+// OpenAPI 3.1 description of the HTTP API, enabled with the "openapi" feature.
+//
+// Every response is a JsonMessage: "data" holds the payload of the endpoint, failures carry
+// "error" instead. Updates take an ApiPost and answer with an ApiPostResponse. Listings are
+// paged: "data" is an ApiDataList from ApiDataList::paginate (ItemTypes::Page), its "items"
+// the payload of the page. The cursor is the startIndex of the page to fetch.
+pub fn document() -> Value {
+    let mut schemas = match crate::schema::bundle().get_mut("$defs").map(Value::take) {
+        Some(Value::Object(defs)) => defs,
+        _ => Map::new(),
+    };
+    for schema in schemas.values_mut() {
+        rewrite_refs(schema);
+    }
+    json!({
+        "openapi": "3.1.0",
+        "info": {
+            "title": "horti-types API",
+            "version": API_VERSION,
+            "description": "Devices, settings and measurements of a Thread network",
+        },
+        "jsonSchemaDialect": "https://json-schema.org/draft/2020-12/schema",
+        "paths": paths(),
+        "components": {
+            "schemas": schemas,
+            "parameters": {
+                "devSn": {
+                    "name": "devSn",
+                    "in": "path",
+                    "required": true,
+                    "description": "Device serial number",
+                    "schema": {"type": "string", "pattern": "^[0-9]+$"},
+                },
+                "cursor": {
+                    "name": "cursor",
+                    "in": "query",
+                    "description": "startIndex of the page, from nextCursor or previousCursor",
+                    "schema": {"type": "string"},
+                },
+                "itemsPerPage": {
+                    "name": "itemsPerPage",
+                    "in": "query",
+                    "schema": {"type": "integer", "minimum": 1},
+                },
+            },
+            "responses": {
+                "Error": {
+                    "description": "Request failed, see error.code and error.errors",
+                    "content": {"application/json": {"schema": error_envelope()}},
+                },
+            },
+        },
+    })
+}
+
+pub fn write_document(path: impl AsRef<Path>) -> std::io::Result<()> {
+    let json = serde_json::to_string_pretty(&document())?;
+    std::fs::write(path, json + "\n")
+}
+
+fn paths() -> Value {
+    let device = [parameter("devSn")];
+    json!({
+        "/devices": {
+            "get": list("listDevices", "Devices in the network", items_of("DevInfo"), &[]),
+        },
+        "/devices/{devSn}": {
+            "get": get("getDevice", "Device info", schema_ref("DevInfo"), &device),
+            "put": update("updateDevice", "Change name or description", schema_ref("NameChange"), &device),
+        },
+        "/devices/{devSn}/heartbeat": {
+            "get": get("getHeartBeat", "Last heartbeat of the device", schema_ref("HeartBeat"), &device),
+        },
+        "/devices/{devSn}/settings": {
+            "get": get("getSettings", "Current settings", schema_ref("ApiDevSettings"), &device),
+            "put": update("updateSettings", "Change settings", schema_ref("ApiDevSettings"), &device),
+        },
+        "/devices/{devSn}/settingTypes": {
+            "get": get("getSettingTypes", "Settings the device supports", schema_ref("ApiSettingTypes"), &device),
+        },
+        "/devices/{devSn}/connected": {
+            "get": get("getConnectedDevices", "Peripherals of the device", schema_ref("ApiDevicesConnected"), &device),
+        },
+        "/devices/{devSn}/measurements": {
+            "get": list("listMeasurements", "Measurements of the device", schema_ref("ApiMeasurements"), &device),
+        },
+        "/devices/{devSn}/neighbors": {
+            "get": get("getNeighbors", "Thread neighbors of the device", schema_ref("ApiNeighbors"), &device),
+        },
+        "/heartbeats": {
+            "get": list("listHeartBeats", "Last heartbeat of every device", items_of("HeartBeat"), &[]),
+        },
+        "/network": {
+            "get": get("listNetworks", "Thread networks in range", items_of("OtNetwork"), &[]),
+        },
+        "/network/config": {
+            "get": get("getNetworkConfig", "Thread network configuration", items_of("OtNetConfig"), &[]),
+            "put": update("updateNetworkConfig", "Change the Thread network configuration", items_of("OtNetConfig"), &[]),
+        },
+    })
+}
+
+fn get(id: &str, summary: &str, data: Value, parameters: &[Value]) -> Value {
+    json!({
+        "operationId": id,
+        "summary": summary,
+        "parameters": parameters,
+        "responses": {
+            "200": ok_response(data),
+            "404": error_response(),
+            "default": error_response(),
+        },
+    })
+}
+
+fn list(id: &str, summary: &str, items: Value, parameters: &[Value]) -> Value {
+    let mut parameters = parameters.to_vec();
+    parameters.extend([parameter("cursor"), parameter("itemsPerPage")]);
+    let page = json!({
+        "allOf": [
+            schema_ref("ApiDataList"),
+            {
+                // JsonMessage tells a page from its payload by these
+                "required": ["startIndex", "itemsPerPage", "totalItems", "pageIndex", "totalPages"],
+                "properties": {"items": items},
+            },
+        ],
+    });
+    get(id, summary, page, &parameters)
+}
+
+fn update(id: &str, summary: &str, data: Value, parameters: &[Value]) -> Value {
+    let request = json!({
+        "allOf": [
+            schema_ref("ApiPost"),
+            {"properties": {"data": data}},
+        ],
+    });
+    json!({
+        "operationId": id,
+        "summary": summary,
+        "parameters": parameters,
+        "requestBody": {
+            "required": true,
+            "content": {"application/json": {"schema": request}},
+        },
+        "responses": {
+            "200": {
+                "description": "Update applied",
+                "content": {"application/json": {"schema": schema_ref("ApiPostResponse")}},
+            },
+            "400": error_response(),
+            "404": error_response(),
+            "default": error_response(),
+        },
+    })
+}
+
+fn ok_response(data: Value) -> Value {
+    let envelope = json!({
+        "allOf": [
+            schema_ref("JsonMessage"),
+            {"required": ["data"], "properties": {"data": data}},
+        ],
+    });
+    json!({
+        "description": "Success",
+        "content": {"application/json": {"schema": envelope}},
+    })
+}
+
+fn error_envelope() -> Value {
+    json!({
+        "allOf": [
+            schema_ref("JsonMessage"),
+            {"required": ["error"], "properties": {"error": schema_ref("ApiError")}},
+        ],
+    })
+}
+
+fn error_response() -> Value {
+    json!({"$ref": "#/components/responses/Error"})
+}
+
+fn parameter(name: &str) -> Value {
+    json!({"$ref": format!("#/components/parameters/{name}")})
+}
+
+fn schema_ref(name: &str) -> Value {
+    json!({"$ref": format!("#/components/schemas/{name}")})
+}
+
+fn items_of(name: &str) -> Value {
+    json!({"type": "array", "items": schema_ref(name)})
+}
+
+fn rewrite_refs(value: &mut Value) {
+    match value {
+        Value::Object(map) => {
+            if let Some(Value::String(reference)) = map.get_mut("$ref") {
+                if let Some(name) = reference.strip_prefix("#/$defs/") {
+                    *reference = format!("#/components/schemas/{name}");
+                }
+            }
+            map.values_mut().for_each(rewrite_refs);
+        }
+        Value::Array(items) => items.iter_mut().for_each(rewrite_refs),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::api::error::ApiError;
+    use crate::api::{ApiDataList, ItemTypes, JsonMessage};
+
+    fn refs<'a>(value: &'a Value, ret: &mut Vec<&'a str>) {
+        match value {
+            Value::Object(map) => {
+                if let Some(Value::String(reference)) = map.get("$ref") {
+                    ret.push(reference);
+                }
+                map.values().for_each(|v| refs(v, ret));
+            }
+            Value::Array(items) => items.iter().for_each(|v| refs(v, ret)),
+            _ => {}
+        }
+    }
+
+    #[test]
+    fn every_ref_resolves() {
+        let document = document();
+        assert_eq!(document["openapi"], "3.1.0");
+        assert_eq!(document["info"]["version"], API_VERSION);
+        let mut found = vec![];
+        refs(&document, &mut found);
+        assert!(!found.is_empty());
+        for reference in found {
+            let pointer = reference.strip_prefix('#').unwrap();
+            assert!(document.pointer(pointer).is_some(), "{reference}");
+        }
+    }
+
+    #[test]
+    fn standard_endpoints() {
+        let document = document();
+        let paths = &document["paths"];
+        for path in [
+            "/devices",
+            "/devices/{devSn}",
+            "/devices/{devSn}/settings",
+            "/devices/{devSn}/settingTypes",
+            "/devices/{devSn}/measurements",
+            "/devices/{devSn}/neighbors",
+            "/devices/{devSn}/heartbeat",
+            "/network/config",
+        ] {
+            assert!(paths.get(path).is_some(), "{path}");
+        }
+        let settings = &paths["/devices/{devSn}/settings"];
+        assert_eq!(
+            settings["get"]["responses"]["200"]["content"]["application/json"]["schema"]["allOf"]
+                [1]["properties"]["data"]["$ref"],
+            "#/components/schemas/ApiDevSettings"
+        );
+        assert_eq!(
+            settings["put"]["responses"]["400"]["$ref"],
+            "#/components/responses/Error"
+        );
+        assert_eq!(
+            paths["/devices/{devSn}/measurements"]["get"]["parameters"][1]["$ref"],
+            "#/components/parameters/cursor"
+        );
+    }
+
+    // What the crate sends for a data schema, $ref or array of $ref
+    fn example(schema: &Value) -> ItemTypes {
+        let name = |schema: &Value| {
+            let reference = schema["$ref"].as_str().unwrap();
+            reference.rsplit('/').next().unwrap().to_string()
+        };
+        if schema["type"] == "array" {
+            return match name(&schema["items"]).as_str() {
+                "DevInfo" => ItemTypes::DeviceInfoList(vec![
+                    crate::devs::DevInfo::unknown_device(1),
+                    crate::devs::DevInfo::unknown_device(2),
+                ]),
+                "HeartBeat" => ItemTypes::HeartBeatList(vec![
+                    crate::devs::hb::HeartBeat::new().id(1).devtype(1),
+                    crate::devs::hb::HeartBeat::new().id(2).devtype(1),
+                ]),
+                "OtNetwork" => ItemTypes::OtNet(vec![crate::otnet::OtNetwork::default()]),
+                "OtNetConfig" => ItemTypes::OtNetConfig(vec![crate::otnet::OtNetConfig::new()]),
+                other => panic!("no example for a list of {other}"),
+            };
+        }
+        match name(schema).as_str() {
+            "DevInfo" => ItemTypes::DeviceInfo(crate::devs::DevInfo::unknown_device(1)),
+            "HeartBeat" => ItemTypes::HeartBeat(crate::devs::hb::HeartBeat::new().id(1).devtype(1)),
+            "ApiDevSettings" => ItemTypes::Settings(crate::settings::ApiDevSettings::new(1)),
+            "ApiSettingTypes" => ItemTypes::SettingTypes(crate::settings::ApiSettingTypes::new(1)),
+            "ApiDevicesConnected" => {
+                ItemTypes::ConnectedDevices(crate::devices_connected::ApiDevicesConnected::new(1))
+            }
+            "ApiNeighbors" => ItemTypes::Neighbor(crate::neighbors::ApiNeighbors::new(&[], 1)),
+            "ApiMeasurements" => {
+                let mut measurements = crate::measurement::ApiMeasurements::new(1);
+                for v in 0..3 {
+                    measurements.add_measurement(crate::measurement::Measurement {
+                        channel: 0.into(),
+                        measurement_type: 13.into(),
+                        value1: v,
+                        value2: 0,
+                    });
+                }
+                ItemTypes::Measurement(measurements)
+            }
+            other => panic!("no example for {other}"),
+        }
+    }
+
+    #[test]
+    fn responses_parse_as_json_message() {
+        let document = document();
+        let mut checked = 0;
+        for (path, operations) in document["paths"].as_object().unwrap() {
+            for operation in operations.as_object().unwrap().values() {
+                let schema =
+                    &operation["responses"]["200"]["content"]["application/json"]["schema"];
+                if schema["allOf"][0]["$ref"] != "#/components/schemas/JsonMessage" {
+                    continue;
+                }
+                let data = &schema["allOf"][1]["properties"]["data"];
+                let required = &data["allOf"][1]["required"];
+                let messages = match data["allOf"][0]["$ref"].as_str() {
+                    Some("#/components/schemas/ApiDataList") => {
+                        let items = example(&data["allOf"][1]["properties"]["items"]);
+                        ApiDataList::paginate("1".to_string(), items, 2)
+                            .into_iter()
+                            .map(|page| JsonMessage::new(ItemTypes::Page(Box::new(page))))
+                            .collect()
+                    }
+                    _ => vec![JsonMessage::new(example(data))],
+                };
+                for message in messages {
+                    let value = serde_json::to_value(&message).unwrap();
+                    for field in required.as_array().into_iter().flatten() {
+                        let field = field.as_str().unwrap();
+                        assert!(value["data"].get(field).is_some(), "{path}: {field}");
+                    }
+                    let parsed: JsonMessage =
+                        serde_json::from_value(value).unwrap_or_else(|e| panic!("{path}: {e}"));
+                    assert_eq!(parsed.data(), message.data(), "{path}");
+                }
+                checked += 1;
+            }
+        }
+        assert!(checked >= 11, "{checked}");
+
+        let value =
+            serde_json::to_value(JsonMessage::new_error(ApiError::not_found("gone"))).unwrap();
+        let parsed: JsonMessage = serde_json::from_value(value).unwrap();
+        assert!(parsed.is_error());
+    }
+}
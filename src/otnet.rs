@@ -59,6 +59,7 @@ impl<'a> dbus::arg::Get<'a> for WpanStatus {
 }
 
 #[derive(Default, Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[serde(rename_all = "camelCase")]
 pub struct OtNetwork {
     id: i32,
@@ -84,6 +85,19 @@ impl Serialize for Tlvarray {
         }
     }
 }
+#[cfg(feature = "schema")]
+impl schemars::JsonSchema for Tlvarray {
+    fn schema_name() -> std::borrow::Cow<'static, str> {
+        "Tlvarray".into()
+    }
+    fn json_schema(_: &mut schemars::SchemaGenerator) -> schemars::Schema {
+        schemars::json_schema!({
+            "type": ["string", "null"],
+            "contentEncoding": "base64",
+            "description": "Thread operational dataset TLVs"
+        })
+    }
+}
 impl<'de> Deserialize<'de> for Tlvarray {
     fn deserialize<D>(deserializer: D) -> Result<Tlvarray, D::Error>
     where
@@ -97,6 +111,11 @@ impl<'de> Deserialize<'de> for Tlvarray {
     }
 }
 #[derive(PartialEq, Hash, Eq, Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(
+    feature = "schema",
+    derive(schemars::JsonSchema),
+    schemars(transform = crate::schema::KindTag("OtNetConfig"))
+)]
 #[serde(tag = "kind")]
 pub struct OtNetConfig {
     #[serde(rename = "updated")]
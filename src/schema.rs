@@ -0,0 +1,151 @@
+use schemars::generate::SchemaSettings;
+use schemars::transform::Transform;
+use schemars::{json_schema, Schema, SchemaGenerator};
+use serde_json::{json, Value};
+use std::path::Path;
+
+use crate::api::API_VERSION;
+
+// JSON Schema (draft 2020-12) for the API types, enabled with the "schema" feature.
+//
+// The bundle keeps every type in $defs. Start from JsonMessage for messages to the
+// frontend, ApiPost and ApiPostList for requests from it.
+pub fn bundle() -> Value {
+    let mut generator = SchemaSettings::draft2020_12().into_generator();
+    add_definitions(&mut generator);
+    let definitions = generator.take_definitions(true);
+    json!({
+        "$schema": "https://json-schema.org/draft/2020-12/schema",
+        "title": "horti-types API",
+        "apiVersion": API_VERSION,
+        "$defs": definitions,
+    })
+}
+
+pub fn write_bundle(path: impl AsRef<Path>) -> std::io::Result<()> {
+    let json = serde_json::to_string_pretty(&bundle())?;
+    std::fs::write(path, json + "\n")
+}
+
+fn add_definitions(generator: &mut SchemaGenerator) {
+    use crate::*;
+    generator.subschema_for::<api::JsonMessage>();
+    generator.subschema_for::<api::post::ApiPost>();
+    generator.subschema_for::<api::post::ApiPostList>();
+    generator.subschema_for::<api::post::ApiPostResponse>();
+    generator.subschema_for::<api::ApiDataList>();
+    generator.subschema_for::<command::Command>();
+    generator.subschema_for::<command::CommandResult>();
+    generator.subschema_for::<devices_connected::ApiDevicesConnected>();
+    generator.subschema_for::<devs::Device>();
+    generator.subschema_for::<devs::DevInfo>();
+    generator.subschema_for::<devs::SensorReading>();
+    generator.subschema_for::<devs::hb::HeartBeat>();
+    generator.subschema_for::<firmware::FirmwareVersion>();
+    generator.subschema_for::<firmware::mcuboot::McubootImage>();
+    generator.subschema_for::<firmware::ota::OtaSession>();
+    generator.subschema_for::<joiner::JoinerData>();
+    generator.subschema_for::<light_effect::LightProgram>();
+    generator.subschema_for::<measurement::ApiMeasurements>();
+    generator.subschema_for::<neighbors::ApiNeighbors>();
+    generator.subschema_for::<neighbors::NetGraph>();
+    generator.subschema_for::<otnet::OtNetwork>();
+    generator.subschema_for::<otnet::OtNetConfig>();
+    generator.subschema_for::<settings::ApiDevSettings>();
+    generator.subschema_for::<settings::ApiSettingTypes>();
+    generator.subschema_for::<settings::cache::Conflict>();
+    generator.subschema_for::<settings::validate::Violation>();
+}
+
+// Adds the "kind" field of structs serialized with #[serde(tag = "kind")]
+pub(crate) struct KindTag(pub &'static str);
+impl Transform for KindTag {
+    fn transform(&mut self, schema: &mut Schema) {
+        if let Some(Value::Object(properties)) = schema.get_mut("properties") {
+            properties.insert(
+                "kind".to_string(),
+                json!({"type": "string", "const": self.0}),
+            );
+        }
+        match schema.get_mut("required") {
+            Some(Value::Array(required)) => required.insert(0, "kind".into()),
+            _ => {
+                schema.insert("required".to_string(), json!(["kind"]));
+            }
+        }
+    }
+}
+
+// Integer enum with a fallback for values this version does not know, the known values
+// are listed with their variant name as title
+pub(crate) fn wire_enum(description: &str, values: impl Iterator<Item = (i64, String)>) -> Schema {
+    let mut any_of: Vec<Value> = values
+        .map(|(value, name)| json!({"const": value, "title": name}))
+        .collect();
+    any_of.push(json!({"type": "integer"}));
+    json_schema!({
+        "type": "integer",
+        "description": description,
+        "anyOf": any_of,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn bundle_covers_custom_serializers() {
+        let bundle = bundle();
+        let defs = &bundle["$defs"];
+        for name in [
+            "JsonMessage",
+            "DevInfo",
+            "ApiMeasurements",
+            "ApiDevSettings",
+            "HeartBeat",
+            "OtNetConfig",
+        ] {
+            assert!(defs.get(name).is_some(), "{name} missing");
+        }
+        assert_eq!(defs["HeartBeat"]["properties"]["id"]["type"], "string");
+        assert_eq!(
+            defs["HeartBeat"]["properties"]["kind"]["const"],
+            "HeartBeat"
+        );
+        assert_eq!(
+            defs["ApiMeasurements"]["properties"]["kind"]["const"],
+            "Measurement"
+        );
+        assert_eq!(
+            defs["Measurement"]["properties"]["type"]["$ref"],
+            "#/$defs/MeasurementType"
+        );
+        assert_eq!(defs["DevType"]["type"], "integer");
+        assert_eq!(
+            defs["DevType"]["anyOf"][1],
+            json!({"const": 1, "title": "HortiLed"})
+        );
+        assert_eq!(defs["DevStatus"]["anyOf"][0]["title"], "Error");
+        assert_eq!(
+            defs["MeasurementType"]["anyOf"][13]["title"],
+            "AmbientTemperature"
+        );
+        assert_eq!(defs["Tlvarray"]["contentEncoding"], "base64");
+        assert_eq!(defs["FirmwareVersion"]["type"], "string");
+        assert_eq!(defs["HeartBeat"]["properties"]["firmware"]["type"], "integer");
+        assert!(defs["DevInfo"]["properties"].get("fwver").is_some());
+        assert!(defs["DevInfo"]["properties"].get("fwverName").is_some());
+        assert!(defs.get("ApiPost").is_some());
+    }
+
+    #[test]
+    fn write_to_file() {
+        let path = std::env::temp_dir().join("horti-types-schema.json");
+        write_bundle(&path).unwrap();
+        let written: Value =
+            serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(written, bundle());
+        std::fs::remove_file(path).unwrap();
+    }
+}
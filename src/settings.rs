@@ -1,7 +1,12 @@
-use bincode::Encode;
+use bincode::{Decode, Encode};
 use chrono::{DateTime, Utc};
 use derivative::Derivative;
 use serde::{Deserialize, Serialize};
+pub mod cache;
+pub mod catalogue;
+pub mod typed;
+pub mod validate;
+pub mod zephyr;
 // enum settings_list {
 //     DEV_TYPE = 0,
 //     FW_BRANCH = 1,
@@ -11,6 +16,7 @@ use serde::{Deserialize, Serialize};
 //     TIME_OFF = 12,
 //     PWM_VAL = 13,
 //     LED_MODE = 14,
+//     LIGHT_EFFECT = 15,
 //     LOG_INTERVAL = 20,
 //     DEFAULT_POS = 30,
 //     DEFAULT_SPEED = 40,
@@ -21,6 +27,7 @@ use serde::{Deserialize, Serialize};
 //     DOORLOCK_CODE_VALID = 54,
 // };
 #[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize, Hash, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[repr(i32)]
 pub enum SettingsType {
     DevType = 0,
@@ -31,6 +38,7 @@ pub enum SettingsType {
     TimeOff = 12,
     PwmVal = 13,
     LedMode = 14,
+    LightEffect = 15,
     LogInterval = 20,
     DefaultPos = 30,
     DefaultSpeed = 40,
@@ -41,6 +49,7 @@ pub enum SettingsType {
     Unknown(i32),
 }
 #[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Default, Derivative)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[derivative(PartialOrd, Ord, Eq)]
 pub struct DevSetting {
     #[serde(rename = "updated")]
@@ -61,6 +70,15 @@ impl DevSetting {
             updated_at: self.updated_at,
         }
     }
+    // None when the type or channel do not fit the i16 fields of the zephyr record
+    pub fn try_to_zephyr(&self) -> Option<DevSettingsZephyr> {
+        Some(DevSettingsZephyr {
+            settings_type: i16::try_from(self.settings_type).ok()?,
+            channel: i16::try_from(self.channel).ok()?,
+            value: self.value,
+            updated_at: self.updated_at,
+        })
+    }
     pub fn id(&self) -> i32 {
         self.settings_type
     }
@@ -72,7 +90,12 @@ impl DevSetting {
         self.value
     }
     pub fn settings_type(&self) -> SettingsType {
-        match self.settings_type {
+        self.settings_type.into()
+    }
+}
+impl From<i32> for SettingsType {
+    fn from(value: i32) -> Self {
+        match value {
             0 => SettingsType::DevType,
             1 => SettingsType::FwBranch,
             9 => SettingsType::NetworkId,
@@ -81,6 +104,7 @@ impl DevSetting {
             12 => SettingsType::TimeOff,
             13 => SettingsType::PwmVal,
             14 => SettingsType::LedMode,
+            15 => SettingsType::LightEffect,
             20 => SettingsType::LogInterval,
             30 => SettingsType::DefaultPos,
             40 => SettingsType::DefaultSpeed,
@@ -92,7 +116,35 @@ impl DevSetting {
         }
     }
 }
+impl From<SettingsType> for i32 {
+    fn from(value: SettingsType) -> Self {
+        match value {
+            SettingsType::DevType => 0,
+            SettingsType::FwBranch => 1,
+            SettingsType::NetworkId => 9,
+            SettingsType::DimTime => 10,
+            SettingsType::TimeOn => 11,
+            SettingsType::TimeOff => 12,
+            SettingsType::PwmVal => 13,
+            SettingsType::LedMode => 14,
+            SettingsType::LightEffect => 15,
+            SettingsType::LogInterval => 20,
+            SettingsType::DefaultPos => 30,
+            SettingsType::DefaultSpeed => 40,
+            SettingsType::DoorlockMode => 50,
+            SettingsType::DoorlockOpenTime => 52,
+            SettingsType::DoorlockCode => 53,
+            SettingsType::DoorlockCodeValid => 54,
+            SettingsType::Unknown(n) => n,
+        }
+    }
+}
 #[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+#[cfg_attr(
+    feature = "schema",
+    derive(schemars::JsonSchema),
+    schemars(rename = "ApiDevSettings", transform = crate::schema::KindTag("Settings"))
+)]
 #[serde(rename_all = "camelCase", tag = "kind", rename = "Settings")]
 pub struct ApiDevSettings {
     id: String,
@@ -128,6 +180,9 @@ impl ApiDevSettings {
     pub fn as_slice(&self) -> &[DevSetting] {
         &self.items
     }
+    pub fn as_mut_slice(&mut self) -> &mut [DevSetting] {
+        &mut self.items
+    }
     pub fn into_vec(self) -> Vec<DevSetting> {
         self.items
     }
@@ -137,6 +192,11 @@ impl ApiDevSettings {
 }
 
 #[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+#[cfg_attr(
+    feature = "schema",
+    derive(schemars::JsonSchema),
+    schemars(rename = "ApiSettingTypes", transform = crate::schema::KindTag("SettingTypes"))
+)]
 #[serde(rename_all = "camelCase", tag = "kind", rename = "SettingTypes")]
 pub struct ApiSettingTypes {
     id: String,
@@ -177,6 +237,11 @@ impl ApiSettingTypes {
     }
 }
 #[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+#[cfg_attr(
+    feature = "schema",
+    derive(schemars::JsonSchema),
+    schemars(transform = crate::schema::KindTag("SettingTypes"))
+)]
 #[serde(rename_all = "camelCase", tag = "kind")]
 pub struct SettingTypes {
     setting_type_id: i32,
@@ -249,7 +314,8 @@ impl SettingTypes {
         self.setting_type_icon = icon;
     }
 }
-#[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Default, Encode)]
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Default, Encode, Decode)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[repr(C)]
 pub struct DevSettingsZephyr {
     pub settings_type: i16,
@@ -0,0 +1,154 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use super::{ApiDevSettings, DevSetting};
+
+// A value on the device that was changed locally after we last set it
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct Conflict {
+    pub desired: DevSetting,
+    pub reported: DevSetting,
+}
+
+// Settings of one device keyed by (type, channel), the newest updated_at wins.
+// On equal timestamps the desired value wins, so it is sent to the device again.
+#[derive(PartialEq, Debug, Clone, Default)]
+pub struct SettingsCache {
+    desired: BTreeMap<(i32, i32), DevSetting>,
+    reported: BTreeMap<(i32, i32), DevSetting>,
+    conflicts: Vec<Conflict>,
+}
+impl SettingsCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    fn key(setting: &DevSetting) -> (i32, i32) {
+        (setting.settings_type, setting.channel)
+    }
+    // Returns false when a newer desired value is already stored
+    pub fn set_desired(&mut self, setting: DevSetting) -> bool {
+        match self.desired.get(&Self::key(&setting)) {
+            Some(current) if current.updated_at > setting.updated_at => false,
+            _ => {
+                self.desired.insert(Self::key(&setting), setting);
+                true
+            }
+        }
+    }
+    pub fn set_desired_all(&mut self, settings: &ApiDevSettings) {
+        for setting in settings.as_slice() {
+            self.set_desired(setting.clone());
+        }
+    }
+    pub fn update_reported(&mut self, setting: DevSetting) {
+        let key = Self::key(&setting);
+        if let Some(current) = self.reported.get(&key) {
+            if current.updated_at > setting.updated_at {
+                return;
+            }
+        }
+        if let Some(desired) = self.desired.get_mut(&key) {
+            if setting.updated_at > desired.updated_at {
+                if setting.value != desired.value {
+                    self.conflicts.push(Conflict {
+                        desired: desired.clone(),
+                        reported: setting.clone(),
+                    });
+                }
+                *desired = setting.clone();
+            }
+        }
+        self.reported.insert(key, setting);
+    }
+    pub fn update_reported_all(&mut self, settings: &ApiDevSettings) {
+        for setting in settings.as_slice() {
+            self.update_reported(setting.clone());
+        }
+    }
+    pub fn desired(&self, settings_type: i32, channel: i32) -> Option<&DevSetting> {
+        self.desired.get(&(settings_type, channel))
+    }
+    pub fn reported(&self, settings_type: i32, channel: i32) -> Option<&DevSetting> {
+        self.reported.get(&(settings_type, channel))
+    }
+    // Desired settings that the device does not have yet
+    pub fn diff(&self) -> Vec<DevSetting> {
+        self.desired
+            .iter()
+            .filter(|(key, desired)| match self.reported.get(key) {
+                Some(reported) => {
+                    reported.value != desired.value && desired.updated_at >= reported.updated_at
+                }
+                None => true,
+            })
+            .map(|(_, desired)| desired.clone())
+            .collect()
+    }
+    pub fn patch(&self, devid: u64) -> ApiDevSettings {
+        ApiDevSettings::from_vec(devid, self.diff())
+    }
+    pub fn is_in_sync(&self) -> bool {
+        self.diff().is_empty()
+    }
+    pub fn conflicts(&self) -> &[Conflict] {
+        &self.conflicts
+    }
+    pub fn take_conflicts(&mut self) -> Vec<Conflict> {
+        std::mem::take(&mut self.conflicts)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn setting(updated_at: i32, settings_type: i32, channel: i32, value: i32) -> DevSetting {
+        DevSetting {
+            updated_at,
+            settings_type,
+            channel,
+            value,
+        }
+    }
+
+    #[test]
+    fn minimal_patch() {
+        let mut cache = SettingsCache::new();
+        cache.set_desired(setting(100, 13, 0, 50));
+        cache.set_desired(setting(100, 13, 1, 60));
+        cache.set_desired(setting(100, 20, 0, 300));
+        assert!(!cache.set_desired(setting(90, 20, 0, 30)));
+
+        cache.update_reported(setting(80, 13, 0, 50));
+        cache.update_reported(setting(80, 13, 1, 10));
+        let patch = cache.patch(1337);
+        assert_eq!(
+            patch.as_slice(),
+            &[setting(100, 13, 1, 60), setting(100, 20, 0, 300)]
+        );
+
+        cache.update_reported(setting(100, 13, 1, 60));
+        cache.update_reported(setting(100, 20, 0, 300));
+        assert!(cache.is_in_sync());
+        assert!(cache.conflicts().is_empty());
+    }
+
+    #[test]
+    fn local_change_is_a_conflict() {
+        let mut cache = SettingsCache::new();
+        cache.set_desired(setting(100, 14, 3, 1));
+        cache.update_reported(setting(120, 14, 3, 0));
+        assert!(cache.is_in_sync());
+        assert_eq!(cache.desired(14, 3), Some(&setting(120, 14, 3, 0)));
+        assert_eq!(
+            cache.take_conflicts(),
+            vec![Conflict {
+                desired: setting(100, 14, 3, 1),
+                reported: setting(120, 14, 3, 0),
+            }]
+        );
+        assert!(cache.conflicts().is_empty());
+    }
+}
@@ -0,0 +1,298 @@
+use super::{ApiSettingTypes, SettingTypes, SettingsType};
+use crate::devices_connected::{DevicesConnected, DevicesConnectedTypes};
+use crate::devs::hb::DevType;
+use crate::devs::DevInfo;
+
+struct Meta {
+    settings_type: SettingsType,
+    name: &'static str,
+    description: &'static str,
+    unit: &'static str,
+    icon: &'static str,
+    default_value: i32,
+    min_value: i32,
+    max_value: i32,
+}
+
+// Units and ranges follow the wire encoding in settings::typed
+const CATALOGUE: &[Meta] = &[
+    Meta {
+        settings_type: SettingsType::FwBranch,
+        name: "Firmware branch",
+        description: "Firmware branch the device updates from (0: release, 1: beta, 2: develop)",
+        unit: "",
+        icon: "system_update",
+        default_value: 0,
+        min_value: 0,
+        max_value: 2,
+    },
+    Meta {
+        settings_type: SettingsType::DimTime,
+        name: "Dim time",
+        description: "Time used to fade the light on and off",
+        unit: "s",
+        icon: "timelapse",
+        default_value: 600,
+        min_value: 0,
+        max_value: 3600,
+    },
+    Meta {
+        settings_type: SettingsType::TimeOn,
+        name: "Time on",
+        description: "Time of day the light turns on",
+        unit: "s",
+        icon: "wb_sunny",
+        default_value: 6 * 3600,
+        min_value: 0,
+        max_value: 86399,
+    },
+    Meta {
+        settings_type: SettingsType::TimeOff,
+        name: "Time off",
+        description: "Time of day the light turns off",
+        unit: "s",
+        icon: "bedtime",
+        default_value: 22 * 3600,
+        min_value: 0,
+        max_value: 86399,
+    },
+    Meta {
+        settings_type: SettingsType::PwmVal,
+        name: "Intensity",
+        description: "Output level when turned on",
+        unit: "",
+        icon: "brightness_medium",
+        default_value: 255,
+        min_value: 0,
+        max_value: 255,
+    },
+    Meta {
+        settings_type: SettingsType::LedMode,
+        name: "LED mode",
+        description: "0: off, 1: on, 2: follow schedule, 3: light effect",
+        unit: "",
+        icon: "lightbulb",
+        default_value: 2,
+        min_value: 0,
+        max_value: 3,
+    },
+    Meta {
+        settings_type: SettingsType::LightEffect,
+        name: "Light effect",
+        description: "Packed light effect: kind, speed, depth and seed",
+        unit: "",
+        icon: "auto_awesome",
+        default_value: 0,
+        min_value: i32::MIN,
+        max_value: i32::MAX,
+    },
+    Meta {
+        settings_type: SettingsType::LogInterval,
+        name: "Log interval",
+        description: "Time between measurements",
+        unit: "s",
+        icon: "timer",
+        default_value: 300,
+        min_value: 10,
+        max_value: 86400,
+    },
+    Meta {
+        settings_type: SettingsType::DefaultPos,
+        name: "Default position",
+        description: "Position the motor returns to after power on",
+        unit: "steps",
+        icon: "straighten",
+        default_value: 0,
+        min_value: 0,
+        max_value: 100000,
+    },
+    Meta {
+        settings_type: SettingsType::DefaultSpeed,
+        name: "Default speed",
+        description: "Motor speed used when no speed is given",
+        unit: "steps/s",
+        icon: "speed",
+        default_value: 100,
+        min_value: 1,
+        max_value: 10000,
+    },
+    Meta {
+        settings_type: SettingsType::DoorlockMode,
+        name: "Lock mode",
+        description: "0: locked, 1: unlocked, 2: open with code",
+        unit: "",
+        icon: "lock",
+        default_value: 0,
+        min_value: 0,
+        max_value: 2,
+    },
+    Meta {
+        settings_type: SettingsType::DoorlockOpenTime,
+        name: "Open time",
+        description: "Time the lock stays open after unlocking",
+        unit: "s",
+        icon: "lock_clock",
+        default_value: 5,
+        min_value: 1,
+        max_value: 300,
+    },
+    Meta {
+        settings_type: SettingsType::DoorlockCode,
+        name: "Door code",
+        description: "Code that opens the lock",
+        unit: "",
+        icon: "pin",
+        default_value: 0,
+        min_value: 0,
+        max_value: 99_999_999,
+    },
+    Meta {
+        settings_type: SettingsType::DoorlockCodeValid,
+        name: "Code valid until",
+        description: "Time the door code expires",
+        unit: "unix time",
+        icon: "event_available",
+        default_value: 0,
+        min_value: 0,
+        max_value: i32::MAX,
+    },
+];
+
+pub fn setting_type(settings_type: SettingsType, channel: i32) -> Option<SettingTypes> {
+    let meta = CATALOGUE
+        .iter()
+        .find(|m| m.settings_type == settings_type)?;
+    let mut ret = SettingTypes::new(
+        settings_type.into(),
+        channel,
+        meta.default_value,
+        meta.min_value,
+        meta.max_value,
+    );
+    ret.set_name(meta.name.to_string());
+    ret.set_description(meta.description.to_string());
+    ret.set_unit(meta.unit.to_string());
+    ret.set_icon(meta.icon.to_string());
+    Some(ret)
+}
+
+// Settings of the device itself, always on channel 0
+pub fn device_settings(dev_type: DevType) -> &'static [SettingsType] {
+    match dev_type {
+        DevType::HortiPlantSensor | DevType::WeatherStation | DevType::EnvironmentSensor => {
+            &[SettingsType::FwBranch, SettingsType::LogInterval]
+        }
+        DevType::Unknown(_) => &[],
+        _ => &[SettingsType::FwBranch],
+    }
+}
+// Settings of a connected peripheral, on the channel given by its idx
+pub fn peripheral_settings(device: DevicesConnectedTypes) -> &'static [SettingsType] {
+    match device {
+        DevicesConnectedTypes::HortiLed => &[
+            SettingsType::LedMode,
+            SettingsType::PwmVal,
+            SettingsType::DimTime,
+            SettingsType::TimeOn,
+            SettingsType::TimeOff,
+        ],
+        DevicesConnectedTypes::FlickeringLed => &[
+            SettingsType::LedMode,
+            SettingsType::PwmVal,
+            SettingsType::LightEffect,
+        ],
+        DevicesConnectedTypes::Shmt3xSensor => &[SettingsType::LogInterval],
+        DevicesConnectedTypes::WateringPump | DevicesConnectedTypes::FanController => &[
+            SettingsType::PwmVal,
+            SettingsType::TimeOn,
+            SettingsType::TimeOff,
+        ],
+        DevicesConnectedTypes::StepperMotorDriver => {
+            &[SettingsType::DefaultPos, SettingsType::DefaultSpeed]
+        }
+        DevicesConnectedTypes::DoorLock => &[
+            SettingsType::DoorlockMode,
+            SettingsType::DoorlockOpenTime,
+            SettingsType::DoorlockCode,
+            SettingsType::DoorlockCodeValid,
+        ],
+        DevicesConnectedTypes::Default
+        | DevicesConnectedTypes::DoorSensor
+        | DevicesConnectedTypes::Other(_) => &[],
+    }
+}
+
+pub fn setting_types(
+    dev_type: DevType,
+    connected_devices: &[DevicesConnected],
+) -> Vec<SettingTypes> {
+    let device = device_settings(dev_type).iter().map(|t| (*t, 0));
+    let peripherals = connected_devices.iter().flat_map(|d| {
+        peripheral_settings(d.device_id())
+            .iter()
+            .map(|t| (*t, i32::from(d.idx())))
+    });
+    let mut ret: Vec<SettingTypes> = vec![];
+    for (settings_type, channel) in device.chain(peripherals) {
+        let id: i32 = settings_type.into();
+        if ret.iter().any(|t| t.id() == id && t.channel() == channel) {
+            continue;
+        }
+        ret.extend(setting_type(settings_type, channel));
+    }
+    ret
+}
+
+pub fn catalogue(dev: &DevInfo) -> ApiSettingTypes {
+    ApiSettingTypes::from_vec(
+        dev.dev_sn,
+        setting_types(dev.dev_type, &dev.connected_devices),
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn led_panel_catalogue() {
+        let mut dev = DevInfo::unknown_device(0x1004);
+        dev.dev_type = DevType::HortiLed;
+        dev.set_connected_devices(vec![
+            DevicesConnected::new_idx(DevicesConnectedTypes::HortiLed, 0),
+            DevicesConnected::new_idx(DevicesConnectedTypes::HortiLed, 1),
+            DevicesConnected::new_idx(DevicesConnectedTypes::DoorSensor, 2),
+        ]);
+        let catalogue = catalogue(&dev);
+        assert_eq!(catalogue.len(), 1 + 2 * 5);
+        let time_on = catalogue
+            .as_slice()
+            .iter()
+            .find(|t| t.id() == 11 && t.channel() == 1)
+            .unwrap();
+        assert_eq!(time_on.name(), "Time on");
+        assert_eq!(time_on.unit(), "s");
+        assert_eq!(time_on.default_value(), 6 * 3600);
+    }
+
+    #[test]
+    fn every_listed_type_has_metadata() {
+        for n in 0..=8 {
+            for t in peripheral_settings(DevicesConnectedTypes::from(n)) {
+                assert!(setting_type(*t, 0).is_some(), "{t:?}");
+            }
+        }
+        for n in 0..=20 {
+            for t in device_settings(DevType::from(n)) {
+                assert!(setting_type(*t, 0).is_some(), "{t:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn defaults_are_in_range() {
+        for meta in CATALOGUE {
+            assert!((meta.min_value..=meta.max_value).contains(&meta.default_value));
+        }
+    }
+}
@@ -0,0 +1,364 @@
+use chrono::{DateTime, NaiveTime, Timelike, Utc};
+use std::fmt::Display;
+use std::time::Duration;
+
+use super::{DevSetting, SettingsType};
+use crate::devs::hb::DevType;
+use crate::light_effect::LightEffect;
+
+// Wire encoding of the values, as used by the zephyr firmware:
+// TimeOn/TimeOff:                          seconds since midnight (0..86400)
+// DimTime/LogInterval/DoorlockOpenTime:    seconds
+// DoorlockCodeValid:                       unix timestamp the code is valid until
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Setting {
+    DevType(DevType),
+    FwBranch(FwBranch),
+    NetworkId(i32),
+    DimTime(Duration),
+    TimeOn(NaiveTime),
+    TimeOff(NaiveTime),
+    PwmVal(u8),
+    LedMode(LedMode),
+    LightEffect(LightEffect),
+    LogInterval(Duration),
+    DefaultPos(i32),
+    DefaultSpeed(i32),
+    DoorlockMode(DoorlockMode),
+    DoorlockOpenTime(Duration),
+    DoorlockCode(u32),
+    DoorlockCodeValid(DateTime<Utc>),
+    Unknown(i32, i32),
+}
+impl Setting {
+    pub fn from_raw(settings_type: SettingsType, value: i32) -> Result<Setting, SettingError> {
+        let out_of_range = || SettingError::OutOfRange {
+            settings_type,
+            value,
+        };
+        let setting = match settings_type {
+            SettingsType::DevType => {
+                Setting::DevType(u8::try_from(value).map_err(|_| out_of_range())?.into())
+            }
+            SettingsType::FwBranch => {
+                Setting::FwBranch(FwBranch::try_from(value).map_err(|_| out_of_range())?)
+            }
+            SettingsType::NetworkId => Setting::NetworkId(value),
+            SettingsType::DimTime => Setting::DimTime(seconds(value).ok_or_else(out_of_range)?),
+            SettingsType::TimeOn => Setting::TimeOn(time_of_day(value).ok_or_else(out_of_range)?),
+            SettingsType::TimeOff => Setting::TimeOff(time_of_day(value).ok_or_else(out_of_range)?),
+            SettingsType::PwmVal => {
+                Setting::PwmVal(u8::try_from(value).map_err(|_| out_of_range())?)
+            }
+            SettingsType::LedMode => {
+                Setting::LedMode(LedMode::try_from(value).map_err(|_| out_of_range())?)
+            }
+            SettingsType::LightEffect => {
+                // from_bytes clamps the depth, a value that changes on the way is out of range
+                let effect = LightEffect::from_bytes(value.to_le_bytes());
+                if effect.to_bytes() != value.to_le_bytes() {
+                    return Err(out_of_range());
+                }
+                Setting::LightEffect(effect)
+            }
+            SettingsType::LogInterval => {
+                Setting::LogInterval(seconds(value).ok_or_else(out_of_range)?)
+            }
+            SettingsType::DefaultPos => Setting::DefaultPos(value),
+            SettingsType::DefaultSpeed => Setting::DefaultSpeed(value),
+            SettingsType::DoorlockMode => {
+                Setting::DoorlockMode(DoorlockMode::try_from(value).map_err(|_| out_of_range())?)
+            }
+            SettingsType::DoorlockOpenTime => {
+                Setting::DoorlockOpenTime(seconds(value).ok_or_else(out_of_range)?)
+            }
+            SettingsType::DoorlockCode => {
+                Setting::DoorlockCode(u32::try_from(value).map_err(|_| out_of_range())?)
+            }
+            SettingsType::DoorlockCodeValid => Setting::DoorlockCodeValid(
+                DateTime::from_timestamp(value.into(), 0).ok_or_else(out_of_range)?,
+            ),
+            SettingsType::Unknown(n) => Setting::Unknown(n, value),
+        };
+        Ok(setting)
+    }
+    pub fn settings_type(&self) -> SettingsType {
+        match self {
+            Setting::DevType(_) => SettingsType::DevType,
+            Setting::FwBranch(_) => SettingsType::FwBranch,
+            Setting::NetworkId(_) => SettingsType::NetworkId,
+            Setting::DimTime(_) => SettingsType::DimTime,
+            Setting::TimeOn(_) => SettingsType::TimeOn,
+            Setting::TimeOff(_) => SettingsType::TimeOff,
+            Setting::PwmVal(_) => SettingsType::PwmVal,
+            Setting::LedMode(_) => SettingsType::LedMode,
+            Setting::LightEffect(_) => SettingsType::LightEffect,
+            Setting::LogInterval(_) => SettingsType::LogInterval,
+            Setting::DefaultPos(_) => SettingsType::DefaultPos,
+            Setting::DefaultSpeed(_) => SettingsType::DefaultSpeed,
+            Setting::DoorlockMode(_) => SettingsType::DoorlockMode,
+            Setting::DoorlockOpenTime(_) => SettingsType::DoorlockOpenTime,
+            Setting::DoorlockCode(_) => SettingsType::DoorlockCode,
+            Setting::DoorlockCodeValid(_) => SettingsType::DoorlockCodeValid,
+            Setting::Unknown(n, _) => SettingsType::Unknown(*n),
+        }
+    }
+    // Fails for values that do not fit the i32 on the wire
+    pub fn value(&self) -> Result<i32, SettingError> {
+        let not_encodable = || SettingError::NotEncodable(self.settings_type());
+        let value = match self {
+            Setting::DevType(devtype) => u8::from(*devtype).into(),
+            Setting::FwBranch(branch) => (*branch).into(),
+            Setting::NetworkId(v) | Setting::DefaultPos(v) | Setting::DefaultSpeed(v) => *v,
+            Setting::DimTime(d) | Setting::LogInterval(d) | Setting::DoorlockOpenTime(d) => {
+                i32::try_from(d.as_secs()).map_err(|_| not_encodable())?
+            }
+            Setting::TimeOn(t) | Setting::TimeOff(t) => t.num_seconds_from_midnight() as i32,
+            Setting::PwmVal(v) => (*v).into(),
+            Setting::LedMode(mode) => (*mode).into(),
+            Setting::LightEffect(effect) => {
+                if effect.depth > 100 {
+                    return Err(not_encodable());
+                }
+                i32::from_le_bytes(effect.to_bytes())
+            }
+            Setting::DoorlockMode(mode) => (*mode).into(),
+            Setting::DoorlockCode(code) => i32::try_from(*code).map_err(|_| not_encodable())?,
+            Setting::DoorlockCodeValid(ts) => {
+                i32::try_from(ts.timestamp()).map_err(|_| not_encodable())?
+            }
+            Setting::Unknown(_, v) => *v,
+        };
+        Ok(value)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TypedSetting {
+    pub updated_at: i32,
+    pub channel: i32,
+    pub setting: Setting,
+}
+impl TypedSetting {
+    pub fn new(channel: i32, setting: Setting) -> Result<Self, SettingError> {
+        setting.value()?;
+        Ok(Self {
+            updated_at: Utc::now().timestamp() as i32,
+            channel,
+            setting,
+        })
+    }
+}
+impl TryFrom<DevSetting> for TypedSetting {
+    type Error = SettingError;
+    fn try_from(value: DevSetting) -> Result<Self, Self::Error> {
+        Ok(TypedSetting {
+            updated_at: value.updated_at,
+            channel: value.channel,
+            setting: Setting::from_raw(value.settings_type(), value.value)?,
+        })
+    }
+}
+impl TryFrom<TypedSetting> for DevSetting {
+    type Error = SettingError;
+    fn try_from(value: TypedSetting) -> Result<Self, Self::Error> {
+        Ok(DevSetting {
+            updated_at: value.updated_at,
+            settings_type: value.setting.settings_type().into(),
+            channel: value.channel,
+            value: value.setting.value()?,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SettingError {
+    OutOfRange {
+        settings_type: SettingsType,
+        value: i32,
+    },
+    NotEncodable(SettingsType),
+}
+impl Display for SettingError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SettingError::OutOfRange {
+                settings_type,
+                value,
+            } => write!(
+                f,
+                "Value {value} out of range for setting {settings_type:?}"
+            ),
+            SettingError::NotEncodable(settings_type) => {
+                write!(f, "Value does not fit setting {settings_type:?}")
+            }
+        }
+    }
+}
+impl std::error::Error for SettingError {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FwBranch {
+    Release,
+    Beta,
+    Develop,
+}
+impl TryFrom<i32> for FwBranch {
+    type Error = i32;
+    fn try_from(value: i32) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(FwBranch::Release),
+            1 => Ok(FwBranch::Beta),
+            2 => Ok(FwBranch::Develop),
+            n => Err(n),
+        }
+    }
+}
+impl From<FwBranch> for i32 {
+    fn from(value: FwBranch) -> Self {
+        match value {
+            FwBranch::Release => 0,
+            FwBranch::Beta => 1,
+            FwBranch::Develop => 2,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LedMode {
+    Off,
+    On,
+    Schedule,
+    Effect,
+}
+impl TryFrom<i32> for LedMode {
+    type Error = i32;
+    fn try_from(value: i32) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(LedMode::Off),
+            1 => Ok(LedMode::On),
+            2 => Ok(LedMode::Schedule),
+            3 => Ok(LedMode::Effect),
+            n => Err(n),
+        }
+    }
+}
+impl From<LedMode> for i32 {
+    fn from(value: LedMode) -> Self {
+        match value {
+            LedMode::Off => 0,
+            LedMode::On => 1,
+            LedMode::Schedule => 2,
+            LedMode::Effect => 3,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DoorlockMode {
+    Locked,
+    Unlocked,
+    Code,
+}
+impl TryFrom<i32> for DoorlockMode {
+    type Error = i32;
+    fn try_from(value: i32) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(DoorlockMode::Locked),
+            1 => Ok(DoorlockMode::Unlocked),
+            2 => Ok(DoorlockMode::Code),
+            n => Err(n),
+        }
+    }
+}
+impl From<DoorlockMode> for i32 {
+    fn from(value: DoorlockMode) -> Self {
+        match value {
+            DoorlockMode::Locked => 0,
+            DoorlockMode::Unlocked => 1,
+            DoorlockMode::Code => 2,
+        }
+    }
+}
+
+fn seconds(value: i32) -> Option<Duration> {
+    Some(Duration::from_secs(u64::try_from(value).ok()?))
+}
+fn time_of_day(value: i32) -> Option<NaiveTime> {
+    NaiveTime::from_num_seconds_from_midnight_opt(u32::try_from(value).ok()?, 0)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn raw(settings_type: SettingsType, channel: i32, value: i32) -> DevSetting {
+        DevSetting {
+            updated_at: 1691096258,
+            settings_type: settings_type.into(),
+            channel,
+            value,
+        }
+    }
+
+    #[test]
+    fn roundtrip() {
+        for setting in [
+            raw(SettingsType::TimeOn, 1, 6 * 3600 + 30 * 60),
+            raw(SettingsType::DimTime, 0, 900),
+            raw(SettingsType::LedMode, 3, 2),
+            raw(SettingsType::FwBranch, 0, 1),
+            raw(SettingsType::DoorlockCodeValid, 0, 1691096258),
+            raw(SettingsType::Unknown(99), 4, -7),
+        ] {
+            let typed = TypedSetting::try_from(setting.clone()).unwrap();
+            assert_eq!(DevSetting::try_from(typed), Ok(setting));
+        }
+        let typed = TypedSetting::try_from(raw(SettingsType::TimeOff, 0, 22 * 3600)).unwrap();
+        assert_eq!(
+            typed.setting,
+            Setting::TimeOff(NaiveTime::from_hms_opt(22, 0, 0).unwrap())
+        );
+    }
+
+    #[test]
+    fn out_of_range() {
+        for (settings_type, value) in [
+            (SettingsType::TimeOn, 86400),
+            (SettingsType::LogInterval, -1),
+            (SettingsType::LedMode, 9),
+            (SettingsType::PwmVal, 256),
+            (SettingsType::DoorlockCode, -1),
+            // Depth 200
+            (SettingsType::LightEffect, 200 << 16 | 1),
+        ] {
+            assert_eq!(
+                TypedSetting::try_from(raw(settings_type, 0, value)),
+                Err(SettingError::OutOfRange {
+                    settings_type,
+                    value
+                })
+            );
+        }
+    }
+
+    #[test]
+    fn not_encodable() {
+        for setting in [
+            Setting::DoorlockCode(u32::MAX),
+            Setting::DimTime(Duration::from_secs(1 << 31)),
+            Setting::DoorlockCodeValid(DateTime::from_timestamp(1 << 31, 0).unwrap()),
+            Setting::LightEffect(LightEffect {
+                depth: 101,
+                ..LightEffect::candle(10, 50, 1)
+            }),
+        ] {
+            assert_eq!(
+                TypedSetting::new(0, setting),
+                Err(SettingError::NotEncodable(setting.settings_type()))
+            );
+        }
+        let typed = TypedSetting::new(1, Setting::DoorlockCode(1234)).unwrap();
+        assert_eq!(DevSetting::try_from(typed).unwrap().value, 1234);
+    }
+}
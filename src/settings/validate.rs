@@ -0,0 +1,204 @@
+use serde::{Deserialize, Serialize};
+use std::fmt::Display;
+
+use super::{ApiDevSettings, ApiSettingTypes, DevSetting, SettingTypes};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "camelCase")]
+pub enum ViolationReason {
+    UnknownType,
+    WrongChannel,
+    BelowMin,
+    AboveMax,
+}
+impl ViolationReason {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ViolationReason::UnknownType => "unknownType",
+            ViolationReason::WrongChannel => "wrongChannel",
+            ViolationReason::BelowMin => "belowMin",
+            ViolationReason::AboveMax => "aboveMax",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct Violation {
+    #[serde(rename = "typeId")]
+    pub settings_type: i32,
+    pub channel: i32,
+    pub value: i32,
+    pub reason: ViolationReason,
+}
+impl Display for Violation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Setting {} channel {} value {}: {}",
+            self.settings_type,
+            self.channel,
+            self.value,
+            self.reason.as_str()
+        )
+    }
+}
+
+// Checks settings against the SettingTypes catalogue of a device before they are sent to it
+pub struct SettingsValidator<'a> {
+    catalogue: &'a ApiSettingTypes,
+}
+impl<'a> SettingsValidator<'a> {
+    pub fn new(catalogue: &'a ApiSettingTypes) -> Self {
+        Self { catalogue }
+    }
+    fn lookup(&self, setting: &DevSetting) -> Result<&'a SettingTypes, ViolationReason> {
+        let mut known_type = false;
+        for setting_type in self.catalogue.as_slice() {
+            if setting_type.id() == setting.settings_type {
+                if setting_type.channel() == setting.channel {
+                    return Ok(setting_type);
+                }
+                known_type = true;
+            }
+        }
+        match known_type {
+            true => Err(ViolationReason::WrongChannel),
+            false => Err(ViolationReason::UnknownType),
+        }
+    }
+    pub fn check(&self, setting: &DevSetting) -> Option<Violation> {
+        let reason = match self.lookup(setting) {
+            Err(reason) => reason,
+            Ok(t) if setting.value < t.min_value() => ViolationReason::BelowMin,
+            Ok(t) if setting.value > t.max_value() => ViolationReason::AboveMax,
+            Ok(_) => return None,
+        };
+        Some(Violation {
+            settings_type: setting.settings_type,
+            channel: setting.channel,
+            value: setting.value,
+            reason,
+        })
+    }
+    // Every violation in the update, empty when it can be sent as is
+    pub fn validate(&self, update: &ApiDevSettings) -> Vec<Violation> {
+        update
+            .as_slice()
+            .iter()
+            .filter_map(|s| self.check(s))
+            .collect()
+    }
+    // Clamps known settings into min..=max, unknown types and channels are left for validate() to report
+    pub fn clamp(&self, update: &mut ApiDevSettings) {
+        for setting in update.as_mut_slice() {
+            if let Ok(t) = self.lookup(setting) {
+                if t.min_value() <= t.max_value() {
+                    setting.value = setting.value.clamp(t.min_value(), t.max_value());
+                }
+            }
+        }
+    }
+    // Adds the default value for every catalogue entry missing from the update.
+    // Defaults get updated_at 0 so that any value set on the device wins over them.
+    pub fn fill_defaults(&self, update: &mut ApiDevSettings) {
+        for t in self.catalogue.as_slice() {
+            let present = update
+                .as_slice()
+                .iter()
+                .any(|s| s.settings_type == t.id() && s.channel == t.channel());
+            if !present {
+                update.add_setting(DevSetting {
+                    updated_at: 0,
+                    settings_type: t.id(),
+                    channel: t.channel(),
+                    value: t.default_value(),
+                });
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn catalogue() -> ApiSettingTypes {
+        ApiSettingTypes::from_vec(
+            1337,
+            vec![
+                SettingTypes::new(13, 0, 50, 0, 100),
+                SettingTypes::new(13, 1, 50, 0, 100),
+                SettingTypes::new(20, 0, 60, 10, 3600),
+            ],
+        )
+    }
+    fn setting(settings_type: i32, channel: i32, value: i32) -> DevSetting {
+        DevSetting {
+            updated_at: 1691096258,
+            settings_type,
+            channel,
+            value,
+        }
+    }
+
+    #[test]
+    fn reports_all_violations() {
+        let catalogue = catalogue();
+        let validator = SettingsValidator::new(&catalogue);
+        let update = ApiDevSettings::from_vec(
+            1337,
+            vec![
+                setting(13, 0, 40),
+                setting(13, 2, 40),
+                setting(99, 0, 1),
+                setting(13, 1, 101),
+                setting(20, 0, 5),
+            ],
+        );
+        let reasons: Vec<_> = validator
+            .validate(&update)
+            .iter()
+            .map(|v| v.reason)
+            .collect();
+        assert_eq!(
+            reasons,
+            vec![
+                ViolationReason::WrongChannel,
+                ViolationReason::UnknownType,
+                ViolationReason::AboveMax,
+                ViolationReason::BelowMin,
+            ]
+        );
+        assert_eq!(
+            serde_json::to_value(&validator.validate(&update)[0]).unwrap(),
+            serde_json::json!({"typeId": 13, "channel": 2, "value": 40, "reason": "wrongChannel"})
+        );
+    }
+
+    #[test]
+    fn clamp_and_defaults() {
+        let catalogue = catalogue();
+        let validator = SettingsValidator::new(&catalogue);
+        let mut update =
+            ApiDevSettings::from_vec(1337, vec![setting(13, 1, 101), setting(20, 0, 5)]);
+        validator.clamp(&mut update);
+        validator.fill_defaults(&mut update);
+        assert!(validator.validate(&update).is_empty());
+        assert_eq!(
+            update.as_slice(),
+            &[
+                setting(13, 1, 100),
+                setting(20, 0, 10),
+                DevSetting {
+                    updated_at: 0,
+                    settings_type: 13,
+                    channel: 0,
+                    value: 50
+                }
+            ]
+        );
+    }
+}
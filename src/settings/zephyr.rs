@@ -0,0 +1,174 @@
+use bincode::config::{Configuration, Fixint, LittleEndian, NoLimit};
+use std::fmt;
+
+use super::{DevSetting, DevSettingsZephyr};
+
+// Wire format of settings exchanged with zephyr.
+//
+// A batch is a plain array of `struct settings_data`, the same bytes as the #[repr(C)] struct:
+//
+// | settings_type: i16 le | channel: i16 le | value: i32 le | updated_at: i32 le |  x n
+//
+// There is no header, the number of records is payload length / 12.
+pub const ZEPHYR_SETTING_LEN: usize = size_of::<DevSettingsZephyr>();
+
+pub fn bincode_config() -> Configuration<LittleEndian, Fixint, NoLimit> {
+    bincode::config::standard()
+        .with_little_endian()
+        .with_fixed_int_encoding()
+}
+
+impl DevSettingsZephyr {
+    pub fn to_bytes(&self) -> [u8; ZEPHYR_SETTING_LEN] {
+        let mut ret = [0; ZEPHYR_SETTING_LEN];
+        // Can not fail, the record is always ZEPHYR_SETTING_LEN bytes
+        let _ = bincode::encode_into_slice(self, &mut ret, bincode_config());
+        ret
+    }
+    pub fn from_payload(payload: &[u8]) -> Option<DevSettingsZephyr> {
+        if payload.len() != ZEPHYR_SETTING_LEN {
+            return None;
+        }
+        bincode::decode_from_slice(payload, bincode_config())
+            .ok()
+            .map(|(setting, _)| setting)
+    }
+    pub fn to_plain(&self) -> DevSetting {
+        DevSetting {
+            updated_at: self.updated_at,
+            settings_type: self.settings_type.into(),
+            channel: self.channel.into(),
+            value: self.value,
+        }
+    }
+}
+
+// A setting whose type or channel does not fit the zephyr record, index is its position in the batch
+#[derive(Debug, Clone, PartialEq)]
+pub struct SettingRangeError {
+    pub index: usize,
+    pub setting: DevSetting,
+}
+impl fmt::Display for SettingRangeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Setting {} (type {}, channel {}) out of range for zephyr",
+            self.index, self.setting.settings_type, self.setting.channel
+        )
+    }
+}
+impl std::error::Error for SettingRangeError {}
+
+pub fn settings_to_payload(settings: &[DevSetting]) -> Result<Vec<u8>, SettingRangeError> {
+    let mut ret = Vec::with_capacity(settings.len() * ZEPHYR_SETTING_LEN);
+    for (index, setting) in settings.iter().enumerate() {
+        let zephyr = setting.try_to_zephyr().ok_or_else(|| SettingRangeError {
+            index,
+            setting: setting.clone(),
+        })?;
+        ret.extend_from_slice(&zephyr.to_bytes());
+    }
+    Ok(ret)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SettingPayloadError {
+    // The payload length is not a multiple of ZEPHYR_SETTING_LEN
+    Truncated(usize),
+    // Record at index could not be decoded
+    Invalid(usize),
+}
+impl fmt::Display for SettingPayloadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SettingPayloadError::Truncated(len) => write!(
+                f,
+                "Settings payload of {len} bytes is not a multiple of {ZEPHYR_SETTING_LEN}"
+            ),
+            SettingPayloadError::Invalid(index) => write!(f, "Unable to parse setting {index}"),
+        }
+    }
+}
+impl std::error::Error for SettingPayloadError {}
+
+pub fn settings_from_payload(payload: &[u8]) -> Result<Vec<DevSetting>, SettingPayloadError> {
+    if !payload.len().is_multiple_of(ZEPHYR_SETTING_LEN) {
+        return Err(SettingPayloadError::Truncated(payload.len()));
+    }
+    payload
+        .chunks(ZEPHYR_SETTING_LEN)
+        .enumerate()
+        .map(|(index, s)| {
+            DevSettingsZephyr::from_payload(s)
+                .map(|s| s.to_plain())
+                .ok_or(SettingPayloadError::Invalid(index))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn record_layout() {
+        let setting = DevSettingsZephyr {
+            settings_type: 14,
+            channel: -2,
+            value: 0x01020304,
+            updated_at: 1691096258,
+        };
+        // struct settings_data on the device, little-endian and without padding
+        let raw = [
+            &14i16.to_le_bytes()[..],
+            &(-2i16).to_le_bytes(),
+            &0x01020304i32.to_le_bytes(),
+            &1691096258i32.to_le_bytes(),
+        ]
+        .concat();
+        assert_eq!(ZEPHYR_SETTING_LEN, 12);
+        assert_eq!(
+            setting.to_bytes(),
+            [14, 0, 0xfe, 0xff, 4, 3, 2, 1, 0xc2, 0x14, 0xcc, 0x64]
+        );
+        assert_eq!(&setting.to_bytes()[..], raw);
+        assert_eq!(DevSettingsZephyr::from_payload(&raw), Some(setting));
+    }
+
+    #[test]
+    fn batch_roundtrip() {
+        let settings = vec![
+            DevSetting {
+                updated_at: 1691096258,
+                settings_type: 14,
+                channel: 3,
+                value: 1,
+            },
+            DevSetting {
+                updated_at: 1691096259,
+                settings_type: 20,
+                channel: 0,
+                value: -300,
+            },
+        ];
+        let payload = settings_to_payload(&settings).unwrap();
+        assert_eq!(payload.len(), 2 * ZEPHYR_SETTING_LEN);
+        assert_eq!(settings_from_payload(&payload), Ok(settings.clone()));
+        assert_eq!(
+            settings_from_payload(&payload[..20]),
+            Err(SettingPayloadError::Truncated(20))
+        );
+        assert_eq!(settings_from_payload(&[]), Ok(vec![]));
+
+        let mut wide = settings;
+        wide[1].channel = 40000;
+        assert_eq!(
+            settings_to_payload(&wide),
+            Err(SettingRangeError {
+                index: 1,
+                setting: wide[1].clone(),
+            })
+        );
+    }
+}
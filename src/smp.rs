@@ -0,0 +1,703 @@
+use ciborium::Value;
+use std::fmt::Display;
+
+use crate::devs::hb::DevStatus;
+use crate::firmware::mcuboot::McubootImage;
+use crate::firmware::FirmwareVersion;
+
+// mcumgr Simple Management Protocol, one frame per request or response:
+//
+// | op: u8 (bits 0-2 op, bits 3-4 version) | flags: u8 | len: u16 be | group: u16 be | seq: u8 | id: u8 | CBOR map (len bytes) |
+//
+// group 0 (OS):    0 echo, 5 reset
+// group 1 (image): 0 state (read: list, write: test/confirm), 1 upload
+pub const SMP_HEADER_LEN: usize = 8;
+pub const GROUP_OS: u16 = 0;
+pub const GROUP_IMAGE: u16 = 1;
+pub const OS_ECHO: u8 = 0;
+pub const OS_RESET: u8 = 5;
+pub const IMAGE_STATE: u8 = 0;
+pub const IMAGE_UPLOAD: u8 = 1;
+// The CBOR body has to fit the u16 len of the header
+pub const SMP_MAX_BODY_LEN: usize = u16::MAX as usize;
+// Largest upload chunk, leaves room for the other fields and the sha of the first chunk
+pub const MAX_CHUNK_SIZE: usize = SMP_MAX_BODY_LEN - 256;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SmpOp {
+    Read,
+    ReadRsp,
+    Write,
+    WriteRsp,
+    Other(u8),
+}
+impl SmpOp {
+    pub fn is_response(&self) -> bool {
+        matches!(self, SmpOp::ReadRsp | SmpOp::WriteRsp)
+    }
+    fn response(&self) -> SmpOp {
+        match self {
+            SmpOp::Read => SmpOp::ReadRsp,
+            SmpOp::Write => SmpOp::WriteRsp,
+            op => *op,
+        }
+    }
+}
+impl From<u8> for SmpOp {
+    fn from(value: u8) -> Self {
+        match value {
+            0 => SmpOp::Read,
+            1 => SmpOp::ReadRsp,
+            2 => SmpOp::Write,
+            3 => SmpOp::WriteRsp,
+            n => SmpOp::Other(n),
+        }
+    }
+}
+impl From<SmpOp> for u8 {
+    fn from(value: SmpOp) -> Self {
+        match value {
+            SmpOp::Read => 0,
+            SmpOp::ReadRsp => 1,
+            SmpOp::Write => 2,
+            SmpOp::WriteRsp => 3,
+            SmpOp::Other(n) => n,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SmpHeader {
+    pub op: SmpOp,
+    // 0 for SMP v1, 1 for SMP v2
+    pub version: u8,
+    pub flags: u8,
+    pub len: u16,
+    pub group: u16,
+    pub seq: u8,
+    pub id: u8,
+}
+impl SmpHeader {
+    pub fn new(op: SmpOp, group: u16, id: u8, seq: u8) -> Self {
+        Self {
+            op,
+            version: 1,
+            flags: 0,
+            len: 0,
+            group,
+            seq,
+            id,
+        }
+    }
+    pub fn to_bytes(&self) -> [u8; SMP_HEADER_LEN] {
+        let [l1, l2] = self.len.to_be_bytes();
+        let [g1, g2] = self.group.to_be_bytes();
+        [
+            u8::from(self.op) & 0x07 | (self.version & 0x03) << 3,
+            self.flags,
+            l1,
+            l2,
+            g1,
+            g2,
+            self.seq,
+            self.id,
+        ]
+    }
+    pub fn from_bytes(bytes: &[u8]) -> Result<SmpHeader, SmpError> {
+        let [op, flags, l1, l2, g1, g2, seq, id, ..] = *bytes else {
+            return Err(SmpError::Truncated);
+        };
+        Ok(SmpHeader {
+            op: (op & 0x07).into(),
+            version: (op >> 3) & 0x03,
+            flags,
+            len: u16::from_be_bytes([l1, l2]),
+            group: u16::from_be_bytes([g1, g2]),
+            seq,
+            id,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SmpError {
+    Truncated,
+    LengthMismatch { header: u16, body: usize },
+    Cbor(String),
+    InvalidBody(&'static str),
+    TooLarge(usize),
+    InvalidChunkSize(usize),
+    Unsupported { op: SmpOp, group: u16, id: u8 },
+}
+impl Display for SmpError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SmpError::Truncated => write!(f, "SMP frame is truncated"),
+            SmpError::LengthMismatch { header, body } => {
+                write!(
+                    f,
+                    "SMP header length {header} does not match body length {body}"
+                )
+            }
+            SmpError::Cbor(e) => write!(f, "Invalid CBOR body: {e}"),
+            SmpError::InvalidBody(e) => write!(f, "Invalid SMP body: {e}"),
+            SmpError::TooLarge(len) => {
+                write!(f, "SMP body of {len} bytes does not fit in a frame")
+            }
+            SmpError::InvalidChunkSize(size) => {
+                write!(f, "Chunk size {size} is not between 1 and {MAX_CHUNK_SIZE}")
+            }
+            SmpError::Unsupported { op, group, id } => {
+                write!(f, "Unsupported SMP command {op:?} group {group} id {id}")
+            }
+        }
+    }
+}
+impl std::error::Error for SmpError {}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct ImageUpload {
+    pub image: Option<u32>,
+    pub off: u32,
+    // Total image length, only in the first chunk
+    pub len: Option<u32>,
+    pub sha: Option<Vec<u8>>,
+    pub data: Vec<u8>,
+    pub upgrade: Option<bool>,
+}
+impl ImageUpload {
+    // Splits an image in upload requests of at most `chunk_size` data bytes
+    pub fn chunks(image: &[u8], chunk_size: usize) -> Result<Vec<ImageUpload>, SmpError> {
+        if !(1..=MAX_CHUNK_SIZE).contains(&chunk_size) {
+            return Err(SmpError::InvalidChunkSize(chunk_size));
+        }
+        Ok(image
+            .chunks(chunk_size)
+            .enumerate()
+            .map(|(n, data)| ImageUpload {
+                off: (n * chunk_size) as u32,
+                len: (n == 0).then_some(image.len() as u32),
+                data: data.to_vec(),
+                ..Default::default()
+            })
+            .collect())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SmpRequest {
+    Echo(String),
+    Reset,
+    ImageState,
+    // Marks the image as pending for the next boot only
+    ImageTest { hash: Vec<u8> },
+    // Makes the image permanent, without hash the running image is confirmed
+    ImageConfirm { hash: Option<Vec<u8>> },
+    ImageUpload(ImageUpload),
+}
+impl SmpRequest {
+    pub fn header(&self, seq: u8) -> SmpHeader {
+        let (op, group, id) = match self {
+            SmpRequest::Echo(_) => (SmpOp::Write, GROUP_OS, OS_ECHO),
+            SmpRequest::Reset => (SmpOp::Write, GROUP_OS, OS_RESET),
+            SmpRequest::ImageState => (SmpOp::Read, GROUP_IMAGE, IMAGE_STATE),
+            SmpRequest::ImageTest { .. } | SmpRequest::ImageConfirm { .. } => {
+                (SmpOp::Write, GROUP_IMAGE, IMAGE_STATE)
+            }
+            SmpRequest::ImageUpload(_) => (SmpOp::Write, GROUP_IMAGE, IMAGE_UPLOAD),
+        };
+        SmpHeader::new(op, group, id, seq)
+    }
+    fn body(&self) -> Vec<(Value, Value)> {
+        match self {
+            SmpRequest::Echo(d) => vec![entry("d", d.as_str().into())],
+            SmpRequest::Reset | SmpRequest::ImageState => vec![],
+            SmpRequest::ImageTest { hash } => vec![
+                entry("confirm", false.into()),
+                entry("hash", hash.clone().into()),
+            ],
+            SmpRequest::ImageConfirm { hash } => {
+                let mut ret = vec![entry("confirm", true.into())];
+                if let Some(hash) = hash {
+                    ret.push(entry("hash", hash.clone().into()));
+                }
+                ret
+            }
+            SmpRequest::ImageUpload(upload) => {
+                let mut ret = Vec::new();
+                if let Some(image) = upload.image {
+                    ret.push(entry("image", image.into()));
+                }
+                if let Some(len) = upload.len {
+                    ret.push(entry("len", len.into()));
+                }
+                ret.push(entry("off", upload.off.into()));
+                if let Some(sha) = &upload.sha {
+                    ret.push(entry("sha", sha.clone().into()));
+                }
+                ret.push(entry("data", upload.data.clone().into()));
+                if let Some(upgrade) = upload.upgrade {
+                    ret.push(entry("upgrade", upgrade.into()));
+                }
+                ret
+            }
+        }
+    }
+    pub fn encode(&self, seq: u8) -> Result<Vec<u8>, SmpError> {
+        encode_frame(self.header(seq), self.body())
+    }
+    pub fn decode(bytes: &[u8]) -> Result<(SmpHeader, SmpRequest), SmpError> {
+        let (header, body) = decode_frame(bytes)?;
+        let request = match (header.op, header.group, header.id) {
+            (SmpOp::Write, GROUP_OS, OS_ECHO) => SmpRequest::Echo(text(&body, "d")?),
+            (SmpOp::Write, GROUP_OS, OS_RESET) => SmpRequest::Reset,
+            (SmpOp::Read, GROUP_IMAGE, IMAGE_STATE) => SmpRequest::ImageState,
+            (SmpOp::Write, GROUP_IMAGE, IMAGE_STATE) => {
+                let hash = opt_bytes(&body, "hash")?;
+                match (opt_bool(&body, "confirm")?.unwrap_or(false), hash) {
+                    (true, hash) => SmpRequest::ImageConfirm { hash },
+                    (false, Some(hash)) => SmpRequest::ImageTest { hash },
+                    (false, None) => return Err(SmpError::InvalidBody("missing hash")),
+                }
+            }
+            (SmpOp::Write, GROUP_IMAGE, IMAGE_UPLOAD) => SmpRequest::ImageUpload(ImageUpload {
+                image: opt_u32(&body, "image")?,
+                off: opt_u32(&body, "off")?.ok_or(SmpError::InvalidBody("missing off"))?,
+                len: opt_u32(&body, "len")?,
+                sha: opt_bytes(&body, "sha")?,
+                data: opt_bytes(&body, "data")?.ok_or(SmpError::InvalidBody("missing data"))?,
+                upgrade: opt_bool(&body, "upgrade")?,
+            }),
+            (op, group, id) => return Err(SmpError::Unsupported { op, group, id }),
+        };
+        Ok((header, request))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImageSlot {
+    pub image: u32,
+    pub slot: u32,
+    pub version: FirmwareVersion,
+    pub hash: Vec<u8>,
+    pub bootable: bool,
+    pub pending: bool,
+    pub confirmed: bool,
+    pub active: bool,
+    pub permanent: bool,
+}
+impl ImageSlot {
+    // The mcumgr hash is the SHA-256 TLV of the image
+    pub fn matches(&self, image: &McubootImage) -> bool {
+        image.sha256().is_some_and(|sha| self.hash == sha)
+    }
+    fn to_value(&self) -> Value {
+        let mut ret = vec![];
+        if self.image != 0 {
+            ret.push(entry("image", self.image.into()));
+        }
+        ret.extend([
+            entry("slot", self.slot.into()),
+            entry("version", self.version.to_string().into()),
+            entry("hash", self.hash.clone().into()),
+            entry("bootable", self.bootable.into()),
+            entry("pending", self.pending.into()),
+            entry("confirmed", self.confirmed.into()),
+            entry("active", self.active.into()),
+            entry("permanent", self.permanent.into()),
+        ]);
+        Value::Map(ret)
+    }
+    fn from_value(value: &Value) -> Result<ImageSlot, SmpError> {
+        let map = value
+            .as_map()
+            .ok_or(SmpError::InvalidBody("image slot is not a map"))?;
+        let flag = |key| opt_bool(map, key).map(|f| f.unwrap_or(false));
+        Ok(ImageSlot {
+            image: opt_u32(map, "image")?.unwrap_or(0),
+            slot: opt_u32(map, "slot")?.ok_or(SmpError::InvalidBody("missing slot"))?,
+            version: text(map, "version")?
+                .parse()
+                .map_err(|_| SmpError::InvalidBody("invalid version"))?,
+            hash: opt_bytes(map, "hash")?.unwrap_or_default(),
+            bootable: flag("bootable")?,
+            pending: flag("pending")?,
+            confirmed: flag("confirmed")?,
+            active: flag("active")?,
+            permanent: flag("permanent")?,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct ImageState {
+    pub images: Vec<ImageSlot>,
+}
+impl ImageState {
+    pub fn active(&self) -> Option<&ImageSlot> {
+        self.images.iter().find(|s| s.active)
+    }
+    pub fn pending(&self) -> Option<&ImageSlot> {
+        self.images.iter().find(|s| s.pending)
+    }
+    pub fn find(&self, image: &McubootImage) -> Option<&ImageSlot> {
+        self.images.iter().find(|s| s.matches(image))
+    }
+    // Version the device is running, as reported in HeartBeat.fwver
+    pub fn firmware_version(&self) -> Option<FirmwareVersion> {
+        self.active().map(|s| s.version.clone())
+    }
+    // A pending image is flashed on the next reset
+    pub fn dev_status(&self) -> DevStatus {
+        match (self.pending(), self.active()) {
+            (Some(_), _) => DevStatus::Flashing,
+            (None, Some(_)) => DevStatus::RunningOk,
+            (None, None) => DevStatus::Error,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SmpResponse {
+    Echo(String),
+    Reset,
+    ImageState(ImageState),
+    // Offset of the next chunk the device expects
+    ImageUpload { off: u32 },
+    // Non zero rc, group is only reported by SMP v2
+    Error { group: Option<u16>, rc: u32 },
+}
+impl SmpResponse {
+    fn body(&self) -> Vec<(Value, Value)> {
+        match self {
+            SmpResponse::Echo(r) => vec![entry("r", r.as_str().into())],
+            SmpResponse::Reset => vec![],
+            SmpResponse::ImageState(state) => vec![entry(
+                "images",
+                Value::Array(state.images.iter().map(ImageSlot::to_value).collect()),
+            )],
+            SmpResponse::ImageUpload { off } => {
+                vec![entry("rc", 0.into()), entry("off", (*off).into())]
+            }
+            SmpResponse::Error { group: None, rc } => vec![entry("rc", (*rc).into())],
+            SmpResponse::Error {
+                group: Some(group),
+                rc,
+            } => vec![entry(
+                "err",
+                Value::Map(vec![
+                    entry("group", (*group).into()),
+                    entry("rc", (*rc).into()),
+                ]),
+            )],
+        }
+    }
+    // Encodes the response to `request`, reusing its group, id and sequence number
+    pub fn encode(&self, request: &SmpHeader) -> Result<Vec<u8>, SmpError> {
+        let header = SmpHeader {
+            op: request.op.response(),
+            flags: 0,
+            len: 0,
+            ..*request
+        };
+        encode_frame(header, self.body())
+    }
+    pub fn decode(bytes: &[u8]) -> Result<(SmpHeader, SmpResponse), SmpError> {
+        let (header, body) = decode_frame(bytes)?;
+        if let Some(err) = get(&body, "err").and_then(Value::as_map) {
+            let response = SmpResponse::Error {
+                group: opt_u32(err, "group")?
+                    .map(|g| {
+                        u16::try_from(g).map_err(|_| SmpError::InvalidBody("group out of range"))
+                    })
+                    .transpose()?,
+                rc: opt_u32(err, "rc")?.unwrap_or(0),
+            };
+            return Ok((header, response));
+        }
+        if let Some(rc) = opt_u32(&body, "rc")?.filter(|rc| *rc != 0) {
+            return Ok((header, SmpResponse::Error { group: None, rc }));
+        }
+        let response = match (header.op, header.group, header.id) {
+            (SmpOp::WriteRsp, GROUP_OS, OS_ECHO) => SmpResponse::Echo(text(&body, "r")?),
+            (SmpOp::WriteRsp, GROUP_OS, OS_RESET) => SmpResponse::Reset,
+            (SmpOp::ReadRsp | SmpOp::WriteRsp, GROUP_IMAGE, IMAGE_STATE) => {
+                let images = get(&body, "images")
+                    .and_then(Value::as_array)
+                    .ok_or(SmpError::InvalidBody("missing images"))?;
+                SmpResponse::ImageState(ImageState {
+                    images: images
+                        .iter()
+                        .map(ImageSlot::from_value)
+                        .collect::<Result<_, _>>()?,
+                })
+            }
+            (SmpOp::WriteRsp, GROUP_IMAGE, IMAGE_UPLOAD) => SmpResponse::ImageUpload {
+                off: opt_u32(&body, "off")?.ok_or(SmpError::InvalidBody("missing off"))?,
+            },
+            (op, group, id) => return Err(SmpError::Unsupported { op, group, id }),
+        };
+        Ok((header, response))
+    }
+}
+
+fn entry(key: &str, value: Value) -> (Value, Value) {
+    (Value::Text(key.to_string()), value)
+}
+
+fn encode_frame(mut header: SmpHeader, body: Vec<(Value, Value)>) -> Result<Vec<u8>, SmpError> {
+    let mut cbor = Vec::new();
+    ciborium::into_writer(&Value::Map(body), &mut cbor).expect("writing to a Vec can not fail");
+    header.len = u16::try_from(cbor.len()).map_err(|_| SmpError::TooLarge(cbor.len()))?;
+    Ok([header.to_bytes().to_vec(), cbor].concat())
+}
+
+fn decode_frame(bytes: &[u8]) -> Result<(SmpHeader, Vec<(Value, Value)>), SmpError> {
+    let header = SmpHeader::from_bytes(bytes)?;
+    let body = &bytes[SMP_HEADER_LEN..];
+    if body.len() != usize::from(header.len) {
+        return Err(SmpError::LengthMismatch {
+            header: header.len,
+            body: body.len(),
+        });
+    }
+    let value: Value = ciborium::from_reader(body).map_err(|e| SmpError::Cbor(e.to_string()))?;
+    match value {
+        Value::Map(map) => Ok((header, map)),
+        _ => Err(SmpError::InvalidBody("body is not a map")),
+    }
+}
+
+fn get<'a>(map: &'a [(Value, Value)], key: &str) -> Option<&'a Value> {
+    map.iter()
+        .find(|(k, _)| k.as_text() == Some(key))
+        .map(|(_, v)| v)
+}
+
+fn text(map: &[(Value, Value)], key: &'static str) -> Result<String, SmpError> {
+    get(map, key)
+        .and_then(Value::as_text)
+        .map(str::to_string)
+        .ok_or(SmpError::InvalidBody(key))
+}
+
+fn opt_u32(map: &[(Value, Value)], key: &'static str) -> Result<Option<u32>, SmpError> {
+    get(map, key)
+        .map(|v| {
+            v.as_integer()
+                .and_then(|i| u32::try_from(i).ok())
+                .ok_or(SmpError::InvalidBody(key))
+        })
+        .transpose()
+}
+
+fn opt_bool(map: &[(Value, Value)], key: &'static str) -> Result<Option<bool>, SmpError> {
+    get(map, key)
+        .map(|v| v.as_bool().ok_or(SmpError::InvalidBody(key)))
+        .transpose()
+}
+
+fn opt_bytes(map: &[(Value, Value)], key: &'static str) -> Result<Option<Vec<u8>>, SmpError> {
+    get(map, key)
+        .map(|v| v.as_bytes().cloned().ok_or(SmpError::InvalidBody(key)))
+        .transpose()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn hex(s: &str) -> Vec<u8> {
+        let s: String = s.split_whitespace().collect();
+        (0..s.len())
+            .step_by(2)
+            .map(|n| u8::from_str_radix(&s[n..n + 2], 16).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn echo() {
+        // mcumgr echo hello
+        let request = hex("0a 00 00 09 00 00 2a 00  a1 61 64 65 68 65 6c 6c 6f");
+        assert_eq!(
+            SmpRequest::Echo("hello".into()).encode(42).unwrap(),
+            request
+        );
+        let (header, decoded) = SmpRequest::decode(&request).unwrap();
+        assert_eq!(decoded, SmpRequest::Echo("hello".into()));
+
+        let response = hex("0b 00 00 09 00 00 2a 00  a1 61 72 65 68 65 6c 6c 6f");
+        assert_eq!(
+            SmpResponse::Echo("hello".into()).encode(&header).unwrap(),
+            response
+        );
+        assert_eq!(
+            SmpResponse::decode(&response).unwrap().1,
+            SmpResponse::Echo("hello".into())
+        );
+    }
+
+    #[test]
+    fn reset() {
+        let request = hex("0a 00 00 01 00 00 07 05  a0");
+        assert_eq!(SmpRequest::Reset.encode(7).unwrap(), request);
+        // SMP v1 response from Zephyr, indefinite length map
+        let response = hex("03 00 00 02 00 00 07 05  bf ff");
+        let (header, decoded) = SmpResponse::decode(&response).unwrap();
+        assert_eq!(decoded, SmpResponse::Reset);
+        assert_eq!(header.version, 0);
+    }
+
+    #[test]
+    fn image_state_list() {
+        let request = hex("08 00 00 01 00 01 03 00  a0");
+        assert_eq!(SmpRequest::ImageState.encode(3).unwrap(), request);
+
+        // mcumgr image list with one confirmed image running 1.4.2.7
+        let response = hex("01 00 00 5e 00 01 03 00
+             bf 66 69 6d 61 67 65 73 9f bf 64 73 6c 6f 74 00 67 76 65 72 73 69 6f 6e
+             67 31 2e 34 2e 32 2e 37 64 68 61 73 68 44 de ad be ef 68 62 6f 6f 74 61
+             62 6c 65 f5 67 70 65 6e 64 69 6e 67 f4 69 63 6f 6e 66 69 72 6d 65 64 f5
+             66 61 63 74 69 76 65 f5 69 70 65 72 6d 61 6e 65 6e 74 f4 ff ff ff");
+        let (_, decoded) = SmpResponse::decode(&response).unwrap();
+        let SmpResponse::ImageState(state) = decoded else {
+            panic!("expected image state, got {decoded:?}");
+        };
+        let active = state.active().unwrap();
+        assert_eq!(active.hash, vec![0xde, 0xad, 0xbe, 0xef]);
+        assert_eq!(state.firmware_version().unwrap().fwver(), 0x01040207);
+        assert_eq!(state.dev_status(), DevStatus::RunningOk);
+
+        let roundtrip = SmpResponse::ImageState(state.clone())
+            .encode(&SmpHeader::from_bytes(&request).unwrap())
+            .unwrap();
+        assert_eq!(
+            SmpResponse::decode(&roundtrip).unwrap().1,
+            SmpResponse::ImageState(state)
+        );
+    }
+
+    #[test]
+    fn image_versions_above_255() {
+        let request = SmpHeader::from_bytes(&hex("08 00 00 01 00 01 03 00  a0")).unwrap();
+        let slot = ImageSlot {
+            image: 0,
+            slot: 0,
+            version: "1.4.2.300".parse().unwrap(),
+            hash: vec![0xde, 0xad, 0xbe, 0xef],
+            bootable: true,
+            pending: false,
+            confirmed: true,
+            active: true,
+            permanent: false,
+        };
+        let state = ImageState { images: vec![slot] };
+        let response = SmpResponse::ImageState(state.clone())
+            .encode(&request)
+            .unwrap();
+        let SmpResponse::ImageState(decoded) = SmpResponse::decode(&response).unwrap().1 else {
+            panic!("expected image state");
+        };
+        assert_eq!(decoded, state);
+        let version = decoded.firmware_version().unwrap();
+        assert_eq!(version.build, 300);
+        assert_eq!(version.to_string(), "1.4.2+300");
+    }
+
+    #[test]
+    fn image_test_and_confirm() {
+        let hash = vec![0xde, 0xad, 0xbe, 0xef];
+        let request = hex("0a 00 00 14 00 01 04 00
+             a2 67 63 6f 6e 66 69 72 6d f4 64 68 61 73 68 44 de ad be ef");
+        let test = SmpRequest::ImageTest { hash: hash.clone() };
+        assert_eq!(test.encode(4).unwrap(), request);
+        assert_eq!(SmpRequest::decode(&request).unwrap().1, test);
+
+        let confirm = SmpRequest::ImageConfirm { hash: None };
+        let bytes = confirm.encode(5).unwrap();
+        assert_eq!(&bytes[8..], &hex("a1 67 63 6f 6e 66 69 72 6d f5"));
+        assert_eq!(SmpRequest::decode(&bytes).unwrap().1, confirm);
+    }
+
+    #[test]
+    fn image_upload() {
+        let image = [0x3d, 0xb8, 0xf3, 0x96, 0x00, 0x00];
+        let chunks = ImageUpload::chunks(&image, 4).unwrap();
+        assert_eq!(chunks.len(), 2);
+        assert_eq!(chunks[1].off, 4);
+        assert_eq!(chunks[1].len, None);
+
+        let request = hex("0a 00 00 15 00 01 00 01
+             a3 63 6c 65 6e 06 63 6f 66 66 00 64 64 61 74 61 44 3d b8 f3 96");
+        let upload = SmpRequest::ImageUpload(chunks[0].clone());
+        assert_eq!(upload.encode(0).unwrap(), request);
+        assert_eq!(SmpRequest::decode(&request).unwrap().1, upload);
+
+        let response = hex("0b 00 00 0b 00 01 00 01  bf 62 72 63 00 63 6f 66 66 04 ff");
+        assert_eq!(
+            SmpResponse::decode(&response).unwrap().1,
+            SmpResponse::ImageUpload { off: 4 }
+        );
+
+        assert_eq!(
+            ImageUpload::chunks(&image, 0),
+            Err(SmpError::InvalidChunkSize(0))
+        );
+        assert_eq!(
+            ImageUpload::chunks(&image, MAX_CHUNK_SIZE + 1),
+            Err(SmpError::InvalidChunkSize(MAX_CHUNK_SIZE + 1))
+        );
+        let image = vec![0xff; MAX_CHUNK_SIZE + 1];
+        let mut chunks = ImageUpload::chunks(&image, MAX_CHUNK_SIZE).unwrap();
+        chunks[0].image = Some(u32::MAX);
+        chunks[0].sha = Some(vec![0xff; 32]);
+        chunks[0].upgrade = Some(false);
+        let bytes = SmpRequest::ImageUpload(chunks[0].clone())
+            .encode(0)
+            .unwrap();
+        assert_eq!(
+            SmpRequest::decode(&bytes).unwrap().1,
+            SmpRequest::ImageUpload(chunks[0].clone())
+        );
+        chunks[0].data = vec![0xff; SMP_MAX_BODY_LEN];
+        assert!(matches!(
+            SmpRequest::ImageUpload(chunks[0].clone()).encode(0),
+            Err(SmpError::TooLarge(_))
+        ));
+    }
+
+    #[test]
+    fn errors() {
+        // SMP v1 rc 3 (MGMT_ERR_EINVAL)
+        let v1 = hex("0b 00 00 05 00 01 00 01  a1 62 72 63 03");
+        assert_eq!(
+            SmpResponse::decode(&v1).unwrap().1,
+            SmpResponse::Error { group: None, rc: 3 }
+        );
+        // SMP v2 image group error
+        let v2 = hex("0b 00 00 11 00 01 00 01  a1 63 65 72 72 a2 65 67 72 6f 75 70 01 62 72 63 02");
+        assert_eq!(
+            SmpResponse::decode(&v2).unwrap().1,
+            SmpResponse::Error {
+                group: Some(1),
+                rc: 2
+            }
+        );
+        let group = hex(
+            "0b 00 00 15 00 01 00 01  a1 63 65 72 72 a2 65 67 72 6f 75 70
+             1a 00 01 00 00 62 72 63 02",
+        );
+        assert_eq!(
+            SmpResponse::decode(&group),
+            Err(SmpError::InvalidBody("group out of range"))
+        );
+        assert_eq!(
+            SmpResponse::decode(&v1[..10]),
+            Err(SmpError::LengthMismatch { header: 5, body: 2 })
+        );
+        assert!(matches!(
+            SmpRequest::decode(&hex("0a 00 00 01 00 09 00 00  a0")),
+            Err(SmpError::Unsupported { group: 9, .. })
+        ));
+    }
+}
@@ -0,0 +1,330 @@
+use serde_json::{Map, Value};
+use std::fmt::Write;
+use std::path::Path;
+
+use crate::devices_connected::DevicesConnectedTypes;
+use crate::devs::hb::{DevStatus, DevType};
+use crate::labels::{Label, Labels};
+use crate::measurement::MeasurementType;
+use crate::settings::SettingsType;
+
+// TypeScript definitions for the API types, enabled with the "typescript" feature.
+//
+// Interfaces and type aliases are generated from the JSON Schema bundle. The enums with
+// numeric wire values become TypeScript enums with a label table per locale:
+//
+//   export enum DevType { BorderRouter = 0, HortiLed = 1, ... }
+//   export const DevTypeLabels: EnumLabels = { en: { 0: "BorderRouter", ... }, nb: { ... } };
+pub fn generate(labels: &Labels) -> String {
+    let mut out = String::new();
+    writeln!(
+        out,
+        "// Generated by horti-types {}, do not edit.\n",
+        env!("CARGO_PKG_VERSION")
+    )
+    .unwrap();
+    writeln!(
+        out,
+        "export const API_VERSION = {};\n",
+        Value::from(crate::api::API_VERSION)
+    )
+    .unwrap();
+    out.push_str(LABEL_HELPER);
+
+    let enums = wire_enums(labels);
+    for (name, values) in &enums {
+        write_enum(&mut out, name, values);
+    }
+    let bundle = crate::schema::bundle();
+    if let Some(Value::Object(defs)) = bundle.get("$defs") {
+        for (name, schema) in defs {
+            if !enums.iter().any(|(n, _)| n == name) {
+                write_definition(&mut out, name, schema);
+            }
+        }
+    }
+    out
+}
+
+pub fn write_file(path: impl AsRef<Path>) -> std::io::Result<()> {
+    std::fs::write(path, generate(&Labels::default()))
+}
+
+const LABEL_HELPER: &str = "export type EnumLabels = Record<string, Record<number, string>>;
+
+// Label in the locale, falls back to English and then to the number
+export function enumLabel(labels: EnumLabels, locale: string, value: number): string {
+  return labels[locale]?.[value] ?? labels[\"en\"]?.[value] ?? String(value);
+}
+
+";
+
+struct WireValue {
+    value: i64,
+    name: String,
+    labels: Vec<(String, String)>,
+}
+
+fn wire_values<T: Label>(
+    labels: &Labels,
+    values: impl Iterator<Item = (i64, String, T)>,
+) -> Vec<WireValue> {
+    values
+        .map(|(value, name, item)| WireValue {
+            value,
+            name,
+            labels: labels
+                .locales()
+                .iter()
+                .map(|l| (l.to_string(), labels.label(l, &item)))
+                .collect(),
+        })
+        .collect()
+}
+
+fn wire_enums(labels: &Labels) -> Vec<(String, Vec<WireValue>)> {
+    vec![
+        (
+            "DevType".to_string(),
+            wire_values(
+                labels,
+                (0..=u8::MAX)
+                    .map(DevType::from)
+                    .filter(|t| !matches!(t, DevType::Unknown(_)))
+                    .map(|t| (u8::from(t).into(), t.to_string(), t)),
+            ),
+        ),
+        (
+            "DevStatus".to_string(),
+            wire_values(
+                labels,
+                (0..=u8::MAX)
+                    .map(DevStatus::from)
+                    .filter(|s| !matches!(s, DevStatus::Unknown(_)))
+                    .map(|s| (u8::from(s).into(), s.to_string(), s)),
+            ),
+        ),
+        (
+            "MeasurementType".to_string(),
+            wire_values(
+                labels,
+                (0..=u8::MAX)
+                    .map(MeasurementType::from)
+                    .filter(|t| !matches!(t, MeasurementType::Other(_)))
+                    .map(|t| (Into::<i32>::into(t.clone()).into(), t.to_string(), t)),
+            ),
+        ),
+        (
+            "DevicesConnectedTypes".to_string(),
+            wire_values(
+                labels,
+                (0..=u16::from(u8::MAX))
+                    .map(DevicesConnectedTypes::from)
+                    .filter(|t| !matches!(t, DevicesConnectedTypes::Other(_)))
+                    .map(|t| (u16::from(t).into(), format!("{t:?}"), t)),
+            ),
+        ),
+        (
+            "SettingsType".to_string(),
+            wire_values(
+                labels,
+                (0..=i32::from(u8::MAX))
+                    .map(SettingsType::from)
+                    .filter(|t| !matches!(t, SettingsType::Unknown(_)))
+                    .map(|t| (i32::from(t).into(), format!("{t:?}"), t)),
+            ),
+        ),
+    ]
+}
+
+fn write_enum(out: &mut String, name: &str, values: &[WireValue]) {
+    writeln!(out, "export enum {name} {{").unwrap();
+    for v in values {
+        writeln!(out, "  {} = {},", v.name, v.value).unwrap();
+    }
+    writeln!(out, "}}\n").unwrap();
+
+    writeln!(out, "export const {name}Labels: EnumLabels = {{").unwrap();
+    let locales = values.first().map(|v| v.labels.len()).unwrap_or(0);
+    for n in 0..locales {
+        writeln!(out, "  {}: {{", values[0].labels[n].0).unwrap();
+        for v in values {
+            writeln!(
+                out,
+                "    {}: {},",
+                v.value,
+                Value::from(v.labels[n].1.as_str())
+            )
+            .unwrap();
+        }
+        writeln!(out, "  }},").unwrap();
+    }
+    writeln!(out, "}};\n").unwrap();
+}
+
+fn write_definition(out: &mut String, name: &str, schema: &Value) {
+    if let Some(description) = schema.get("description").and_then(Value::as_str) {
+        writeln!(out, "/** {description} */").unwrap();
+    }
+    match schema.get("properties") {
+        Some(Value::Object(properties)) if schema.get("type") == Some(&"object".into()) => {
+            writeln!(
+                out,
+                "export interface {name} {}\n",
+                object(schema, properties, 0)
+            )
+            .unwrap();
+        }
+        _ => writeln!(out, "export type {name} = {};\n", ts_type(schema, 0)).unwrap(),
+    }
+}
+
+fn object(schema: &Value, properties: &Map<String, Value>, indent: usize) -> String {
+    let required: Vec<&str> = match schema.get("required") {
+        Some(Value::Array(r)) => r.iter().filter_map(Value::as_str).collect(),
+        _ => vec![],
+    };
+    let pad = "  ".repeat(indent + 1);
+    let mut ret = "{\n".to_string();
+    for (key, property) in properties {
+        let optional = if required.contains(&key.as_str()) {
+            ""
+        } else {
+            "?"
+        };
+        writeln!(
+            ret,
+            "{pad}{}{optional}: {};",
+            property_name(key),
+            ts_type(property, indent + 1)
+        )
+        .unwrap();
+    }
+    ret + &"  ".repeat(indent) + "}"
+}
+
+fn property_name(key: &str) -> String {
+    let identifier = key.chars().enumerate().all(|(n, c)| {
+        c == '_' || c == '$' || c.is_ascii_alphabetic() || (n > 0 && c.is_ascii_digit())
+    });
+    if identifier && !key.is_empty() {
+        key.to_string()
+    } else {
+        Value::from(key).to_string()
+    }
+}
+
+fn ts_type(schema: &Value, indent: usize) -> String {
+    let Value::Object(map) = schema else {
+        return "unknown".to_string();
+    };
+    if let Some(Value::String(reference)) = map.get("$ref") {
+        return reference
+            .rsplit('/')
+            .next()
+            .unwrap_or("unknown")
+            .to_string();
+    }
+    if let Some(value) = map.get("const") {
+        return value.to_string();
+    }
+    if let Some(Value::Array(values)) = map.get("enum") {
+        return union(values.iter().map(Value::to_string));
+    }
+    for key in ["oneOf", "anyOf"] {
+        if let Some(Value::Array(variants)) = map.get(key) {
+            return union(variants.iter().map(|v| ts_type(v, indent)));
+        }
+    }
+    match map.get("type") {
+        Some(Value::String(t)) => type_name(t, map, indent),
+        Some(Value::Array(types)) => union(
+            types
+                .iter()
+                .filter_map(Value::as_str)
+                .map(|t| type_name(t, map, indent)),
+        ),
+        _ => "unknown".to_string(),
+    }
+}
+
+fn type_name(t: &str, map: &Map<String, Value>, indent: usize) -> String {
+    match t {
+        "string" => "string".to_string(),
+        "integer" | "number" => "number".to_string(),
+        "boolean" => "boolean".to_string(),
+        "null" => "null".to_string(),
+        "array" => match (map.get("prefixItems"), map.get("items")) {
+            (Some(Value::Array(items)), _) => {
+                let items: Vec<String> = items.iter().map(|i| ts_type(i, indent)).collect();
+                format!("[{}]", items.join(", "))
+            }
+            (_, Some(items)) => {
+                let item = ts_type(items, indent);
+                if item.contains(' ') {
+                    format!("({item})[]")
+                } else {
+                    format!("{item}[]")
+                }
+            }
+            _ => "unknown[]".to_string(),
+        },
+        "object" => match (map.get("properties"), map.get("additionalProperties")) {
+            (Some(Value::Object(properties)), _) => {
+                object(&Value::Object(map.clone()), properties, indent)
+            }
+            (_, Some(values @ Value::Object(_))) => {
+                format!("Record<string, {}>", ts_type(values, indent))
+            }
+            _ => "Record<string, unknown>".to_string(),
+        },
+        _ => "unknown".to_string(),
+    }
+}
+
+fn union(types: impl Iterator<Item = String>) -> String {
+    let mut ret: Vec<String> = Vec::new();
+    for t in types {
+        if !ret.contains(&t) {
+            ret.push(t);
+        }
+    }
+    match ret.len() {
+        0 => "never".to_string(),
+        _ => ret.join(" | "),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn enums_with_labels() {
+        let ts = generate(&Labels::default());
+        assert!(ts.contains("export enum DevType {\n  BorderRouter = 0,\n  HortiLed = 1,\n"));
+        assert!(ts.contains("  AmbientTemperature = 13,\n"));
+        assert!(ts.contains("  LightEffect = 15,\n"));
+        assert!(ts.contains("  FlickeringLed = 5,\n"));
+        assert!(ts.contains("    1: \"Hagebruk: LED-panel\",\n"));
+        assert!(ts.contains(
+            "export const DevStatusLabels: EnumLabels = {\n  en: {\n    1: \"Error\",\n"
+        ));
+    }
+
+    #[test]
+    fn api_types() {
+        let ts = generate(&Labels::default());
+        assert!(ts.contains(
+            "export interface HeartBeat {\n  firmware: number;\n  id: string;\n  infobits: number;\n  kind: \"HeartBeat\";\n"
+        ));
+        assert!(ts.contains("  status: DevStatus;\n  type: DevType;\n"));
+        assert!(ts.contains(
+            "export interface JsonMessage {\n  apiVersion: string;\n  data?: ItemTypes | null;\n"
+        ));
+        assert!(ts.contains("  tlv: Tlvarray;\n"));
+        assert!(ts.contains("export type Tlvarray = string | null;\n"));
+        assert!(ts.contains("export type DeviceCommand = {\n"));
+        assert!(!ts.contains("unknown;"), "{ts}");
+    }
+}